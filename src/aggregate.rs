@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use chrono::{DateTime, Days, Local, NaiveDate}; // 按天汇总
+use foreground_watcher_types::FocusEvent; // 今天还没有结束的区间
+use log::warn; // 缓存无法读写时记录日志，不影响统计结果
+use serde::{Deserialize, Serialize}; // 缓存文件的格式
+
+use crate::archive; // 事件记录可能已经压缩归档
+use crate::clock::Clock; // 判断哪些天的记录已经不会再变
+use crate::history::{self, Entry}; // 读取事件记录
+
+// 缓存文件名，放在缓存目录中
+const FILE: &str = "daily.json";
+
+// 今天的实时汇总保存的文件名，放在缓存目录中
+const TODAY_FILE: &str = "today.json";
+
+// 最多缓存的天数，超出时丢弃最早的几天
+const MAX_DAYS: usize = 400;
+
+// 一个应用的一个可执行文件在一天中的前台时长
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppTotal {
+    pub app: String,             // 应用标识，同 AppIdentity 的 id
+    pub exe: Option<String>,     // 可执行文件路径，同一个应用的不同路径分开汇总
+    pub product: Option<String>, // 第一次出现时的产品名称
+    pub duration_ms: i64,        // 在前台的时长
+}
+
+// 一个事件记录文件的大小和修改时间，文件变化后缓存的汇总作废
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    bytes: u64,
+    modified_ms: u64,
+}
+
+// 缓存中的一天
+#[derive(Serialize, Deserialize)]
+struct CachedDay {
+    stamps: [Option<Stamp>; 3], // 前一天、当天、后一天的事件记录文件，当天的区间会受到这三个文件的影响
+    apps: Vec<AppTotal>,        // 按应用标识和可执行文件路径排序
+}
+
+// 按天汇总的各应用前台时长，保存在缓存目录中：统计多天时只重新读取事件记录有变化的那几天，其余直接使用缓存
+// 今天使用监视程序保存的实时汇总，事件记录文件在那之后没有变化时直接使用；缓存损坏或删除后自动重建
+pub struct DailyTotals {
+    path: PathBuf,
+    today: PathBuf, // 监视程序保存的今天的实时汇总
+    days: BTreeMap<NaiveDate, CachedDay>,
+    changed: bool, // 有没有需要写回的变化
+}
+
+impl DailyTotals {
+    // 读取缓存文件，不存在或无法解析时从空的缓存开始
+    pub fn open(cache: &Path) -> DailyTotals {
+        let path = cache.join(FILE);
+        let days = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                warn!("按天汇总的缓存 {} 无法解析，将重新汇总: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        DailyTotals { path, today: cache.join(TODAY_FILE), days, changed: false }
+    }
+
+    // 某一天各应用的前台时长，今天只统计到现在
+    // 过去的日子按三个文件的大小和修改时间判断缓存是否有效，今天的区间还在增加，优先使用监视程序保存的实时汇总
+    pub fn day(&mut self, events: &Path, date: NaiveDate, clock: &dyn Clock) -> std::io::Result<Vec<AppTotal>> {
+        let settled = date < clock.today();
+        let stamps = stamps(events, date);
+        if let Some(cached) = self.days.get(&date).filter(|cached| settled && cached.stamps == stamps) {
+            return Ok(cached.apps.clone());
+        }
+        if date == clock.today() {
+            if let Some(apps) = saved_today(&self.today, date, &stamps, clock.now()) {
+                return Ok(apps);
+            }
+        }
+        let apps = summarize(events, date, clock)?;
+        if settled {
+            self.days.insert(date, CachedDay { stamps, apps: apps.clone() });
+            self.changed = true;
+        }
+        Ok(apps)
+    }
+
+    // 把变化写回缓存文件，只保留最近的 MAX_DAYS 天；写入失败时只记录日志，下次重新汇总
+    pub fn save(&mut self) {
+        if !self.changed {
+            return;
+        }
+        while self.days.len() > MAX_DAYS {
+            self.days.pop_first();
+        }
+        let temp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        let written = serde_json::to_string(&self.days)
+            .map_err(std::io::Error::other)
+            .and_then(|text| std::fs::write(&temp, text))
+            .and_then(|()| std::fs::rename(&temp, &self.path));
+        match written {
+            Ok(()) => self.changed = false,
+            Err(e) => warn!("写入按天汇总的缓存 {} 失败: {}", self.path.display(), e),
+        }
+    }
+}
+
+// 一段日期中每天各应用的前台时长，使用并更新缓存
+pub fn range(cache: &Path, events: &Path, from: NaiveDate, to: NaiveDate, clock: &dyn Clock) -> std::io::Result<Vec<(NaiveDate, Vec<AppTotal>)>> {
+    let mut totals = DailyTotals::open(cache);
+    let mut days = Vec::new();
+    let mut date = from;
+    while date <= to {
+        days.push((date, totals.day(events, date, clock)?));
+        date = date + Days::new(1);
+    }
+    totals.save();
+    Ok(days)
+}
+
+// 读取事件记录，汇总某一天各应用的前台时长
+fn summarize(events: &Path, date: NaiveDate, clock: &dyn Clock) -> std::io::Result<Vec<AppTotal>> {
+    let start = history::day_start(date);
+    let end = history::day_start(date + Days::new(1)).min(clock.now());
+    let intervals = if start < end { history::intervals(events, start, end)? } else { Vec::new() };
+    let mut apps: BTreeMap<(String, Option<String>), AppTotal> = BTreeMap::new();
+    for interval in &intervals {
+        let identity = interval.event.identity();
+        let app = apps.entry((identity.id.clone(), identity.exe.clone())).or_insert_with(|| AppTotal {
+            app: identity.id,
+            exe: identity.exe,
+            product: interval.event.product.clone(),
+            duration_ms: 0,
+        });
+        app.duration_ms += interval.duration_ms();
+    }
+    Ok(apps.into_values().collect())
+}
+
+// 某一天的汇总所依赖的前一天、当天、后一天事件记录文件的大小和修改时间
+fn stamps(events: &Path, date: NaiveDate) -> [Option<Stamp>; 3] {
+    [date - Days::new(1), date, date + Days::new(1)].map(|d| stamp(&history::day_path(events, d)))
+}
+
+// 删除按天汇总和今天的实时汇总，它们含有应用标识和可执行文件路径，匿名化事件记录后需要一并删除，下次统计时自动重建
+pub fn clear(cache: &Path) -> std::io::Result<()> {
+    for name in [FILE, TODAY_FILE] {
+        match std::fs::remove_file(cache.join(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// 正在汇总的一天：已经结束的区间按应用累计，最后一个区间还没有结束
+#[derive(Clone)]
+struct Running {
+    day: NaiveDate,
+    apps: BTreeMap<(String, Option<String>), AppTotal>, // 按应用标识和可执行文件路径累计
+    open: Option<FocusEvent>,                            // 还没有结束的区间从这条记录开始，没有前台程序时为空
+}
+
+impl Running {
+    // 处理一条记录：上一个区间在这条记录的时间结束
+    fn apply(&mut self, entry: &Entry) {
+        self.close(entry.time());
+        self.open = match entry {
+            Entry::Focus(event) => Some(event.as_ref().clone()),
+            Entry::Stop(_) => None,
+        };
+    }
+
+    // 当前区间累计到 until，跨过零点时在零点切开，新的一天从零开始累计
+    fn close(&mut self, until: DateTime<Local>) {
+        while until.date_naive() > self.day {
+            let next = self.day + Days::new(1);
+            self.add(history::day_start(next));
+            self.day = next;
+            self.apps.clear();
+        }
+        self.add(until);
+    }
+
+    // 把当前区间从开始（不早于当天零点）到 until 的部分计入所属的应用
+    fn add(&mut self, until: DateTime<Local>) {
+        let Some(open) = &self.open else {
+            return;
+        };
+        let ms = (until - open.time.max(history::day_start(self.day))).num_milliseconds();
+        if ms <= 0 {
+            return;
+        }
+        let identity = open.identity();
+        let app = self.apps.entry((identity.id.clone(), identity.exe.clone())).or_insert_with(|| AppTotal {
+            app: identity.id,
+            exe: identity.exe,
+            product: open.product.clone(),
+            duration_ms: 0,
+        });
+        app.duration_ms += ms;
+    }
+
+    // 到 now 为止的汇总：当前区间在 now 或状态心跳的合并窗口结束处中断
+    fn finish(mut self, pulse_until: Option<DateTime<Local>>, now: DateTime<Local>) -> Vec<AppTotal> {
+        self.close(pulse_until.map_or(now, |until| until.min(now)));
+        self.open = None;
+        self.close(now);
+        self.apps.into_values().collect()
+    }
+}
+
+// 保存到缓存目录的今天的实时汇总，事件记录文件的大小和修改时间与保存时相同才使用
+#[derive(Serialize, Deserialize)]
+struct SavedToday {
+    day: NaiveDate,
+    stamps: [Option<Stamp>; 3],             // 前一天、当天、后一天的事件记录文件
+    apps: Vec<AppTotal>,                    // 已经结束的区间
+    open: Option<FocusEvent>,               // 还没有结束的区间的开始
+    pulse_until: Option<DateTime<Local>>,   // 还没有结束的区间最晚在这时中断（状态心跳的合并窗口）
+}
+
+// 读取保存的今天的实时汇总，不存在、不是这一天或事件记录文件在保存后有变化时返回None
+fn saved_today(path: &Path, date: NaiveDate, stamps: &[Option<Stamp>; 3], now: DateTime<Local>) -> Option<Vec<AppTotal>> {
+    let saved: SavedToday = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    if saved.day != date || saved.stamps != *stamps {
+        return None;
+    }
+    let apps = saved.apps.into_iter().map(|app| ((app.app.clone(), app.exe.clone()), app)).collect();
+    Some(Running { day: saved.day, apps, open: saved.open }.finish(saved.pulse_until, now))
+}
+
+// 今天各应用前台时长的实时汇总：事件记录输出目标每写入一行就交给它，浮窗和本地HTTP接口的 /stats 直接读取，
+// 启动时读取一次昨天和今天的事件记录，之后不再重新读取；写入后连同事件记录文件的大小和修改时间保存到缓存目录，
+// 导出等子命令统计今天时，文件在那之后没有变化就直接使用。与按天读取事件记录使用相同的解析规则，结果相同
+pub struct Live {
+    events: PathBuf,           // 事件记录目录
+    path: PathBuf,             // 缓存目录中保存汇总的文件
+    running: Running,          // 正在汇总的一天
+    parser: history::Parser,   // 正在写入的事件记录文件的解析状态
+    last: Vec<Entry>,          // 最后一条解析出的记录，判断状态心跳能否合并
+    file_day: NaiveDate,       // 正在写入的事件记录文件的日期
+}
+
+impl Live {
+    // 从昨天和今天的事件记录开始汇总，读取失败时记录日志并从零开始
+    pub fn load(events: &Path, cache: &Path, clock: &dyn Clock) -> Live {
+        let today = clock.today();
+        let mut live = Live {
+            events: events.to_path_buf(),
+            path: cache.join(TODAY_FILE),
+            running: Running { day: today, apps: BTreeMap::new(), open: None },
+            parser: history::Parser::default(),
+            last: Vec::new(),
+            file_day: today,
+        };
+        match history::read_day(events, today - Days::new(1)) {
+            Ok(entries) => entries.iter().for_each(|entry| live.running.apply(entry)), // 跨过零点的区间从零点开始计算
+            Err(e) => warn!("读取昨天的事件记录失败，今天的汇总可能少算零点之后的一段: {}", e),
+        }
+        match archive::read_to_string(&history::day_path(events, today)) {
+            Ok(text) => text.iter().flat_map(|text| text.lines()).for_each(|line| live.parse(line)),
+            Err(e) => warn!("读取今天的事件记录失败，今天的汇总从零开始: {}", e),
+        }
+        live
+    }
+
+    // 事件记录输出目标写入了一行，time 为记录的时间，决定它写入哪一天的文件
+    pub fn push(&mut self, time: DateTime<Local>, line: &str) {
+        let date = time.date_naive();
+        if date > self.file_day {
+            // 换到了新的文件，与按天读取一样，上一个文件最后的状态心跳在合并窗口结束处中断
+            let start = self.last.len();
+            std::mem::take(&mut self.parser).finish(&mut self.last);
+            self.apply_from(start);
+            self.last.clear();
+            self.file_day = date;
+        }
+        self.parse(line);
+    }
+
+    // 到 now 为止今天各应用的前台时长
+    pub fn totals(&self, now: DateTime<Local>) -> Vec<AppTotal> {
+        self.running.clone().finish(self.parser.pulse_until(), now)
+    }
+
+    // 保存到缓存目录，写入失败时只记录日志，子命令会改为读取事件记录
+    pub fn save(&self) {
+        let day = self.running.day;
+        let saved = SavedToday {
+            day,
+            stamps: stamps(&self.events, day),
+            apps: self.running.apps.values().cloned().collect(),
+            open: self.running.open.clone(),
+            pulse_until: self.parser.pulse_until(),
+        };
+        let temp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        let written = serde_json::to_string(&saved)
+            .map_err(std::io::Error::other)
+            .and_then(|text| std::fs::write(&temp, text))
+            .and_then(|()| std::fs::rename(&temp, &self.path));
+        if let Err(e) = written {
+            warn!("保存今天的汇总 {} 失败: {}", self.path.display(), e);
+        }
+    }
+
+    // 解析一行并处理得到的记录，只保留最后一条用于合并状态心跳
+    fn parse(&mut self, line: &str) {
+        let start = self.last.len();
+        let _ = self.parser.line(line, &mut self.last); // 补充信息不影响时长
+        self.apply_from(start);
+        let keep = self.last.len().saturating_sub(1);
+        self.last.drain(..keep);
+    }
+
+    fn apply_from(&mut self, start: usize) {
+        for entry in &self.last[start..] {
+            self.running.apply(entry);
+        }
+    }
+}
+
+// 事件记录文件的大小和修改时间，归档和原文件同时存在时为大小之和、最晚的修改时间，文件不存在时为None
+fn stamp(path: &Path) -> Option<Stamp> {
+    let mut stamp: Option<Stamp> = None;
+    for file in archive::existing(path) {
+        let metadata = std::fs::metadata(file).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+        let last = stamp.unwrap_or(Stamp { bytes: 0, modified_ms: 0 });
+        stamp = Some(Stamp { bytes: last.bytes + metadata.len(), modified_ms: last.modified_ms.max(modified) });
+    }
+    stamp
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use foreground_watcher_types::{FocusEvent, PulseRecord};
+
+    use super::*;
+    use crate::clock::FixedClock;
+
+    // 今天的汇总截止到时间来源给出的“现在”，最后一个窗口只统计到那时
+    #[test]
+    fn summarize_stops_at_clock_now() {
+        let events = std::env::temp_dir().join(format!("foreground-watcher-summarize-{}", std::process::id()));
+        std::fs::create_dir_all(&events).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let at = |hour, minute| Local.from_local_datetime(&date.and_hms_opt(hour, minute, 0).unwrap()).unwrap();
+        let lines: Vec<String> = [(at(9, 0), r"c:\a.exe"), (at(10, 0), r"c:\b.exe")]
+            .into_iter()
+            .map(|(time, exe)| serde_json::to_string(&FocusEvent { exe: Some(exe.to_string()), ..FocusEvent::bare(time, 1) }).unwrap())
+            .collect();
+        std::fs::write(history::day_path(&events, date), lines.join("\n") + "\n").unwrap();
+
+        let apps = summarize(&events, date, &FixedClock(at(10, 30))).unwrap();
+        std::fs::remove_dir_all(&events).unwrap();
+        let durations: Vec<(&str, i64)> = apps.iter().map(|app| (app.app.as_str(), app.duration_ms)).collect();
+        assert_eq!(durations, [(r"c:\a.exe", 3_600_000), (r"c:\b.exe", 1_800_000)]);
+    }
+
+    // 实时汇总与读取事件记录得到的结果相同，保存后子命令直接使用保存的结果
+    #[test]
+    fn live_matches_summarize() {
+        let dir = std::env::temp_dir().join(format!("foreground-watcher-live-{}", std::process::id()));
+        let (events, cache) = (dir.join("events"), dir.join("cache"));
+        std::fs::create_dir_all(&events).unwrap();
+        std::fs::create_dir_all(&cache).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let at = |hour, minute, second| Local.from_local_datetime(&date.and_hms_opt(hour, minute, second).unwrap()).unwrap();
+        let event = |time, exe: &str| FocusEvent { exe: Some(exe.to_string()), ..FocusEvent::bare(time, 1) };
+        let pulse = |time, exe| serde_json::to_string(&PulseRecord { state: event(time, exe), merge_ms: 15_000 }).unwrap();
+        let lines = [
+            serde_json::to_string(&event(at(9, 0, 0), r"c:\a.exe")).unwrap(),
+            pulse(at(9, 30, 0), r"c:\b.exe"),
+            pulse(at(9, 30, 10), r"c:\b.exe"), // 与上一条合并，状态心跳在 9:30:25 中断
+            serde_json::to_string(&event(at(9, 45, 0), r"c:\a.exe")).unwrap(),
+        ];
+        let clock = FixedClock(at(10, 30, 0));
+
+        let mut live = Live::load(&events, &cache, &clock);
+        std::fs::write(history::day_path(&events, date), lines.join("\n") + "\n").unwrap();
+        for line in &lines {
+            live.push(clock.0, line);
+        }
+        live.save();
+        let durations = |apps: Vec<AppTotal>| apps.into_iter().map(|app| (app.app, app.duration_ms)).collect::<Vec<_>>();
+        let expected = vec![(r"c:\a.exe".to_string(), 4_500_000), (r"c:\b.exe".to_string(), 25_000)];
+        assert_eq!(durations(live.totals(clock.0)), expected);
+        assert_eq!(durations(summarize(&events, date, &clock).unwrap()), expected);
+        assert_eq!(durations(saved_today(&cache.join(TODAY_FILE), date, &stamps(&events, date), clock.0).unwrap()), expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local}; // 告警的时间
+use log::{info, warn}; // 告警和解除时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 从配置文件读取，发送给 webhook 的内容
+use windows::Win32::System::SystemInformation::GetTickCount; // 与最后一次输入的时间比较
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetLastInputInfo, // 最后一次键盘鼠标输入的时间
+    LASTINPUTINFO,
+};
+
+use crate::notify; // 系统通知
+use crate::sink::Totals; // 输出目标的写入次数
+use crate::trigger::Action; // 与触发规则共用的 webhook 和程序动作
+
+// 检查告警条件的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// 最后一次输入在这么久以内视为正在使用电脑
+const ACTIVE_INPUT: Duration = Duration::from_secs(60);
+
+// [alarm] 健康告警的设置，各项条件默认都不开启
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct AlarmConfig {
+    pub no_events_minutes: u64,  // 正在使用电脑却连续这么多分钟没有成功取得前台窗口时告警，0表示不检查
+    pub sink_error_percent: u64, // 统计窗口内写入失败的比例超过这个百分比时告警，0表示不检查
+    pub queue_drops: bool,       // 统计窗口内有输出目标处理不过来而丢弃记录时告警
+    pub window_minutes: u64,     // 统计写入失败和丢弃的窗口（分钟）
+    pub toast: bool,             // 是否弹出系统通知
+    pub webhook: Option<String>, // 以 POST 发送JSON的地址，只支持 http://
+    pub command: Vec<String>,    // 执行的程序及其参数
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        AlarmConfig {
+            no_events_minutes: 0,
+            sink_error_percent: 0,
+            queue_drops: false,
+            window_minutes: 10,
+            toast: true,
+            webhook: None,
+            command: Vec::new(),
+        }
+    }
+}
+
+// 告警的条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Condition {
+    NoEvents,   // 正在使用电脑却没有取得前台窗口
+    SinkErrors, // 写入失败过多
+    QueueDrops, // 丢弃了记录
+}
+
+impl Condition {
+    // 名称，用于 webhook 的 alarm 和环境变量 FW_ALARM
+    fn name(self) -> &'static str {
+        match self {
+            Condition::NoEvents => "no_events",
+            Condition::SinkErrors => "sink_errors",
+            Condition::QueueDrops => "queue_drops",
+        }
+    }
+}
+
+// 发送给 webhook 的内容
+#[derive(Serialize)]
+struct AlarmPayload<'a> {
+    kind: &'static str,    // 固定为 alarm
+    alarm: &'static str,   // 条件的名称
+    time: DateTime<Local>, // 告警的时间
+    message: &'a str,      // 说明
+}
+
+// 监视程序自身的健康告警：记录中断、写入失败、丢弃记录时当天就能发现，而不是到月底统计时才发现
+// 每个条件成立时只告警一次，恢复正常后再次成立时才会再告警
+pub struct Alarms {
+    config: AlarmConfig,
+    action: Action,
+    last_seen: Instant,        // 最近一次成功取得前台窗口的时间
+    last_check: Instant,       // 上次检查的时间
+    window_start: Instant,     // 当前统计窗口的开始时间
+    window_totals: Totals,     // 统计窗口开始时的写入次数
+    active: BTreeSet<Condition>, // 正在告警的条件
+}
+
+impl Alarms {
+    // 没有开启任何条件时返回None
+    pub fn new(config: &AlarmConfig, totals: Totals) -> Option<Alarms> {
+        if config.no_events_minutes == 0 && config.sink_error_percent == 0 && !config.queue_drops {
+            return None;
+        }
+        let now = Instant::now();
+        Some(Alarms {
+            config: config.clone(),
+            action: Action { name: "健康告警".to_string(), webhook: config.webhook.clone(), command: config.command.clone() },
+            last_seen: now,
+            last_check: now,
+            window_start: now,
+            window_totals: totals,
+            active: BTreeSet::new(),
+        })
+    }
+
+    // 主循环成功取得了前台窗口，不论有没有切换；一直停留在同一个窗口时也说明监视仍在进行
+    pub fn observed(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    // 到了检查间隔时检查各个条件，totals 为输出目标当前的写入次数
+    pub fn check(&mut self, totals: Totals, now: DateTime<Local>) {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        if self.config.no_events_minutes > 0 {
+            let quiet = self.last_seen.elapsed();
+            let raised = quiet >= Duration::from_secs(self.config.no_events_minutes * 60) && idle().is_some_and(|idle| idle < ACTIVE_INPUT);
+            let message = format!("正在使用电脑，但已经 {} 分钟没有取得前台窗口，监视可能已经失效", quiet.as_secs() / 60);
+            self.update(Condition::NoEvents, raised, &message, now);
+        }
+
+        if self.window_start.elapsed() >= Duration::from_secs(self.config.window_minutes.max(1) * 60) {
+            let written = totals.written - self.window_totals.written;
+            let failed = totals.failed - self.window_totals.failed;
+            let dropped = totals.dropped - self.window_totals.dropped;
+            if self.config.sink_error_percent > 0 {
+                let raised = failed > 0 && failed * 100 > self.config.sink_error_percent * (written + failed);
+                let message = format!("最近 {} 分钟内写入失败 {} 次，成功 {} 次", self.config.window_minutes, failed, written);
+                self.update(Condition::SinkErrors, raised, &message, now);
+            }
+            if self.config.queue_drops {
+                let message = format!("最近 {} 分钟内输出目标处理不过来，丢弃了 {} 条记录", self.config.window_minutes, dropped);
+                self.update(Condition::QueueDrops, dropped > 0, &message, now);
+            }
+            self.window_start = Instant::now();
+            self.window_totals = totals;
+        }
+    }
+
+    // 条件新成立时告警，恢复正常时记录日志
+    fn update(&mut self, condition: Condition, raised: bool, message: &str, now: DateTime<Local>) {
+        if !raised {
+            if self.active.remove(&condition) {
+                info!("{} | 告警解除: {}", now.format("%Y-%m-%d %H:%M:%S"), condition.name());
+            }
+            return;
+        }
+        if !self.active.insert(condition) {
+            return; // 已经告警过
+        }
+        warn!("{} | 告警 {}: {}", now.format("%Y-%m-%d %H:%M:%S"), condition.name(), message);
+        if self.config.toast {
+            notify::toast("ForegroundWatcher 告警", message);
+        }
+        if self.action.webhook.is_none() && self.action.command.is_empty() {
+            return;
+        }
+        let payload = AlarmPayload { kind: "alarm", alarm: condition.name(), time: now, message };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => return warn!("序列化告警失败: {}", e),
+        };
+        let env = vec![("FW_ALARM", condition.name().to_string()), ("FW_TIME", now.to_rfc3339()), ("FW_MESSAGE", message.to_string())];
+        if let Err(e) = self.action.run(body, env) {
+            warn!("执行告警动作失败: {}", e);
+        }
+    }
+}
+
+// 距最后一次键盘鼠标输入的时间，查询失败时为None
+fn idle() -> Option<Duration> {
+    let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return None;
+        }
+        Some(Duration::from_millis(GetTickCount().wrapping_sub(info.dwTime) as u64))
+    }
+}
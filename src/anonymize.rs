@@ -0,0 +1,114 @@
+use std::hash::{BuildHasher, RandomState};
+use std::path::Path;
+
+use foreground_watcher_types::{FocusEvent, PulseRecord}; // 需要匿名化的记录
+use serde::Deserialize; // 区分记录的种类
+
+use crate::aggregate; // 缓存中按天汇总的时长含有应用标识，需要删除
+use crate::archive::{self, ArchiveConfig}; // 读写已经压缩归档的事件记录
+use crate::error::{Error, FileAction}; // 哪个文件读取或改写失败
+use crate::metadata::exe_name; // 可执行文件路径去掉目录
+use crate::private; // 隐私浏览窗口的标题本来就不含原标题
+
+// 只取出记录的种类，窗口切换记录没有 kind
+#[derive(Deserialize)]
+struct Kind {
+    kind: Option<String>,
+}
+
+// 匿名化的结果
+#[derive(Default)]
+pub struct Summary {
+    pub files: usize,   // 改写的文件数
+    pub records: usize, // 匿名化的记录数
+    pub dropped: usize, // 无法解析而删除的行（程序被结束时写到一半的行）
+}
+
+// 把记录中可以识别个人的内容替换掉
+struct Anonymizer {
+    keep_categories: bool, // 是否保留可执行文件名，分类按可执行文件名查找
+    hasher: RandomState,   // 每次执行随机生成密钥，散列值无法反推，也无法与其他次执行的结果对照
+}
+
+impl Anonymizer {
+    // 散列值，同一次执行中相同的内容得到相同的值，仍然可以按它汇总
+    fn hash(&self, prefix: &str, text: &str) -> String {
+        format!("{}-{:016x}", prefix, self.hasher.hash_one(text))
+    }
+
+    // 窗口标题替换为散列值，可执行文件和应用标识去掉目录（目录中通常包含用户名），环境变量删除
+    // 时间、进程ID、版本信息和演示状态保留，时长统计不受影响
+    fn event(&self, event: &mut FocusEvent) {
+        if let Some(title) = event.title.as_mut().filter(|t| *t != private::PRIVATE_TITLE) {
+            *title = self.hash("title", title);
+        }
+        event.exe = event.exe.as_deref().map(|exe| self.name(exe));
+        event.app = event.app.as_deref().map(|app| self.name(app));
+        event.env.clear();
+    }
+
+    // 路径只保留文件名；不保留分类时文件名也替换为散列值
+    fn name(&self, path: &str) -> String {
+        let name = exe_name(path).to_lowercase();
+        if self.keep_categories {
+            name
+        } else {
+            self.hash("app", &name)
+        }
+    }
+
+    // 匿名化一行记录，无法解析时返回None
+    fn line(&self, line: &str) -> Option<(String, bool)> {
+        let Kind { kind } = serde_json::from_str(line).ok()?;
+        match kind.as_deref() {
+            None => {
+                let mut event: FocusEvent = serde_json::from_str(line).ok()?;
+                self.event(&mut event);
+                Some((serde_json::to_string(&event).ok()?, true))
+            }
+            Some("pulse") => {
+                let mut record: PulseRecord = serde_json::from_str(line).ok()?;
+                self.event(&mut record.state);
+                Some((serde_json::to_string(&record).ok()?, true))
+            }
+            Some(_) => Some((line.to_string(), false)), // 心跳、会话标记等记录中没有可以识别个人的内容
+        }
+    }
+}
+
+// 就地改写事件记录目录中的所有文件（包括已经压缩归档的），不可恢复；缓存目录中由原记录汇总出的时长一并删除
+// keep_categories 为 false 时可执行文件名也替换为散列值，分类随之丢失
+// 运行日志不在处理范围内，其中仍有原始的窗口标题
+pub fn anonymize(dir: &Path, cache: &Path, keep_categories: bool, archive: &ArchiveConfig) -> Result<Summary, Error> {
+    aggregate::clear(cache).map_err(|source| Error::File { action: FileAction::ClearTotals, path: cache.to_path_buf(), source })?;
+    let anonymizer = Anonymizer { keep_categories, hasher: RandomState::new() };
+    let mut summary = Summary::default();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(summary); // 还没有事件记录
+    };
+    let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).filter(|p| is_journal(p)).collect();
+    paths.sort();
+    for path in paths {
+        let text = archive::read_file(&path).map_err(|source| Error::File { action: FileAction::Read, path: path.clone(), source })?;
+        let mut output = String::with_capacity(text.len());
+        for line in text.lines() {
+            match anonymizer.line(line) {
+                Some((line, anonymized)) => {
+                    output.push_str(&line);
+                    output.push('\n');
+                    summary.records += usize::from(anonymized);
+                }
+                None => summary.dropped += 1,
+            }
+        }
+        archive::replace_file(&path, &output, archive).map_err(|source| Error::File { action: FileAction::Rewrite, path: path.clone(), source })?;
+        summary.files += 1;
+    }
+    Ok(summary)
+}
+
+// 是否是事件记录文件：YYYY-MM-DD.jsonl，以及压缩归档后的 .gz / .zst
+fn is_journal(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    [".jsonl", ".jsonl.gz", ".jsonl.zst"].iter().any(|ext| name.ends_with(ext))
+}
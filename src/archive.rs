@@ -0,0 +1,238 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::os::windows::fs::OpenOptionsExt; // 独占打开锁文件
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{debug, error}; // 记录压缩结果
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+// 归档文件使用的压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None, // 不压缩，保留原始文件
+    Gzip,
+    Zstd,
+}
+
+// 配置文件中的 [archive] 部分
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub compression: Compression, // 压缩算法
+    pub level: u32,               // 压缩级别，gzip为0-9，zstd目前只支持1
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        ArchiveConfig { compression: Compression::Gzip, level: 6 }
+    }
+}
+
+impl ArchiveConfig {
+    // 压缩后文件追加的扩展名
+    fn extension(&self) -> Option<&'static str> {
+        match self.compression {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+        }
+    }
+}
+
+// 同一目录中的锁文件，压缩期间独占打开，另一个进程（如监视程序运行时执行的 maintain）要等它压缩完
+const LOCK_FILE: &str = "archive.lock";
+
+// 等待其他进程压缩完的最长时间
+const LOCK_WAIT: Duration = Duration::from_secs(30);
+
+// 进程内同一时间只压缩一个文件：启动和跨天时的后台归档与定期维护可能同时压缩同一个文件
+static COMPRESSING: Mutex<()> = Mutex::new(());
+
+// 临时文件名的序号，与进程ID一起保证不同线程、不同进程的临时文件不会重名
+static TEMP_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// 需要压缩归档的文件扩展名：运行日志和事件记录
+const ARCHIVABLE: &[&str] = &["log", "jsonl"];
+
+// 文件是否是尚未压缩、可以归档的文件
+pub fn is_archivable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ARCHIVABLE.iter().any(|a| ext == *a))
+}
+
+// 压缩目录中所有已经不再写入的文件，keep 为正在写入或还会写入的文件名（今天和最新一天的文件），会被跳过
+pub fn compress_old(dir: &Path, keep: &[String], config: &ArchiveConfig) {
+    if config.compression == Compression::None {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return; // 目录尚未创建，没有需要压缩的文件
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_archivable(&path) && !keep.contains(&name) {
+            compress_logged(&path, config);
+        }
+    }
+}
+
+// 在后台线程中压缩目录里不再写入的文件，用于按天切换文件之后，避免阻塞写入
+pub fn compress_old_in_background(dir: PathBuf, keep: Vec<String>, config: ArchiveConfig) {
+    std::thread::spawn(move || compress_old(&dir, &keep, &config));
+}
+
+// 向已经不再写入的文件追加迟到的内容，与压缩归档互斥：不会写进正在被压缩、随后被删除的文件
+// 文件已经压缩归档时在旁边新建同名文件，读取时与归档合并，下次归档时并入归档
+pub fn append_late(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let _guard = COMPRESSING.lock().unwrap_or_else(|e| e.into_inner());
+    let _lock = lock_dir(path.parent().unwrap_or(Path::new(".")))?;
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(data)
+}
+
+// 压缩单个文件并记录结果
+fn compress_logged(path: &Path, config: &ArchiveConfig) {
+    match compress_file(path, config) {
+        Ok(target) => debug!("已压缩归档: {} -> {}", path.display(), target.display()),
+        Err(e) => error!("压缩归档 {} 失败: {}", path.display(), e),
+    }
+}
+
+// 把文件压缩为同目录下追加扩展名的新文件，成功后删除原文件
+// 先写入临时文件再改名，压缩中途被打断也不会留下损坏的归档
+// 这一天已经有归档时（归档之后又写入了迟到的记录），把归档的内容放在前面合并为一个归档，不会覆盖已有的归档
+pub fn compress_file(path: &Path, config: &ArchiveConfig) -> std::io::Result<PathBuf> {
+    let extension = config.extension().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Unsupported, "未启用压缩"))?;
+    let target = PathBuf::from(format!("{}.{}", path.display(), extension));
+    let _guard = COMPRESSING.lock().unwrap_or_else(|e| e.into_inner());
+    let _lock = lock_dir(path.parent().unwrap_or(Path::new(".")))?;
+    if !path.is_file() {
+        return Ok(target); // 等待期间已经被其他进程压缩
+    }
+    let temp = temp_path(&target);
+
+    let archived: Vec<PathBuf> = existing(path).into_iter().filter(|p| p != path).collect();
+    let mut earlier = String::new();
+    for archive in &archived {
+        earlier.push_str(&read_file(archive)?);
+    }
+    let mut source = BufReader::new(earlier.as_bytes().chain(File::open(path)?));
+    let mut output = BufWriter::new(File::create(&temp)?);
+    match config.compression {
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(&mut output, flate2::Compression::new(config.level));
+            std::io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Compression::Zstd => {
+            ruzstd::encoding::compress(&mut source, &mut output, ruzstd::encoding::CompressionLevel::Fastest);
+        }
+        Compression::None => unreachable!("未启用压缩时不会调用"),
+    }
+    output.flush()?;
+    drop(output); // 改名前先关闭文件
+
+    std::fs::rename(&temp, &target)?;
+    for archive in archived.iter().filter(|a| **a != target) {
+        std::fs::remove_file(archive)?; // 合并进了新的归档（压缩算法改过时扩展名不同）
+    }
+    std::fs::remove_file(path)?;
+    Ok(target)
+}
+
+// 独占打开目录中的锁文件，其他进程正在压缩时等待，超过 LOCK_WAIT 返回错误；进程退出（包括崩溃）时由系统关闭
+fn lock_dir(dir: &Path) -> std::io::Result<File> {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    let start = Instant::now();
+    loop {
+        match OpenOptions::new().create(true).truncate(false).write(true).share_mode(0).open(dir.join(LOCK_FILE)) {
+            Ok(file) => return Ok(file),
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) && start.elapsed() < LOCK_WAIT => {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => {
+                return Err(std::io::Error::other(format!("另一个进程正在压缩 {} 中的文件", dir.display())));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// 改名之前写入的临时文件，文件名中带有进程ID和序号，每次都不同
+fn temp_path(target: &Path) -> PathBuf {
+    let sequence = TEMP_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    PathBuf::from(format!("{}.{}-{}.tmp", target.display(), std::process::id(), sequence))
+}
+
+// 读取文件的全部内容，原文件已经压缩归档时读取同名的 .gz / .zst 文件，都不存在时返回None
+// 归档之后又写入了迟到的记录时，归档和原文件同时存在，先读归档再读原文件，合并为一份内容
+pub fn read_to_string(path: &Path) -> std::io::Result<Option<String>> {
+    let files = existing(path);
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let mut text = String::new();
+    for file in &files {
+        text.push_str(&read_file(file)?);
+    }
+    Ok(Some(text))
+}
+
+// 实际存在的文件：先是压缩归档后同名的 .gz / .zst 文件，最后是原文件
+pub fn existing(path: &Path) -> Vec<PathBuf> {
+    [PathBuf::from(format!("{}.gz", path.display())), PathBuf::from(format!("{}.zst", path.display())), path.to_path_buf()]
+        .into_iter()
+        .filter(|candidate| candidate.is_file())
+        .collect()
+}
+
+// 按扩展名（.gz / .zst，其他为不压缩）解压并读取已经打开的文件
+fn decode(file: File, path: &Path) -> std::io::Result<String> {
+    let mut text = String::new();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => {
+            flate2::read::GzDecoder::new(BufReader::new(file)).read_to_string(&mut text)?;
+        }
+        Some("zst") => {
+            let mut decoder = ruzstd::decoding::StreamingDecoder::new(BufReader::new(file)).map_err(std::io::Error::other)?;
+            decoder.read_to_string(&mut text)?;
+        }
+        _ => {
+            BufReader::new(file).read_to_string(&mut text)?;
+        }
+    }
+    Ok(text)
+}
+
+// 读取一个文件的全部内容，.gz / .zst 文件自动解压
+pub fn read_file(path: &Path) -> std::io::Result<String> {
+    decode(File::open(path)?, path)
+}
+
+// 用新的内容替换文件，.gz / .zst 文件按原来的算法重新压缩
+// 先写入临时文件再改名，中途被打断时原文件不受影响
+pub fn replace_file(path: &Path, text: &str, config: &ArchiveConfig) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let mut output = BufWriter::new(File::create(&temp)?);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => {
+            let level = if config.compression == Compression::Gzip { config.level } else { ArchiveConfig::default().level };
+            let mut encoder = flate2::write::GzEncoder::new(&mut output, flate2::Compression::new(level));
+            encoder.write_all(text.as_bytes())?;
+            encoder.finish()?;
+        }
+        Some("zst") => ruzstd::encoding::compress(text.as_bytes(), &mut output, ruzstd::encoding::CompressionLevel::Fastest),
+        _ => output.write_all(text.as_bytes())?,
+    }
+    output.flush()?;
+    drop(output); // 改名前先关闭文件
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
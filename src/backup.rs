@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize}; // 备份清单
+use zip::write::SimpleFileOptions; // 压缩包内文件的压缩方式
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::clock::Clock; // 记录备份时间
+use crate::error::{BackupError, Error}; // 备份文件无法恢复的原因
+use crate::paths::Paths; // 需要备份的文件位置
+
+// 备份清单文件名，恢复时用来确认压缩包确实是本程序生成的备份
+const MANIFEST: &str = "manifest.json";
+
+// 备份清单内容
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: String, // 生成备份的程序版本
+    created: String, // 备份时间
+    files: usize,    // 备份的文件数量（不含清单本身）
+}
+
+// 需要备份的配置类文件，对应压缩包根目录下的同名文件
+fn config_files(paths: &Paths) -> [(&'static str, &Path); 2] {
+    [("config.toml", &paths.config), ("apps.toml", &paths.apps)]
+}
+
+// 需要备份的数据子目录，对应压缩包内的同名目录
+fn data_dirs(paths: &Paths) -> [(&'static str, &Path); 2] {
+    [("events", &paths.events), ("cache", &paths.cache)]
+}
+
+// 把配置文件、应用信息、事件记录和缓存备份到一个zip文件
+// 先写入临时文件，全部完成后再改名，中途失败不会留下不完整的备份
+pub fn backup(paths: &Paths, to: &Path, clock: &dyn Clock) -> Result<usize, Error> {
+    let temp = PathBuf::from(format!("{}.tmp", to.display()));
+    let mut zip = ZipWriter::new(File::create(&temp)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut files = 0;
+
+    for (name, path) in config_files(paths) {
+        if path.exists() {
+            zip.start_file(name, options)?;
+            zip.write_all(&std::fs::read(path)?)?;
+            files += 1;
+        }
+    }
+    for (name, dir) in data_dirs(paths) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue; // 目录不存在，说明还没有数据
+        };
+        let mut entries: Vec<_> = entries.flatten().filter(|e| e.path().is_file()).collect();
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            if entry.path().extension().is_some_and(|ext| ext == "tmp" || ext == "lock") {
+                continue; // 正在压缩归档的临时文件和锁文件
+            }
+            let Some((path, data)) = snapshot(&entry.path())? else {
+                continue; // 文件在读取前被删除
+            };
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            zip.start_file(format!("{}/{}", name, file_name), options)?;
+            zip.write_all(&data)?;
+            files += 1;
+        }
+    }
+
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        created: clock.now().to_rfc3339(),
+        files,
+    };
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+
+    std::fs::rename(&temp, to)?;
+    Ok(files)
+}
+
+// 读取文件的一致快照，返回实际读取的文件路径和内容
+// 正在被监视程序追加写入的JSONL文件只取到最后一个完整行；
+// 文件在列出目录之后被归档压缩时，改为读取压缩后的文件；文件已不存在时返回None
+fn snapshot(path: &Path) -> std::io::Result<Option<(PathBuf, Vec<u8>)>> {
+    let candidates = [
+        path.to_path_buf(),
+        PathBuf::from(format!("{}.gz", path.display())),
+        PathBuf::from(format!("{}.zst", path.display())),
+    ];
+    for candidate in candidates {
+        let mut data = Vec::new();
+        match File::open(&candidate) {
+            Ok(mut file) => file.read_to_end(&mut data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if candidate.extension().is_some_and(|ext| ext == "jsonl") {
+            let complete = data.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+            data.truncate(complete); // 丢弃可能只写了一半的最后一行
+        }
+        return Ok(Some((candidate, data)));
+    }
+    Ok(None)
+}
+
+// 从备份恢复配置文件、应用信息、事件记录和缓存
+// 目标位置已有数据时，只有 force 为 true 才会覆盖同名文件
+pub fn restore(paths: &Paths, from: &Path, force: bool) -> Result<usize, Error> {
+    let mut zip = ZipArchive::new(File::open(from)?)?;
+    let manifest: Manifest = match zip.by_name(MANIFEST) {
+        Ok(mut file) => {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+            serde_json::from_str(&text)?
+        }
+        Err(_) => return Err(Error::Backup(BackupError::NotBackup(from.to_path_buf()))),
+    };
+
+    // 先确定每个文件的恢复位置，检查完全部文件再开始写入
+    let mut targets = Vec::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        let raw_name = file.name()?.to_string();
+        if file.is_dir() || raw_name == MANIFEST {
+            continue;
+        }
+        let name = file.enclosed_name().ok_or_else(|| Error::Backup(BackupError::UnsafePath(raw_name.clone())))?;
+        let target = target_path(paths, &name).ok_or_else(|| Error::Backup(BackupError::UnknownFile(raw_name.clone())))?;
+        if target.exists() && !force {
+            return Err(Error::Backup(BackupError::Exists(target)));
+        }
+        targets.push((i, target));
+    }
+    if targets.len() != manifest.files {
+        return Err(Error::Backup(BackupError::Incomplete { expected: manifest.files, found: targets.len() }));
+    }
+
+    for (i, target) in &targets {
+        let mut data = Vec::new();
+        zip.by_index(*i)?.read_to_end(&mut data)?;
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp = PathBuf::from(format!("{}.tmp", target.display()));
+        std::fs::write(&temp, &data)?; // 先写临时文件再改名，避免恢复中断时留下半个文件
+        std::fs::rename(&temp, target)?;
+    }
+    Ok(targets.len())
+}
+
+// 压缩包内的路径对应的恢复位置
+fn target_path(paths: &Paths, name: &Path) -> Option<PathBuf> {
+    if let Some((_, path)) = config_files(paths).iter().find(|(n, _)| name == Path::new(n)) {
+        return Some(path.to_path_buf());
+    }
+    let mut components = name.components();
+    let dir = components.next()?.as_os_str().to_str()?;
+    let file_name = components.as_path();
+    if file_name.as_os_str().is_empty() || file_name.components().count() != 1 {
+        return None; // 数据目录下只有一层文件
+    }
+    data_dirs(paths)
+        .iter()
+        .find(|(n, _)| *n == dir)
+        .map(|(_, d)| d.join(file_name))
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local}; // 组内记录使用第一条记录的时间
+
+// 连续快速的窗口切换（如按住 Alt+Tab 依次经过几个窗口）：与上一次切换相隔不到 burst_ms 的切换属于同一组，
+// 组内除第一条以外的记录都带上第一条记录的ID，并使用第一条记录的时间，分析时可以把整组当作一次切换；
+// 组内的先后顺序由记录ID中会话内的序号（以及写入的顺序）确定
+pub struct Bursts {
+    window: Duration,                           // 属于同一组的最长间隔，为0时不分组
+    last: Option<Instant>,                      // 上一次切换被发现的时间
+    previous: Option<(String, DateTime<Local>)>, // 上一条记录的ID和时间
+    group: Option<(String, DateTime<Local>)>,    // 当前组第一条记录的ID和时间，不在组中时为空
+}
+
+impl Bursts {
+    pub fn new(burst_ms: u64) -> Bursts {
+        Bursts { window: Duration::from_millis(burst_ms), last: None, previous: None, group: None }
+    }
+
+    // 一条新的记录，返回它所属组的第一条记录的ID和记录应使用的时间：在组中时为第一条记录的时间，
+    // 不属于任何组时为None和 time 本身
+    pub fn mark(&mut self, detected: Instant, id: &str, time: DateTime<Local>) -> (Option<String>, DateTime<Local>) {
+        if self.window.is_zero() {
+            return (None, time);
+        }
+        let within = self.last.is_some_and(|last| detected.duration_since(last) < self.window);
+        if !within {
+            self.group = None;
+        } else if self.group.is_none() {
+            self.group = self.previous.take(); // 上一条记录是这一组的第一条
+        }
+        self.last = Some(detected);
+        self.previous = Some((id.to_string(), time));
+        match &self.group {
+            Some((first, start)) => (Some(first.clone()), *start),
+            None => (None, time),
+        }
+    }
+
+    // 会话中断后重新开始，不与之前的记录分到同一组
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.previous = None;
+        self.group = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    // 间隔不到 burst_ms 的切换连成一组，都带上第一条的ID并使用它的时间；间隔足够长时重新开始
+    #[test]
+    fn chained_switches_share_first_record() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let time = |second| Local.with_ymd_and_hms(2024, 3, 5, 9, 0, second).unwrap();
+        let mut bursts = Bursts::new(500);
+
+        assert_eq!(bursts.mark(at(0), "a", time(0)), (None, time(0)));
+        assert_eq!(bursts.mark(at(400), "b", time(1)), (Some("a".to_string()), time(0)));
+        assert_eq!(bursts.mark(at(800), "c", time(2)), (Some("a".to_string()), time(0))); // 与上一次相隔不到500毫秒，距第一条已超过
+        assert_eq!(bursts.mark(at(1400), "d", time(3)), (None, time(3)));
+        assert_eq!(bursts.mark(at(1500), "e", time(4)), (Some("d".to_string()), time(3)));
+    }
+
+    // 为0时不分组，reset 之后不与之前的记录分到同一组
+    #[test]
+    fn disabled_and_reset() {
+        let start = Instant::now();
+        let time = Local.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        let mut disabled = Bursts::new(0);
+        disabled.mark(start, "a", time);
+        assert_eq!(disabled.mark(start, "b", time), (None, time));
+
+        let mut bursts = Bursts::new(500);
+        bursts.mark(start, "a", time);
+        bursts.reset();
+        assert_eq!(bursts.mark(start + Duration::from_millis(100), "b", time), (None, time));
+    }
+}
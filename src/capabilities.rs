@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use foreground_watcher_types::{Capabilities, Capability}; // 各项数据的提供情况
+
+use crate::capture::CaptureMode; // 精简模式下不提供大部分数据
+use crate::config::Config; // 提供哪些数据取决于配置
+
+// 按当前配置列出记录中各个字段能否提供
+pub fn capabilities(config: &Config) -> Capabilities {
+    let minimal = config.capture == CaptureMode::Minimal;
+    let mut fields = BTreeMap::new();
+    let mut add = |name: &str, available: bool, note: Option<&str>| {
+        fields.insert(name.to_string(), Capability { available, note: note.map(str::to_string) });
+    };
+    const MINIMAL: &str = "精简模式下不提供";
+    let enriched = |note: Option<&'static str>| if minimal { (false, Some(MINIMAL)) } else { (true, note) };
+    let partial = if config.minimal.apps.is_empty() { None } else { Some("[minimal] apps 中的程序在前台时不提供") };
+
+    add("time", true, None);
+    add("pid", true, None);
+    add("id", true, Some("旧版本写入的记录没有"));
+    match config.burst_ms {
+        0 => add("burst", false, Some("没有设置 burst_ms")),
+        _ => add("burst", true, Some("只有连续快速切换的组内第二条起的记录才有")),
+    }
+    add("exe", true, Some("无法打开的进程（如已结束）为空"));
+    let (available, note) = enriched(partial.or(Some("没有标题的窗口为空，浏览器的隐私浏览窗口记录为 [private browsing]")));
+    add("title", available, note);
+    let (available, note) = enriched(partial);
+    add("app", available, note);
+    let (available, note) = enriched(partial.or(Some("没有版本资源的程序为空")));
+    for name in ["product", "company", "version"] {
+        add(name, available, note);
+    }
+    let (available, note) = enriched(partial);
+    add("presenting", available, note);
+    match (minimal, config.environment.is_empty()) {
+        _ if !cfg!(feature = "sysinfo") => add("env", false, Some("构建时没有开启 sysinfo 功能")),
+        (true, _) => add("env", false, Some(MINIMAL)),
+        (false, true) => add("env", false, Some("没有配置 [[environment]] 规则")),
+        (false, false) => add("env", true, Some("只包含匹配规则的程序中列出的变量，无权读取的进程为空")),
+    }
+    match (minimal, config.detect_language) {
+        (true, _) => add("lang", false, Some(MINIMAL)),
+        (false, false) => add("lang", false, Some("没有开启 detect_language")),
+        (false, true) => add("lang", true, Some("标题太短或判断不出时为空，隐私浏览窗口为空")),
+    }
+    match (minimal, config.enrichment.signer) {
+        (true, _) => add("signature", false, Some(MINIMAL)),
+        (false, false) => add("signature", false, Some("没有开启 [enrichment] signer")),
+        (false, true) => add("signature", true, Some("在后台验证，另写一条 enrichment 记录，超时或任务过多时没有")),
+    }
+    add("url", false, Some("尚不支持获取浏览器地址"));
+    add("idle", false, Some("尚不支持空闲检测"));
+
+    Capabilities { platform: "windows".to_string(), backend: config.backend.name().to_string(), fields }
+}
@@ -0,0 +1,59 @@
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+use windows::core::PWSTR; // 接收路径的缓冲区
+use windows::Win32::Foundation::CloseHandle; // 关闭进程句柄
+use windows::Win32::System::Threading::{
+    OpenProcess,                       // 打开进程句柄
+    QueryFullProcessImageNameW,        // 查询进程的可执行文件路径
+    PROCESS_NAME_WIN32,                // 返回 C:\... 形式的路径
+    PROCESS_QUERY_LIMITED_INFORMATION, // 最小的访问权限，对大多数进程（包括反作弊保护的游戏）都能打开
+};
+
+use crate::metadata::exe_name; // 从路径中取出文件名
+
+// 记录方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureMode {
+    #[default]
+    Full,    // 完整记录：窗口标题、版本信息、环境变量、演示状态等
+    Minimal, // 精简记录：只记录前台窗口的进程ID和可执行文件路径，不使用 sysinfo 等开销较大的接口
+}
+
+// [minimal] 精简模式的设置
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct MinimalConfig {
+    pub interval_ms: u64,  // 精简模式下的轮询间隔（毫秒），不小于1000
+    pub apps: Vec<String>, // 这些程序（可执行文件名，不区分大小写）在前台时自动切换到精简模式，如对延迟敏感的游戏
+}
+
+impl Default for MinimalConfig {
+    fn default() -> Self {
+        MinimalConfig { interval_ms: 1000, apps: Vec::new() }
+    }
+}
+
+impl MinimalConfig {
+    // 可执行文件是否在自动切换到精简模式的列表中
+    pub fn is_listed(&self, exe: &str) -> bool {
+        let name = exe_name(exe);
+        self.apps.iter().any(|app| app.eq_ignore_ascii_case(name))
+    }
+}
+
+// 用开销最小的接口查询进程的可执行文件路径，无法打开进程时返回None
+pub fn process_image(pid: u32) -> Option<String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = vec![0u16; 1024]; // 足够容纳长路径
+        let mut len = buffer.len() as u32; // 输入缓冲区大小，输出实际字符数
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use chrono::Days; // 统计的天数
+
+use crate::aggregate; // 按天汇总的缓存
+use crate::clock::Clock; // 确定“今天”
+use crate::config; // 把分类写回应用信息文件
+use crate::metadata::{exe_name, Metadata}; // 已有的分类和显示名称
+use crate::report::format_duration; // 时长的可读形式
+
+// 最多统计的天数
+pub const MAX_DAYS: u32 = 366;
+
+// 一个还没有分类的程序
+pub struct Uncategorized {
+    pub exe: String,      // 可执行文件名，即 apps.toml 中的键
+    pub name: String,     // 显示名称
+    pub duration_ms: i64, // 统计期间在前台的时间
+}
+
+// 最近 days 天（含今天）在前台出现过、apps.toml 中没有分类的程序，按前台时间从长到短排列
+// 使用缓存目录中按天汇总的缓存，只重新读取有变化的那几天的事件记录
+pub fn uncategorized(events: &Path, cache: &Path, metadata: &Metadata, days: u32, clock: &dyn Clock) -> std::io::Result<Vec<Uncategorized>> {
+    let today = clock.today();
+    let from = today - Days::new(days.saturating_sub(1) as u64);
+    let mut apps: BTreeMap<String, Uncategorized> = BTreeMap::new(); // 小写的可执行文件名 -> 统计
+    for total in aggregate::range(cache, events, from, today, clock)?.into_iter().flat_map(|(_, apps)| apps) {
+        let Some(exe) = total.exe.as_deref() else {
+            continue; // 无法得知是哪个程序
+        };
+        if metadata.get(exe).is_some_and(|app| app.category.is_some()) {
+            continue;
+        }
+        let app = apps.entry(exe_name(exe).to_lowercase()).or_insert_with(|| Uncategorized {
+            exe: exe_name(exe).to_string(),
+            name: metadata.display_name(exe, total.product.as_deref()),
+            duration_ms: 0,
+        });
+        app.duration_ms += total.duration_ms;
+    }
+    let mut apps: Vec<Uncategorized> = apps.into_values().collect();
+    apps.sort_by_key(|app| std::cmp::Reverse(app.duration_ms));
+    Ok(apps)
+}
+
+// 列出没有分类的程序
+pub fn list(apps: &[Uncategorized], days: u32) -> String {
+    if apps.is_empty() {
+        return format!("最近 {} 天出现过的程序都已经设置了分类\n", days);
+    }
+    let mut text = format!("最近 {} 天没有分类的程序，共 {} 个：\n\n", days, apps.len());
+    for app in apps {
+        text.push_str(&format!("{:>10}  {}{}\n", format_duration(app.duration_ms), app.exe, alias(app)));
+    }
+    text.push_str("\n用 categorize --interactive 逐个设置分类\n");
+    text
+}
+
+// 逐个询问没有分类的程序的分类，每设置一个就写回 apps.toml，中途退出时已经设置的不会丢失
+// 返回设置了分类的程序数
+pub fn interactive(path: &Path, metadata: &Metadata, apps: &[Uncategorized]) -> std::io::Result<usize> {
+    let mut categories: Vec<String> = metadata.categories().into_iter().collect();
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    let mut assigned = 0;
+    for (i, app) in apps.iter().enumerate() {
+        writeln!(output)?;
+        if !categories.is_empty() {
+            let choices: Vec<String> = categories.iter().enumerate().map(|(n, c)| format!("{} {}", n + 1, c)).collect();
+            writeln!(output, "已有分类: {}", choices.join("  "))?;
+        }
+        writeln!(output, "[{}/{}] {}{}，前台 {}", i + 1, apps.len(), app.exe, alias(app), format_duration(app.duration_ms))?;
+        write!(output, "分类（输入编号或新的分类名，直接回车跳过，q 结束）: ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break; // 输入已经结束
+        }
+        let answer = line.trim();
+        let category = match answer {
+            "" => continue,
+            "q" | "Q" => break,
+            _ => match answer.parse::<usize>() {
+                Ok(n) if (1..=categories.len()).contains(&n) => categories[n - 1].clone(),
+                Ok(n) if !categories.is_empty() => {
+                    writeln!(output, "没有编号为 {} 的分类，已跳过", n)?;
+                    continue;
+                }
+                _ => answer.to_string(),
+            },
+        };
+        match config::set_app_category(path, &app.exe, &category) {
+            Ok(()) => {
+                writeln!(output, "已设置 {} 的分类: {}", app.exe, category)?;
+                assigned += 1;
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+            Err(e) => writeln!(output, "{}，已跳过", e)?,
+        }
+    }
+    Ok(assigned)
+}
+
+// 显示名称与可执行文件名不同时附在后面，如（Visual Studio Code）
+fn alias(app: &Uncategorized) -> String {
+    if app.name.eq_ignore_ascii_case(&app.exe) {
+        String::new()
+    } else {
+        format!("（{}）", app.name)
+    }
+}
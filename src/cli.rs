@@ -0,0 +1,439 @@
+use std::path::PathBuf;
+
+use crate::categorize; // 最多统计的天数
+use crate::error::Error; // 参数有误
+use crate::export; // 支持的导出格式
+use crate::report; // 数据质量报告最多统计的周数
+
+// 要执行的子命令
+pub enum Command {
+    Run,                  // 默认：监视前台窗口
+    Init { force: bool }, // 在用户配置目录生成默认配置文件
+    Completions(String),  // 输出指定shell的补全脚本
+    Paths,                // 输出配置、数据、日志等文件的位置
+    #[cfg(feature = "backup")]
+    Backup { to: PathBuf },                  // 备份配置、事件记录和缓存到zip文件
+    #[cfg(feature = "backup")]
+    Restore { from: PathBuf, force: bool },  // 从zip备份恢复
+    Maintain,                                // 按保留期限清理、压缩归档，并报告释放的空间
+    Anonymize { keep_categories: bool, force: bool }, // 就地匿名化已有的事件记录
+    #[cfg(feature = "http")]
+    TokenCreate(String),                     // 创建本地HTTP接口的令牌
+    #[cfg(feature = "http")]
+    TokenList,                               // 列出已经创建的令牌
+    #[cfg(feature = "http")]
+    TokenRevoke(String),                     // 按 id 或名称吊销令牌
+    #[cfg(feature = "schema")]
+    Schema(String),                          // 输出事件记录、统计结果或配置文件的JSON Schema
+    Capabilities,                            // 输出当前平台和配置下各项数据能否提供
+    Doctor,                                  // 检查运行环境，给出修正建议
+    Inspect { hwnd: Option<isize>, pid: Option<u32> }, // 列出窗口或进程的全部信息，都不指定时查看当前前台窗口
+    RulesTest { exe: String, title: Option<String> },  // 用假想的记录测试配置的规则
+    Categorize { interactive: bool, days: u32 },      // 列出或逐个设置没有分类的程序
+    Report { kind: String, day: String, drill_down: Option<String>, weeks: u32 }, // 根据事件记录在终端输出报告
+    Export { format: String, from: Option<String>, to: Option<String>, by: Option<String>, output: Option<PathBuf> }, // 把事件记录导出为表格或时间线
+    Help,                 // 输出用法说明
+}
+
+// 命令行参数
+pub struct Args {
+    pub command: Command,
+    pub trace_win32: bool,       // 是否记录每一次轮询结果（包括被过滤掉的），用于调试
+    pub track_self: bool,        // 是否记录切换到本程序自己的窗口
+    pub config: Option<PathBuf>, // 通过 --config 指定的配置文件路径
+    pub data_dir: Option<PathBuf>, // 通过 --data-dir 指定的数据目录
+    pub portable: bool,            // 便携模式，配置文件和数据都放在可执行文件旁边
+    pub instance: Option<String>,  // 通过 --instance 指定的实例名称
+    pub soak: Option<u64>,         // 通过 --soak 指定的压力测试时长（分钟）
+}
+
+// 命令行选项的说明
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub value: Option<&'static str>, // 需要参数值时为参数名，如 "<路径>"
+    pub about: &'static str,
+}
+
+// 子命令的说明
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub options: &'static [OptionSpec],  // 子命令自己的选项
+    pub values: &'static [&'static str], // 子命令可接受的位置参数取值，用于补全
+}
+
+// 所有子命令都可以使用的全局选项
+pub const GLOBAL_OPTIONS: &[OptionSpec] = &[
+    OptionSpec { name: "--trace-win32", value: None, about: "记录每一次轮询结果及其被过滤的原因" },
+    OptionSpec { name: "--track-self", value: None, about: "记录切换到本程序自己的控制台窗口和浮窗" },
+    OptionSpec { name: "--config", value: Some("<路径>"), about: "指定配置文件" },
+    OptionSpec { name: "--data-dir", value: Some("<路径>"), about: "指定数据目录（日志、事件记录、缓存、崩溃报告）" },
+    OptionSpec { name: "--portable", value: None, about: "便携模式：配置文件、事件记录和日志都放在本程序所在目录，不使用注册表和 Known Folder" },
+    OptionSpec { name: "--instance", value: Some("<名称>"), about: "以指定名称的实例运行，使用单独的配置文件和数据目录，可以与默认实例同时运行" },
+    OptionSpec { name: "--soak", value: Some("<分钟>"), about: "压力测试：在几个测试窗口之间轮流切换指定的分钟数，同时照常记录" },
+    OptionSpec { name: "--help", value: None, about: "输出用法说明" },
+];
+
+// 子命令列表，用法说明和补全脚本都由这里生成
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "init",
+        about: "在用户配置目录生成带注释的默认配置文件",
+        options: &[OptionSpec { name: "--force", value: None, about: "覆盖已存在的配置文件" }],
+        values: &[],
+    },
+    CommandSpec {
+        name: "completions",
+        about: "输出指定shell的补全脚本",
+        options: &[],
+        values: &["powershell", "bash", "zsh"],
+    },
+    CommandSpec {
+        name: "paths",
+        about: "输出配置文件、数据、日志等的存放位置",
+        options: &[],
+        values: &[],
+    },
+    #[cfg(feature = "backup")]
+    CommandSpec {
+        name: "backup",
+        about: "把配置文件、事件记录和缓存备份到一个zip文件",
+        options: &[OptionSpec { name: "--to", value: Some("<文件>"), about: "备份文件路径" }],
+        values: &[],
+    },
+    #[cfg(feature = "backup")]
+    CommandSpec {
+        name: "restore",
+        about: "从backup生成的zip文件恢复，用于迁移到新电脑",
+        options: &[
+            OptionSpec { name: "--from", value: Some("<文件>"), about: "备份文件路径" },
+            OptionSpec { name: "--force", value: None, about: "覆盖已存在的文件" },
+        ],
+        values: &[],
+    },
+    CommandSpec {
+        name: "maintain",
+        about: "按保留期限清理旧数据、压缩尚未归档的文件，并报告释放的空间",
+        options: &[],
+        values: &[],
+    },
+    CommandSpec {
+        name: "anonymize",
+        about: "就地匿名化已有的事件记录：标题替换为散列值，路径去掉目录，删除环境变量，不可恢复",
+        options: &[
+            OptionSpec { name: "--keep", value: Some("categories"), about: "保留可执行文件名，使分类仍然可用" },
+            OptionSpec { name: "--force", value: None, about: "确认执行" },
+        ],
+        values: &[],
+    },
+    #[cfg(feature = "http")]
+    CommandSpec {
+        name: "token",
+        about: "token create/list/revoke：管理本地HTTP接口的令牌，只保存散列值；创建了令牌后请求必须带上令牌",
+        options: &[OptionSpec { name: "--name", value: Some("<名称>"), about: "token create 时令牌的用途，如 dashboard" }],
+        values: &["create", "list", "revoke"],
+    },
+    #[cfg(feature = "schema")]
+    CommandSpec {
+        name: "schema",
+        about: "输出事件记录、心跳、统计结果、配置文件或应用信息文件的JSON Schema",
+        options: &[],
+        values: &["events", "heartbeat", "session", "pulse", "secure_desktop", "correction", "enrichment", "stats", "config", "apps", "capabilities"],
+    },
+    CommandSpec {
+        name: "capabilities",
+        about: "以JSON输出当前平台和配置下记录中各个字段能否提供",
+        options: &[],
+        values: &[],
+    },
+    CommandSpec {
+        name: "doctor",
+        about: "检查钩子、权限、数据目录、网络输出目标和开机启动，列出每一项是否通过及修正建议",
+        options: &[],
+        values: &[],
+    },
+    CommandSpec {
+        name: "categorize",
+        about: "按前台时间列出没有分类的程序，加 --interactive 时逐个询问分类并写入 apps.toml",
+        options: &[
+            OptionSpec { name: "--interactive", value: None, about: "逐个询问分类，每设置一个就写入 apps.toml" },
+            OptionSpec { name: "--days", value: Some("<天数>"), about: "统计最近多少天（含今天）的记录，默认30" },
+        ],
+        values: &[],
+    },
+    CommandSpec {
+        name: "inspect",
+        about: "列出窗口或进程的类名、样式、可执行文件、签名等全部信息，用于编写规则",
+        options: &[
+            OptionSpec { name: "--hwnd", value: Some("<句柄>"), about: "窗口句柄，如 0x1234，默认为当前前台窗口" },
+            OptionSpec { name: "--pid", value: Some("<进程ID>"), about: "只查看进程" },
+        ],
+        values: &[],
+    },
+    CommandSpec {
+        name: "rules",
+        about: "rules test：用假想的窗口测试别名、分类等规则，列出匹配的规则和最终的记录",
+        options: &[
+            OptionSpec { name: "--exe", value: Some("<路径>"), about: "可执行文件完整路径" },
+            OptionSpec { name: "--title", value: Some("<标题>"), about: "窗口标题" },
+        ],
+        values: &["test"],
+    },
+    CommandSpec {
+        name: "report",
+        about: "report timeline：按小时输出某一天每10分钟占用最多的分类；report apps：各应用的时长；report quality：每周的数据质量",
+        options: &[
+            OptionSpec { name: "--day", value: Some("<日期>"), about: "today（默认）、yesterday 或 YYYY-MM-DD" },
+            OptionSpec { name: "--drill-down", value: Some("<条件>"), about: "report apps 中只看一个应用的窗口标题，如 exe=chrome.exe" },
+            OptionSpec { name: "--weeks", value: Some("<周数>"), about: "report quality 统计截至 --day 的几周，默认4" },
+        ],
+        values: &["timeline", "apps", "quality"],
+    },
+    CommandSpec {
+        name: "export",
+        about: "导出事件记录，daily-matrix：每天一行、每个应用或分类一列的分钟数（CSV）；perfetto：可以在 Perfetto UI 中查看的时间线（JSON）；dot：应用之间的切换图（Graphviz）",
+        options: &[
+            OptionSpec { name: "--format", value: Some("<格式>"), about: "导出格式：daily-matrix/perfetto/dot" },
+            OptionSpec { name: "--from", value: Some("<日期>"), about: "开始日期，默认为结束日期之前的第29天" },
+            OptionSpec { name: "--to", value: Some("<日期>"), about: "结束日期（包含），默认为今天" },
+            OptionSpec { name: "--by", value: Some("<app|category>"), about: "每个应用还是每个分类一列（一条轨道、一个节点），perfetto 默认 category，其余默认 app" },
+            OptionSpec { name: "--output", value: Some("<文件>"), about: "写入文件，默认输出到标准输出" },
+        ],
+        values: &[],
+    },
+];
+
+// 解析命令行参数，有误时返回 Error::Argument
+pub fn parse_args() -> Result<Args, Error> {
+    parse(std::env::args().skip(1)).map_err(Error::Argument) // 跳过程序自身路径
+}
+
+// 按顺序解析每个参数，有误时返回说明
+fn parse(mut iter: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = Args { command: Command::Run, trace_win32: false, track_self: false, config: None, data_dir: None, portable: false, instance: None, soak: None };
+    let mut subcommand: Option<&CommandSpec> = None; // 已识别出的子命令
+    let mut parsed = Parsed { options: Vec::new(), positional: Vec::new() };
+    let mut help = false;
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--trace-win32" => args.trace_win32 = true,
+            "--track-self" => args.track_self = true,
+            "--config" => args.config = Some(iter.next().ok_or("--config 需要一个文件路径")?.into()),
+            "--data-dir" => args.data_dir = Some(iter.next().ok_or("--data-dir 需要一个目录路径")?.into()),
+            "--portable" => args.portable = true,
+            "--instance" => {
+                let name = iter.next().ok_or("--instance 需要一个实例名称")?;
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                    return Err(format!("无效的实例名称: {}，只能包含字母、数字、- 和 _", name));
+                }
+                args.instance = Some(name);
+            }
+            "--soak" => {
+                let minutes = iter.next().ok_or("--soak 需要测试的分钟数")?;
+                args.soak = Some(minutes.parse().ok().filter(|m| *m > 0).ok_or_else(|| format!("无效的分钟数: {}", minutes))?);
+            }
+            "--help" | "-h" => help = true,
+            _ if arg.starts_with('-') => {
+                // 子命令自己的选项
+                let option = subcommand
+                    .and_then(|c| c.options.iter().find(|o| o.name == arg))
+                    .ok_or_else(|| format!("未知参数: {}", arg))?; // 不认识的参数直接报错，避免拼写错误被忽略
+                let value = match option.value {
+                    Some(name) => Some(iter.next().ok_or_else(|| format!("{} 需要参数 {}", option.name, name))?),
+                    None => None,
+                };
+                parsed.options.push((option.name, value));
+            }
+            _ if subcommand.is_none() => {
+                subcommand = Some(
+                    COMMANDS
+                        .iter()
+                        .find(|c| c.name == arg)
+                        .ok_or_else(|| format!("未知子命令: {}", arg))?,
+                );
+            }
+            _ => parsed.positional.push(arg),
+        }
+    }
+    if help {
+        args.command = Command::Help; // 要求输出帮助时不再检查其余参数
+        return Ok(args);
+    }
+    if let Some(spec) = subcommand {
+        if args.soak.is_some() {
+            return Err(format!("--soak 不能与子命令 {} 一起使用", spec.name));
+        }
+        args.command = match spec.name {
+            "init" => Command::Init { force: parsed.flag("--force") },
+            "completions" => {
+                let shell = parsed.positional.pop().ok_or("completions 需要指定shell: powershell/bash/zsh")?;
+                if !spec.values.contains(&shell.as_str()) {
+                    return Err(format!("不支持的shell: {}，可选值: {}", shell, spec.values.join("/")));
+                }
+                Command::Completions(shell)
+            }
+            "paths" => Command::Paths,
+            #[cfg(feature = "backup")]
+            "backup" => Command::Backup { to: parsed.required("--to")?.into() },
+            #[cfg(feature = "backup")]
+            "restore" => Command::Restore { from: parsed.required("--from")?.into(), force: parsed.flag("--force") },
+            "maintain" => Command::Maintain,
+            "anonymize" => {
+                let keep_categories = match parsed.value("--keep").as_deref() {
+                    Some("categories") => true,
+                    Some(other) => return Err(format!("--keep 不支持 {}，可选值: categories", other)),
+                    None => false,
+                };
+                Command::Anonymize { keep_categories, force: parsed.flag("--force") }
+            }
+            #[cfg(feature = "http")]
+            "token" => {
+                if parsed.positional.is_empty() {
+                    return Err(format!("token 需要指定操作: {}", spec.values.join("/")));
+                }
+                let action = parsed.positional.remove(0);
+                match action.as_str() {
+                    "create" => Command::TokenCreate(parsed.required("--name")?),
+                    "list" => Command::TokenList,
+                    "revoke" => Command::TokenRevoke(parsed.positional.pop().ok_or("token revoke 需要指定令牌的 id 或名称")?),
+                    other => return Err(format!("不支持的操作: {}，可选值: {}", other, spec.values.join("/"))),
+                }
+            }
+            #[cfg(feature = "schema")]
+            "schema" => {
+                let kind = parsed.positional.pop().ok_or_else(|| format!("schema 需要指定数据: {}", spec.values.join("/")))?;
+                if !spec.values.contains(&kind.as_str()) {
+                    return Err(format!("不支持的数据: {}，可选值: {}", kind, spec.values.join("/")));
+                }
+                Command::Schema(kind)
+            }
+            "capabilities" => Command::Capabilities,
+            "doctor" => Command::Doctor,
+            "categorize" => {
+                let days = match parsed.value("--days") {
+                    Some(text) => match text.parse::<u32>() {
+                        Ok(days) if (1..=categorize::MAX_DAYS).contains(&days) => days,
+                        _ => return Err(format!("--days 应为 1 到 {} 之间的整数: {}", categorize::MAX_DAYS, text)),
+                    },
+                    None => 30,
+                };
+                Command::Categorize { interactive: parsed.flag("--interactive"), days }
+            }
+            "inspect" => {
+                let hwnd = parsed.value("--hwnd").map(|v| parse_handle(&v)).transpose()?;
+                let pid = parsed.value("--pid").map(|v| v.parse().map_err(|_| format!("无效的进程ID: {}", v))).transpose()?;
+                if hwnd.is_some() && pid.is_some() {
+                    return Err("--hwnd 和 --pid 只能指定一个".into());
+                }
+                Command::Inspect { hwnd, pid }
+            }
+            "rules" => {
+                match parsed.positional.pop().as_deref() {
+                    Some("test") => {}
+                    Some(other) => return Err(format!("不支持的操作: {}，可选值: {}", other, spec.values.join("/"))),
+                    None => return Err(format!("rules 需要指定操作: {}", spec.values.join("/"))),
+                }
+                Command::RulesTest { exe: parsed.required("--exe")?, title: parsed.value("--title") }
+            }
+            "report" => {
+                let kind = parsed.positional.pop().ok_or_else(|| format!("report 需要指定报告: {}", spec.values.join("/")))?;
+                if !spec.values.contains(&kind.as_str()) {
+                    return Err(format!("不支持的报告: {}，可选值: {}", kind, spec.values.join("/")));
+                }
+                let drill_down = parsed.value("--drill-down");
+                if drill_down.is_some() && kind != "apps" {
+                    return Err("--drill-down 只能用于 report apps".into());
+                }
+                let weeks = match parsed.value("--weeks") {
+                    Some(_) if kind != "quality" => return Err("--weeks 只能用于 report quality".into()),
+                    Some(text) => match text.parse::<u32>() {
+                        Ok(weeks) if (1..=report::MAX_WEEKS).contains(&weeks) => weeks,
+                        _ => return Err(format!("--weeks 应为 1 到 {} 之间的整数: {}", report::MAX_WEEKS, text)),
+                    },
+                    None => 4,
+                };
+                Command::Report { kind, day: parsed.value("--day").unwrap_or_else(|| "today".to_string()), drill_down, weeks }
+            }
+            "export" => {
+                let format = parsed.required("--format")?;
+                if !export::FORMATS.contains(&format.as_str()) {
+                    return Err(format!("不支持的导出格式: {}，可选值: {}", format, export::FORMATS.join("/")));
+                }
+                Command::Export {
+                    format,
+                    from: parsed.value("--from"),
+                    to: parsed.value("--to"),
+                    by: parsed.value("--by"),
+                    output: parsed.value("--output").map(PathBuf::from),
+                }
+            }
+            _ => unreachable!("COMMANDS 中的每个子命令都需要在这里处理"),
+        };
+        if !parsed.positional.is_empty() {
+            return Err(format!("多余的参数: {}", parsed.positional.join(" ")));
+        }
+    }
+    Ok(args)
+}
+
+// 子命令的选项和位置参数
+struct Parsed {
+    options: Vec<(&'static str, Option<String>)>, // 出现过的选项及其参数值
+    positional: Vec<String>,                      // 位置参数
+}
+
+impl Parsed {
+    // 开关选项是否出现
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| *n == name)
+    }
+
+    // 选项的参数值，出现多次时取最后一次
+    fn value(&self, name: &str) -> Option<String> {
+        self.options.iter().rev().find(|(n, _)| *n == name).and_then(|(_, v)| v.clone())
+    }
+
+    // 必须提供的选项的参数值
+    fn required(&self, name: &str) -> Result<String, String> {
+        self.value(name).ok_or_else(|| format!("缺少必需的选项 {}", name))
+    }
+}
+
+// 解析窗口句柄，可以写成十六进制（0x1234）或十进制
+fn parse_handle(text: &str) -> Result<isize, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("无效的窗口句柄: {}", text))
+}
+
+// 生成用法说明
+pub fn usage() -> String {
+    let mut text = String::from("用法: ForegroundWatcher [子命令] [选项]\n\n不带子命令时开始监视前台窗口。\n\n子命令:\n");
+    for command in COMMANDS {
+        text.push_str(&format!("  {}{}\n", pad(command.name, 14), command.about));
+        for option in command.options {
+            text.push_str(&format!("      {}{}\n", pad(&option_usage(option), 20), option.about));
+        }
+    }
+    text.push_str("\n全局选项:\n");
+    for option in GLOBAL_OPTIONS {
+        text.push_str(&format!("  {}{}\n", pad(&option_usage(option), 24), option.about));
+    }
+    text
+}
+
+// 按终端显示宽度补齐空格，中文字符占两列
+fn pad(s: &str, width: usize) -> String {
+    let used: usize = s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+    format!("{}{}", s, " ".repeat(width.saturating_sub(used)))
+}
+
+// 选项在用法说明中的写法，如 "--config <路径>"
+fn option_usage(option: &OptionSpec) -> String {
+    match option.value {
+        Some(value) => format!("{} {}", option.name, value),
+        None => option.name.to_string(),
+    }
+}
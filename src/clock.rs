@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, NaiveDate}; // 本地时间和日期
+
+// 当前时间的来源，所有需要“现在”的地方都通过它获取，而不是直接调用 Local::now()，
+// 这样时长统计、按天分文件、保留期限等逻辑可以用固定或模拟的时间运行，回放时得到相同的结果
+pub trait Clock: Send + Sync {
+    // 当前的本地时间
+    fn now(&self) -> DateTime<Local>;
+
+    // 今天的本地日期
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+// 可以在线程之间共享的时间来源
+pub type SharedClock = Arc<dyn Clock>;
+
+// 系统时间
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+// 固定的时间，测试中让依赖“现在”的统计得到确定的结果
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Local>);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
@@ -0,0 +1,168 @@
+use crate::cli::{OptionSpec, COMMANDS, GLOBAL_OPTIONS};
+
+// 补全脚本注册的命令名，Windows下可执行文件名不区分大小写
+const COMMAND_NAMES: &[&str] = &["ForegroundWatcher", "foregroundwatcher"];
+
+// 生成指定shell的补全脚本
+pub fn script(shell: &str) -> String {
+    match shell {
+        "powershell" => powershell(),
+        "bash" => bash(),
+        "zsh" => zsh(),
+        _ => unreachable!("参数解析阶段已检查shell名称"),
+    }
+}
+
+// 选项名列表
+fn option_names(options: &[OptionSpec]) -> Vec<&'static str> {
+    options.iter().map(|o| o.name).collect()
+}
+
+// 需要参数值的选项名列表
+fn value_options() -> Vec<&'static str> {
+    GLOBAL_OPTIONS
+        .iter()
+        .chain(COMMANDS.iter().flat_map(|c| c.options.iter()))
+        .filter(|o| o.value.is_some())
+        .map(|o| o.name)
+        .collect()
+}
+
+// PowerShell 补全脚本，在 $PROFILE 中执行: ForegroundWatcher completions powershell | Out-String | Invoke-Expression
+fn powershell() -> String {
+    // 单引号字符串中的单引号需要写成两个
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let item = |name: &str, tip: &str| format!("@{{ Name = {}; Tip = {} }}", quote(name), quote(tip));
+    let globals: Vec<String> = GLOBAL_OPTIONS.iter().map(|o| item(o.name, o.about)).collect();
+
+    let mut cases = String::new();
+    for command in COMMANDS {
+        let mut items: Vec<String> = command.options.iter().map(|o| item(o.name, o.about)).collect();
+        items.extend(command.values.iter().map(|v| item(v, command.about)));
+        items.extend(globals.iter().cloned());
+        cases.push_str(&format!("        {} {{ @({}) }}\n", quote(command.name), items.join(", ")));
+    }
+    let mut top: Vec<String> = COMMANDS.iter().map(|c| item(c.name, c.about)).collect();
+    top.extend(globals.iter().cloned());
+    cases.push_str(&format!("        default {{ @({}) }}\n", top.join(", ")));
+
+    let names: Vec<String> = COMMAND_NAMES
+        .iter()
+        .flat_map(|n| [quote(n), quote(&format!("{}.exe", n))])
+        .collect();
+    let commands: Vec<String> = COMMANDS.iter().map(|c| quote(c.name)).collect();
+    let value_options: Vec<String> = value_options().iter().map(|o| quote(o)).collect();
+
+    format!(
+        r#"Register-ArgumentCompleter -Native -CommandName {names} -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object {{ $_.ToString() }})
+    if ($wordToComplete) {{ $words = @($words | Select-Object -SkipLast 1) }}
+    if ($words.Count -gt 0 -and $words[-1] -in @({value_options})) {{ return }}
+    $command = $words | Where-Object {{ $_ -in @({commands}) }} | Select-Object -First 1
+    $candidates = switch ($command) {{
+{cases}    }}
+    $candidates | Where-Object {{ $_.Name -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_.Name, $_.Name, 'ParameterValue', $_.Tip)
+    }}
+}}
+"#,
+        names = names.join(", "),
+        value_options = value_options.join(", "),
+        commands = commands.join(", "),
+        cases = cases,
+    )
+}
+
+// bash 补全脚本，在 ~/.bashrc 中执行: source <(ForegroundWatcher completions bash)
+fn bash() -> String {
+    let globals = option_names(GLOBAL_OPTIONS).join(" ");
+    let mut cases = String::new();
+    for command in COMMANDS {
+        let mut words = option_names(command.options);
+        words.extend(command.values.iter());
+        cases.push_str(&format!(
+            "        {}) COMPREPLY=( $(compgen -W \"{} {}\" -- \"$cur\") ) ;;\n",
+            command.name,
+            words.join(" "),
+            globals
+        ));
+    }
+    let commands: Vec<&str> = COMMANDS.iter().map(|c| c.name).collect();
+    format!(
+        r#"_foregroundwatcher() {{
+    local cur prev cmd word
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        {value_options}) COMPREPLY=( $(compgen -f -- "$cur") ); return ;;
+    esac
+    cmd=""
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        case "$word" in
+            {command_pattern}) cmd="$word"; break ;;
+        esac
+    done
+    case "$cmd" in
+{cases}        *) COMPREPLY=( $(compgen -W "{commands} {globals}" -- "$cur") ) ;;
+    esac
+}}
+complete -F _foregroundwatcher {names}
+"#,
+        value_options = value_options().join("|"),
+        command_pattern = commands.join("|"),
+        cases = cases,
+        commands = commands.join(" "),
+        globals = globals,
+        names = COMMAND_NAMES.join(" "),
+    )
+}
+
+// zsh 补全脚本，在 ~/.zshrc 中执行: source <(ForegroundWatcher completions zsh)
+fn zsh() -> String {
+    // zsh 的 _arguments 规格里方括号和冒号有特殊含义，需要转义
+    let escape = |s: &str| s.replace('[', "\\[").replace(']', "\\]").replace(':', "\\:").replace('\'', "'\\''");
+    let option_spec = |o: &OptionSpec| match o.value {
+        Some(_) => format!("'{}[{}]:file:_files'", o.name, escape(o.about)),
+        None => format!("'{}[{}]'", o.name, escape(o.about)),
+    };
+    let globals: Vec<String> = GLOBAL_OPTIONS.iter().map(option_spec).collect();
+    let commands: Vec<String> = COMMANDS
+        .iter()
+        .map(|c| format!("'{}:{}'", c.name, escape(c.about)))
+        .collect();
+
+    let mut cases = String::new();
+    for command in COMMANDS {
+        let mut specs: Vec<String> = command.options.iter().map(option_spec).collect();
+        specs.extend(globals.iter().cloned());
+        if !command.values.is_empty() {
+            specs.push(format!("'1:value:({})'", command.values.join(" ")));
+        }
+        cases.push_str(&format!("                {}) _arguments {} ;;\n", command.name, specs.join(" ")));
+    }
+
+    format!(
+        r#"#compdef {names}
+
+_foregroundwatcher() {{
+    local -a commands
+    commands=({commands})
+    _arguments -C {globals} '1: :->command' '*:: :->args'
+    case $state in
+        command) _describe 'command' commands ;;
+        args)
+            case $words[1] in
+{cases}            esac
+            ;;
+    esac
+}}
+
+compdef _foregroundwatcher {names}
+"#,
+        names = COMMAND_NAMES.join(" "),
+        commands = commands.join(" "),
+        globals = globals.join(" "),
+        cases = cases,
+    )
+}
@@ -0,0 +1,1145 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use log::LevelFilter; // 日志级别
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::de::DeserializeOwned; // 按结构定义校验后再反序列化
+use serde::Deserialize; // 反序列化配置
+use toml::de::{DeTable, DeValue}; // 带位置信息的TOML解析结果，用于校验时报告行号
+use toml::Spanned; // 带位置信息的值
+
+use crate::alarm::AlarmConfig; // 健康告警
+use crate::archive::ArchiveConfig; // 归档压缩设置
+use crate::enrich::EnrichmentConfig; // 后台查询的补充信息
+use crate::capture::{CaptureMode, MinimalConfig}; // 记录方式
+use crate::environment::EnvironmentRule; // 环境变量记录规则
+use crate::error::{ConfigError, ConfigFile, Error, Problem}; // 配置文件有误
+use crate::filter::{self, FilterConfig}; // 按窗口样式过滤
+use crate::heartbeat::MemoryConfig; // 内存上限设置
+use crate::hook::Backend; // 发现窗口切换的方式
+use crate::hud::HudConfig; // 浮窗
+#[cfg(feature = "http")]
+use crate::http::HttpConfig; // 本地HTTP接口
+use crate::identity::{self, AliasRule}; // 应用别名规则
+use crate::maintain::MaintenanceConfig; // 定期维护设置
+use crate::pulse::PulseConfig; // 状态心跳模式
+use crate::schedule::{self, ScheduleRule}; // 定时生成报告和导出
+use crate::sink::{self, FieldsConfig}; // 各个输出目标接收的字段
+use crate::splash::SplashConfig; // 启动窗口
+use crate::trigger::{self, TriggerRule}; // 每天第一次切换到某个程序时的触发规则
+use crate::workhours::{self, WorkWindow}; // 工作时间
+
+// 配置文件内容，所有字段都有默认值，配置文件中只需写出需要修改的项
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct Config {
+    pub interval_ms: u64,   // 轮询间隔（毫秒）
+    pub backend: Backend,   // 发现窗口切换的方式：轮询，或钩子加定期核对
+    pub verify_seconds: u64, // 钩子方式下每隔多少秒轮询一次，核对钩子是否漏报
+    pub timer_tolerance_ms: u32, // 允许系统推迟每次轮询唤醒的最长时间（毫秒），用于合并唤醒以节能
+    pub priority_boost: bool, // 是否允许系统临时提升本进程的优先级
+    pub log_level: String,  // 日志级别：off/error/warn/info/debug/trace
+    pub trace_win32: bool,  // 是否开启Win32跟踪模式，等同于命令行参数 --trace-win32
+    pub capture: CaptureMode, // 记录方式：完整或精简
+    pub minimal: MinimalConfig, // 精简模式的设置
+    pub filter: FilterConfig, // 按窗口样式和大小过滤不需要记录的窗口
+    pub splash: SplashConfig, // 启动画面等很快被主窗口取代的窗口并入之后的窗口
+    pub heartbeat_minutes: u64, // 每隔多少分钟在事件记录中写一条心跳，0表示不写
+    pub detect_language: bool, // 是否检测窗口标题的语言，写入记录的 lang
+    pub burst_ms: u64,      // 与上一次切换相隔不到这么多毫秒的切换分为一组，写入记录的 burst，0表示不分组
+    pub track_self: bool,   // 是否记录切换到本程序自己的控制台窗口和浮窗，等同于命令行参数 --track-self
+    pub memory: MemoryConfig, // 内存上限设置
+    pub pulse: PulseConfig, // 状态心跳模式，用按固定间隔重复发送的当前状态代替窗口切换记录
+    pub hud: HudConfig,     // 显示当前应用和今天时长的浮窗
+    pub alarm: AlarmConfig, // 记录中断、写入失败等异常时的健康告警
+    pub enrichment: EnrichmentConfig, // 在后台查询的补充信息（数字签名等）及其并发数和超时
+    #[cfg(feature = "http")]
+    pub http: HttpConfig, // 本地HTTP接口（SSE 和长轮询）
+    pub archive: ArchiveConfig, // 日志和事件记录的归档压缩设置
+    pub maintenance: MaintenanceConfig, // 保留期限和定期维护设置
+    pub fields: FieldsConfig, // 各个输出目标接收的字段
+    pub alias: Vec<AliasRule>, // 应用别名规则，把不同路径、不同版本的程序归为同一个应用
+    pub environment: Vec<EnvironmentRule>, // 需要记录前台进程哪些环境变量，默认不记录
+    pub work_hours: Vec<WorkWindow>, // 工作时间，设置后报告把各项时长拆分为工作时间内和工作时间外
+    pub trigger: Vec<TriggerRule>, // 每天第一次切换到匹配的程序时发送 webhook 或执行程序
+    pub schedule: Vec<ScheduleRule>, // 监视程序运行期间按 cron 表达式定时生成报告和导出
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            interval_ms: 10, // 与之前写死的10毫秒保持一致
+            backend: Backend::default(),
+            verify_seconds: 5,
+            timer_tolerance_ms: 5,
+            priority_boost: false,
+            log_level: "info".to_string(),
+            trace_win32: false,
+            capture: CaptureMode::default(),
+            minimal: MinimalConfig::default(),
+            filter: FilterConfig::default(),
+            splash: SplashConfig::default(),
+            heartbeat_minutes: 10,
+            detect_language: false,
+            burst_ms: 0,
+            track_self: false,
+            memory: MemoryConfig::default(),
+            pulse: PulseConfig::default(),
+            hud: HudConfig::default(),
+            alarm: AlarmConfig::default(),
+            enrichment: EnrichmentConfig::default(),
+            #[cfg(feature = "http")]
+            http: HttpConfig::default(),
+            archive: ArchiveConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            fields: FieldsConfig::default(),
+            alias: Vec::new(),
+            environment: Vec::new(),
+            work_hours: Vec::new(),
+            trigger: Vec::new(),
+            schedule: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    // 配置中的日志级别，校验阶段已保证可以解析
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.parse().unwrap_or(LevelFilter::Info)
+    }
+}
+
+// 配置项的值类型
+enum Kind {
+    Integer,
+    Boolean,
+    String,
+    StringArray, // 字符串数组
+    Table(&'static [Field], Option<TableCheck>), // 子表及其各项定义，可附带涉及多个键的检查
+    TableArray(&'static [Field], Option<ArrayCheck>), // 表数组（[[name]]）中每个表的定义，可附带涉及多个元素的检查
+}
+
+impl Kind {
+    // 类型的中文名称，用于错误提示
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Integer => "整数",
+            Kind::Boolean => "布尔值",
+            Kind::String => "字符串",
+            Kind::StringArray => "字符串数组",
+            Kind::Table(..) => "表",
+            Kind::TableArray(..) => "表数组",
+        }
+    }
+
+    // 判断值是否属于该类型
+    fn matches(&self, value: &DeValue) -> bool {
+        match self {
+            Kind::Integer => value.is_integer(),
+            Kind::Boolean => value.is_bool(),
+            Kind::String => value.is_str(),
+            Kind::StringArray => value.as_array().is_some_and(|a| a.iter().all(|v| v.get_ref().is_str())),
+            Kind::Table(..) => value.is_table(),
+            Kind::TableArray(..) => value.as_array().is_some_and(|a| a.iter().all(|v| v.get_ref().is_table())),
+        }
+    }
+}
+
+// 配置项的取值检查，返回Err时附带错误说明
+type Check = fn(&DeValue) -> Result<(), String>;
+
+// 涉及同一个表中多个键的检查，返回出错位置（字节偏移）和错误说明
+type TableCheck = fn(&DeTable) -> Vec<(usize, String)>;
+
+// 涉及表数组中多个元素的检查（如规则之间的冲突），返回出错位置（字节偏移）和错误说明
+type ArrayCheck = fn(&[Spanned<DeValue>]) -> Vec<(usize, String)>;
+
+// 配置文件结构中的一项：键名、类型，以及可选的取值检查
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    check: Option<Check>,
+}
+
+// 顶层配置项定义，新增配置时需要同时加到这里，否则会被当作未知键报错
+const SCHEMA: &[Field] = &[
+    Field { name: "interval_ms", kind: Kind::Integer, check: Some(check_interval) },
+    Field { name: "backend", kind: Kind::String, check: Some(check_backend) },
+    Field { name: "verify_seconds", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "timer_tolerance_ms", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "priority_boost", kind: Kind::Boolean, check: None },
+    Field { name: "log_level", kind: Kind::String, check: Some(check_log_level) },
+    Field { name: "trace_win32", kind: Kind::Boolean, check: None },
+    Field { name: "capture", kind: Kind::String, check: Some(check_capture) },
+    Field { name: "minimal", kind: Kind::Table(MINIMAL_SCHEMA, None), check: None },
+    Field { name: "filter", kind: Kind::Table(FILTER_SCHEMA, None), check: None },
+    Field { name: "splash", kind: Kind::Table(SPLASH_SCHEMA, None), check: None },
+    Field { name: "heartbeat_minutes", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "detect_language", kind: Kind::Boolean, check: None },
+    Field { name: "burst_ms", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "track_self", kind: Kind::Boolean, check: None },
+    Field { name: "memory", kind: Kind::Table(MEMORY_SCHEMA, None), check: None },
+    Field { name: "pulse", kind: Kind::Table(PULSE_SCHEMA, Some(check_pulse)), check: None },
+    Field { name: "hud", kind: Kind::Table(HUD_SCHEMA, None), check: None },
+    Field { name: "alarm", kind: Kind::Table(ALARM_SCHEMA, None), check: None },
+    Field { name: "enrichment", kind: Kind::Table(ENRICHMENT_SCHEMA, None), check: None },
+    #[cfg(feature = "http")]
+    Field { name: "http", kind: Kind::Table(HTTP_SCHEMA, None), check: None },
+    Field { name: "archive", kind: Kind::Table(ARCHIVE_SCHEMA, Some(check_archive)), check: None },
+    Field { name: "maintenance", kind: Kind::Table(MAINTENANCE_SCHEMA, None), check: None },
+    Field { name: "fields", kind: Kind::Table(FIELDS_SCHEMA, None), check: None },
+    Field { name: "alias", kind: Kind::TableArray(ALIAS_SCHEMA, Some(check_alias_conflicts)), check: None },
+    Field { name: "environment", kind: Kind::TableArray(ENVIRONMENT_SCHEMA, Some(check_environment_rules)), check: None },
+    Field { name: "work_hours", kind: Kind::TableArray(WORK_HOURS_SCHEMA, Some(check_work_hours)), check: None },
+    Field { name: "trigger", kind: Kind::TableArray(TRIGGER_SCHEMA, Some(check_triggers)), check: None },
+    Field { name: "schedule", kind: Kind::TableArray(SCHEDULE_SCHEMA, Some(check_schedules)), check: None },
+];
+
+// [[alias]] 规则的定义
+const ALIAS_SCHEMA: &[Field] = &[
+    Field { name: "pattern", kind: Kind::String, check: Some(check_regex) },
+    Field { name: "app", kind: Kind::String, check: None },
+];
+
+// 正则表达式必须能够编译
+fn check_regex(value: &DeValue) -> Result<(), String> {
+    identity::compile(value.as_str().unwrap_or_default())
+        .map(|_| ())
+        .map_err(|e| format!("无效的正则表达式: {}", e))
+}
+
+// 同一个匹配规则不能对应不同的应用，缺少必需的键也在这里报告
+fn check_alias_conflicts(rules: &[Spanned<DeValue>]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    let mut seen: Vec<(String, &str, usize)> = Vec::new(); // 小写的匹配规则、应用名、所在位置
+    for rule in rules {
+        let Some(table) = rule.get_ref().as_table() else {
+            continue;
+        };
+        let pattern = table.get("pattern").and_then(|v| v.get_ref().as_str());
+        let app = table.get("app").and_then(|v| v.get_ref().as_str());
+        let (Some(pattern), Some(app)) = (pattern, app) else {
+            problems.push((rule.span().start, "[[alias]] 规则需要同时写出 pattern 和 app".to_string()));
+            continue;
+        };
+        let offset = rule.span().start;
+        match seen.iter().find(|(p, _, _)| *p == pattern.to_lowercase()) {
+            Some((_, other, _)) if *other != app => problems.push((
+                offset,
+                format!("[[alias]] 规则 \"{}\" 已经对应应用 \"{}\"，这里又对应 \"{}\"，请删除其中一条", pattern, other, app),
+            )),
+            Some(_) => problems.push((offset, format!("[[alias]] 规则 \"{}\" 重复，请删除其中一条", pattern))),
+            None => seen.push((pattern.to_lowercase(), app, offset)),
+        }
+    }
+    problems
+}
+
+// [[environment]] 规则的定义
+const ENVIRONMENT_SCHEMA: &[Field] = &[
+    Field { name: "pattern", kind: Kind::String, check: Some(check_regex) },
+    Field { name: "variables", kind: Kind::StringArray, check: Some(check_variables) },
+];
+
+// 环境变量名不能为空，也不能包含等号
+fn check_variables(value: &DeValue) -> Result<(), String> {
+    let names: Vec<&str> = value.as_array().into_iter().flatten().filter_map(|v| v.get_ref().as_str()).collect();
+    if names.is_empty() {
+        return Err("至少需要一个环境变量名".to_string());
+    }
+    match names.iter().find(|n| n.is_empty() || n.contains('=')) {
+        Some(name) => Err(format!("无效的环境变量名 \"{}\"", name)),
+        None => Ok(()),
+    }
+}
+
+// [[environment]] 规则必须同时写出 pattern 和 variables
+fn check_environment_rules(rules: &[Spanned<DeValue>]) -> Vec<(usize, String)> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.get_ref().as_table().map(|table| (rule.span().start, table)))
+        .filter(|(_, table)| !table.contains_key("pattern") || !table.contains_key("variables"))
+        .map(|(offset, _)| (offset, "[[environment]] 规则需要同时写出 pattern 和 variables".to_string()))
+        .collect()
+}
+
+// [[work_hours]] 时间段的定义
+const WORK_HOURS_SCHEMA: &[Field] = &[
+    Field { name: "days", kind: Kind::StringArray, check: Some(check_days) },
+    Field { name: "start", kind: Kind::String, check: Some(check_time) },
+    Field { name: "end", kind: Kind::String, check: Some(check_time) },
+];
+
+// 星期名称必须是 mon 到 sun 之一
+fn check_days(value: &DeValue) -> Result<(), String> {
+    let days: Vec<&str> = value.as_array().into_iter().flatten().filter_map(|v| v.get_ref().as_str()).collect();
+    if days.is_empty() {
+        return Err("至少需要一个星期名称".to_string());
+    }
+    match days.iter().find(|d| workhours::parse_day(d).is_none()) {
+        Some(day) => match suggest(&day.to_lowercase(), workhours::DAYS) {
+            Some(s) => Err(format!("无效的星期名称 \"{}\"，你是不是想写 \"{}\"？", day, s)),
+            None => Err(format!("无效的星期名称 \"{}\"，可选值: {}", day, workhours::DAYS.join("/"))),
+        },
+        None => Ok(()),
+    }
+}
+
+// 时间必须是 HH:MM
+fn check_time(value: &DeValue) -> Result<(), String> {
+    let text = value.as_str().unwrap_or_default();
+    match workhours::parse_time(text) {
+        Some(_) => Ok(()),
+        None => Err(format!("无效的时间 \"{}\"，请写成 HH:MM，如 09:00", text)),
+    }
+}
+
+// [[work_hours]] 时间段必须同时写出 start 和 end，结束时间必须晚于开始时间
+fn check_work_hours(windows: &[Spanned<DeValue>]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    for window in windows {
+        let Some(table) = window.get_ref().as_table() else {
+            continue;
+        };
+        let time = |key: &str| table.get(key).and_then(|v| v.get_ref().as_str());
+        let (Some(start), Some(end)) = (time("start"), time("end")) else {
+            problems.push((window.span().start, "[[work_hours]] 时间段需要同时写出 start 和 end".to_string()));
+            continue;
+        };
+        if let (Some(from), Some(to)) = (workhours::parse_time(start), workhours::parse_time(end)) {
+            if from >= to {
+                problems.push((
+                    window.span().start,
+                    format!("[[work_hours]] 时间段的结束时间 {} 必须晚于开始时间 {}，跨过午夜的时间段请拆成两段", end, start),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// [[trigger]] 规则的定义
+const TRIGGER_SCHEMA: &[Field] = &[
+    Field { name: "pattern", kind: Kind::String, check: Some(check_regex) },
+    Field { name: "name", kind: Kind::String, check: None },
+    Field { name: "webhook", kind: Kind::String, check: Some(check_webhook) },
+    Field { name: "command", kind: Kind::StringArray, check: Some(check_command) },
+];
+
+// webhook 必须是 http:// 地址
+fn check_webhook(value: &DeValue) -> Result<(), String> {
+    trigger::parse_url(value.as_str().unwrap_or_default()).map(|_| ())
+}
+
+// command 至少要写出程序
+fn check_command(value: &DeValue) -> Result<(), String> {
+    match value.as_array().and_then(|a| a.first()).and_then(|v| v.get_ref().as_str()) {
+        Some(program) if !program.is_empty() => Ok(()),
+        _ => Err("command 的第一项应为要执行的程序".to_string()),
+    }
+}
+
+// [[trigger]] 规则必须写出 pattern，以及 webhook 和 command 中的至少一个
+fn check_triggers(rules: &[Spanned<DeValue>]) -> Vec<(usize, String)> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.get_ref().as_table().map(|table| (rule.span().start, table)))
+        .filter_map(|(offset, table)| {
+            if !table.contains_key("pattern") {
+                Some((offset, "[[trigger]] 规则需要写出 pattern".to_string()))
+            } else if !table.contains_key("webhook") && !table.contains_key("command") {
+                Some((offset, "[[trigger]] 规则需要写出 webhook 或 command".to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// [[schedule]] 定时任务的定义
+const SCHEDULE_SCHEMA: &[Field] = &[
+    Field { name: "cron", kind: Kind::String, check: Some(check_cron) },
+    Field { name: "task", kind: Kind::String, check: Some(check_task) },
+    Field { name: "name", kind: Kind::String, check: None },
+    Field { name: "days", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "until", kind: Kind::String, check: Some(check_until) },
+    Field { name: "output", kind: Kind::String, check: None },
+    Field { name: "webhook", kind: Kind::String, check: Some(check_webhook) },
+    Field { name: "command", kind: Kind::StringArray, check: Some(check_command) },
+];
+
+// cron 表达式必须能够解析
+fn check_cron(value: &DeValue) -> Result<(), String> {
+    schedule::Cron::parse(value.as_str().unwrap_or_default()).map(|_| ())
+}
+
+// task 必须是支持的报告或导出
+fn check_task(value: &DeValue) -> Result<(), String> {
+    let task = value.as_str().unwrap_or_default();
+    if schedule::TASKS.contains(&task) {
+        return Ok(());
+    }
+    match suggest(task, schedule::TASKS) {
+        Some(s) => Err(format!("不支持的任务 \"{}\"，你是不是想写 \"{}\"？", task, s)),
+        None => Err(format!("不支持的任务 \"{}\"，可选值: {}", task, schedule::TASKS.join("/"))),
+    }
+}
+
+// until 只能是 today 或 yesterday，定时任务没有固定的日期
+fn check_until(value: &DeValue) -> Result<(), String> {
+    match value.as_str() {
+        Some("today" | "yesterday") => Ok(()),
+        _ => Err("until 可选值: today/yesterday".to_string()),
+    }
+}
+
+// [[schedule]] 定时任务必须写出 cron、task 和 output
+fn check_schedules(rules: &[Spanned<DeValue>]) -> Vec<(usize, String)> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.get_ref().as_table().map(|table| (rule.span().start, table)))
+        .filter_map(|(offset, table)| {
+            let missing = ["cron", "task", "output"].into_iter().find(|name| !table.contains_key(*name))?;
+            Some((offset, format!("[[schedule]] 定时任务需要写出 {}", missing)))
+        })
+        .collect()
+}
+
+// [memory] 表的定义
+const MEMORY_SCHEMA: &[Field] = &[
+    Field { name: "cache_kb", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "max_processes", kind: Kind::Integer, check: Some(check_non_negative) },
+];
+
+// [pulse] 表的定义
+const PULSE_SCHEMA: &[Field] = &[
+    Field { name: "enabled", kind: Kind::Boolean, check: None },
+    Field { name: "interval_seconds", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "merge_seconds", kind: Kind::Integer, check: Some(check_non_negative) },
+];
+
+// 合并窗口不大于发送间隔时，每两条状态心跳之间都会被当作中断
+fn check_pulse(table: &DeTable) -> Vec<(usize, String)> {
+    let get = |name| table.get(name).map(|v| (v.span().start, v.get_ref().as_integer().and_then(|i| i.as_str().parse::<i64>().ok())));
+    let (interval, merge) = (get("interval_seconds"), get("merge_seconds"));
+    let Some((offset, _)) = merge.or(interval) else {
+        return Vec::new(); // 都没有写时使用默认值
+    };
+    match (interval.map_or(Some(10), |(_, v)| v), merge.map_or(Some(15), |(_, v)| v)) {
+        (Some(interval), Some(merge)) if merge <= interval => vec![(
+            offset,
+            format!("`pulse.merge_seconds` ({}) 应大于 `pulse.interval_seconds` ({})，否则每两条状态心跳之间都会被当作中断", merge, interval),
+        )],
+        _ => Vec::new(), // 类型错误已由类型检查报告
+    }
+}
+
+// [hud] 表的定义
+const HUD_SCHEMA: &[Field] = &[
+    Field { name: "enabled", kind: Kind::Boolean, check: None },
+    Field { name: "corner", kind: Kind::String, check: Some(check_corner) },
+];
+
+// 浮窗只能放在屏幕的四个角
+fn check_corner(value: &DeValue) -> Result<(), String> {
+    let name = value.as_str().unwrap_or_default();
+    let corners = ["top-left", "top-right", "bottom-left", "bottom-right"];
+    if corners.contains(&name) {
+        return Ok(());
+    }
+    match suggest(name, &corners) {
+        Some(s) => Err(format!("不支持的位置 \"{}\"，你是不是想写 \"{}\"？", name, s)),
+        None => Err(format!("不支持的位置 \"{}\"，可选值: {}", name, corners.join("/"))),
+    }
+}
+
+// [enrichment] 表的定义
+const ENRICHMENT_SCHEMA: &[Field] = &[
+    Field { name: "signer", kind: Kind::Boolean, check: None },
+    Field { name: "concurrency", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "timeout_seconds", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "queue", kind: Kind::Integer, check: Some(check_positive) },
+];
+
+// [alarm] 表的定义
+const ALARM_SCHEMA: &[Field] = &[
+    Field { name: "no_events_minutes", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "sink_error_percent", kind: Kind::Integer, check: Some(check_percent) },
+    Field { name: "queue_drops", kind: Kind::Boolean, check: None },
+    Field { name: "window_minutes", kind: Kind::Integer, check: Some(check_positive) },
+    Field { name: "toast", kind: Kind::Boolean, check: None },
+    Field { name: "webhook", kind: Kind::String, check: Some(check_webhook) },
+    Field { name: "command", kind: Kind::StringArray, check: Some(check_command) },
+];
+
+// 百分比在 0 到 100 之间
+fn check_percent(value: &DeValue) -> Result<(), String> {
+    match value.as_integer().and_then(|i| i.as_str().parse::<i64>().ok()) {
+        Some(percent) if (0..=100).contains(&percent) => Ok(()),
+        _ => Err("应为 0 到 100 之间的整数".to_string()),
+    }
+}
+
+// [http] 表的定义
+#[cfg(feature = "http")]
+const HTTP_SCHEMA: &[Field] = &[
+    Field { name: "enabled", kind: Kind::Boolean, check: None },
+    Field { name: "listen", kind: Kind::String, check: Some(check_listen) },
+    Field { name: "buffer", kind: Kind::Integer, check: Some(check_non_negative) },
+];
+
+// 监听地址必须写成 IP:端口
+#[cfg(feature = "http")]
+fn check_listen(value: &DeValue) -> Result<(), String> {
+    let listen = value.as_str().unwrap_or_default();
+    match listen.parse::<std::net::SocketAddr>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("无效的监听地址 \"{}\"，应写成 IP:端口，如 \"127.0.0.1:5600\"", listen)),
+    }
+}
+
+// [minimal] 表的定义
+const MINIMAL_SCHEMA: &[Field] = &[
+    Field { name: "interval_ms", kind: Kind::Integer, check: Some(check_minimal_interval) },
+    Field { name: "apps", kind: Kind::StringArray, check: None },
+];
+
+// [filter] 表的定义
+const FILTER_SCHEMA: &[Field] = &[
+    Field { name: "styles", kind: Kind::StringArray, check: Some(check_styles) },
+    Field { name: "ex_styles", kind: Kind::StringArray, check: Some(check_ex_styles) },
+    Field { name: "min_size", kind: Kind::Integer, check: Some(check_non_negative) },
+];
+
+// [splash] 表的定义
+const SPLASH_SCHEMA: &[Field] = &[
+    Field { name: "grace_ms", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "startup_seconds", kind: Kind::Integer, check: Some(check_positive) },
+];
+
+// 窗口样式必须是支持的名称
+fn check_styles(value: &DeValue) -> Result<(), String> {
+    check_style_names(value, filter::STYLES)
+}
+
+// 扩展窗口样式必须是支持的名称
+fn check_ex_styles(value: &DeValue) -> Result<(), String> {
+    check_style_names(value, filter::EX_STYLES)
+}
+
+fn check_style_names(value: &DeValue, table: &[(u32, &str)]) -> Result<(), String> {
+    let names: Vec<&str> = table.iter().map(|(_, name)| *name).collect();
+    for style in value.as_array().into_iter().flatten().filter_map(|v| v.get_ref().as_str()) {
+        if names.contains(&style) {
+            continue;
+        }
+        return match suggest(style, &names) {
+            Some(s) => Err(format!("不支持的样式 \"{}\"，你是不是想写 \"{}\"？", style, s)),
+            None => Err(format!("不支持的样式 \"{}\"，可选值: {}", style, names.join("/"))),
+        };
+    }
+    Ok(())
+}
+
+// 记录方式必须是支持的名称
+fn check_capture(value: &DeValue) -> Result<(), String> {
+    let name = value.as_str().unwrap_or_default();
+    let modes = ["full", "minimal"];
+    if modes.contains(&name) {
+        return Ok(());
+    }
+    match suggest(name, &modes) {
+        Some(s) => Err(format!("不支持的记录方式 \"{}\"，你是不是想写 \"{}\"？", name, s)),
+        None => Err(format!("不支持的记录方式 \"{}\"，可选值: {}", name, modes.join("/"))),
+    }
+}
+
+// 发现窗口切换的方式必须是支持的名称
+fn check_backend(value: &DeValue) -> Result<(), String> {
+    let name = value.as_str().unwrap_or_default();
+    let backends = ["poll", "hybrid"];
+    if backends.contains(&name) {
+        return Ok(());
+    }
+    match suggest(name, &backends) {
+        Some(s) => Err(format!("不支持的方式 \"{}\"，你是不是想写 \"{}\"？", name, s)),
+        None => Err(format!("不支持的方式 \"{}\"，可选值: {}", name, backends.join("/"))),
+    }
+}
+
+// 精简模式的意义在于降低开销，轮询间隔不能小于1秒
+fn check_minimal_interval(value: &DeValue) -> Result<(), String> {
+    match value.as_integer().and_then(|i| i.as_str().parse::<i64>().ok()) {
+        Some(ms) if ms >= 1000 => Ok(()),
+        _ => Err("精简模式的轮询间隔不能小于1000毫秒".to_string()),
+    }
+}
+
+// [archive] 表的定义
+const ARCHIVE_SCHEMA: &[Field] = &[
+    Field { name: "compression", kind: Kind::String, check: Some(check_compression) },
+    Field { name: "level", kind: Kind::Integer, check: None },
+];
+
+// [maintenance] 表的定义
+const MAINTENANCE_SCHEMA: &[Field] = &[
+    Field { name: "events_days", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "logs_days", kind: Kind::Integer, check: Some(check_non_negative) },
+    Field { name: "interval_hours", kind: Kind::Integer, check: Some(check_non_negative) },
+];
+
+// [fields] 表的定义
+const FIELDS_SCHEMA: &[Field] = &[
+    Field { name: "journal", kind: Kind::StringArray, check: Some(check_fields) },
+    #[cfg(feature = "http")]
+    Field { name: "http", kind: Kind::StringArray, check: Some(check_fields) },
+];
+
+// 字段名必须是记录中出现的字段
+fn check_fields(value: &DeValue) -> Result<(), String> {
+    let names = value.as_array().into_iter().flatten().filter_map(|v| v.get_ref().as_str());
+    for name in names {
+        if sink::FIELDS.contains(&name) || sink::KEY_FIELDS.contains(&name) {
+            continue;
+        }
+        return match suggest(name, sink::FIELDS) {
+            Some(s) => Err(format!("未知的字段 \"{}\"，你是不是想写 \"{}\"？", name, s)),
+            None => Err(format!("未知的字段 \"{}\"，可选值: {}", name, sink::FIELDS.join("/"))),
+        };
+    }
+    Ok(())
+}
+
+// 应用信息文件中每个程序的表的定义
+const APP_SCHEMA: &[Field] = &[
+    Field { name: "name", kind: Kind::String, check: None },
+    Field { name: "color", kind: Kind::String, check: Some(check_color) },
+    Field { name: "icon", kind: Kind::String, check: None },
+    Field { name: "category", kind: Kind::String, check: None },
+];
+
+// 颜色必须写成 #RRGGBB
+fn check_color(value: &DeValue) -> Result<(), String> {
+    let color = value.as_str().unwrap_or_default();
+    let valid = color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("无效的颜色 \"{}\"，应写成 #RRGGBB，如 \"#6264A7\"", color))
+    }
+}
+
+// 发送间隔等必须大于0
+fn check_positive(value: &DeValue) -> Result<(), String> {
+    match value.as_integer().and_then(|i| i.as_str().parse::<i64>().ok()) {
+        Some(n) if n > 0 => Ok(()),
+        _ => Err("必须大于0".to_string()),
+    }
+}
+
+// 天数、小时数等不能为负数
+fn check_non_negative(value: &DeValue) -> Result<(), String> {
+    match value.as_integer().and_then(|i| i.as_str().parse::<i64>().ok()) {
+        Some(n) if n >= 0 => Ok(()),
+        _ => Err("不能为负数".to_string()),
+    }
+}
+
+// 压缩算法必须是支持的名称
+fn check_compression(value: &DeValue) -> Result<(), String> {
+    let name = value.as_str().unwrap_or_default();
+    let algorithms = ["gzip", "zstd", "none"];
+    if algorithms.contains(&name) {
+        return Ok(());
+    }
+    match suggest(name, &algorithms) {
+        Some(s) => Err(format!("不支持的压缩算法 \"{}\"，你是不是想写 \"{}\"？", name, s)),
+        None => Err(format!("不支持的压缩算法 \"{}\"，可选值: {}", name, algorithms.join("/"))),
+    }
+}
+
+// 压缩级别的有效范围取决于压缩算法
+fn check_archive(table: &DeTable) -> Vec<(usize, String)> {
+    let Some(level) = table.get("level") else {
+        return Vec::new();
+    };
+    let compression = table.get("compression").and_then(|v| v.get_ref().as_str()).unwrap_or("gzip");
+    let value = level.get_ref().as_integer().and_then(|i| i.as_str().parse::<i64>().ok());
+    let message = match (compression, value) {
+        ("gzip", Some(0..=9)) | ("none", _) => return Vec::new(),
+        ("zstd", Some(1)) => return Vec::new(),
+        ("gzip", _) => "gzip 的压缩级别应为 0-9",
+        ("zstd", _) => "当前使用的纯Rust zstd 编码器只支持压缩级别 1",
+        _ => return Vec::new(), // 算法名称本身有误，已由 compression 的检查报告
+    };
+    vec![(level.span().start, format!("`archive.level`: {}", message))]
+}
+
+// 轮询间隔必须大于0
+fn check_interval(value: &DeValue) -> Result<(), String> {
+    match value.as_integer().and_then(|i| i.as_str().parse::<i64>().ok()) {
+        Some(ms) if ms > 0 => Ok(()),
+        _ => Err("轮询间隔必须是大于0的毫秒数".to_string()),
+    }
+}
+
+// 日志级别必须是log库认识的名称
+fn check_log_level(value: &DeValue) -> Result<(), String> {
+    let level = value.as_str().unwrap_or_default();
+    if level.parse::<LevelFilter>().is_ok() {
+        return Ok(());
+    }
+    let levels = ["off", "error", "warn", "info", "debug", "trace"];
+    match suggest(&level.to_lowercase(), &levels) {
+        Some(s) => Err(format!("无效的日志级别 \"{}\"，你是不是想写 \"{}\"？", level, s)),
+        None => Err(format!("无效的日志级别 \"{}\"，可选值: {}", level, levels.join("/"))),
+    }
+}
+
+// init 子命令生成的默认配置文件，内容应与 Config::default() 保持一致
+const DEFAULT_CONFIG: &str = r#"# ForegroundWatcher 配置文件
+# 所有配置项都可以省略，省略时使用下面注释中的默认值。
+
+# 轮询间隔（毫秒），越小越能及时发现窗口切换，占用的CPU也越多
+interval_ms = 10
+
+# 发现窗口切换的方式：poll 按 interval_ms 轮询；hybrid 以 WinEvent 钩子为主，切换后立即记录，
+# 另外每隔 verify_seconds 秒轮询一次核对，发现钩子漏报时写一条纠正记录并补记实际的前台窗口
+backend = "poll"
+verify_seconds = 5
+
+# 允许系统推迟每次轮询唤醒的最长时间（毫秒）。系统会把多个程序的唤醒合并到一起，
+# CPU可以在轮询间隙进入更深的节能状态；设为0则每次都按时唤醒
+timer_tolerance_ms = 5
+
+# 是否允许系统临时提升本程序的优先级。监视程序不需要及时响应，默认关闭，避免与前台程序争抢CPU
+priority_boost = false
+
+# 日志级别：off/error/warn/info/debug/trace
+log_level = "info"
+
+# Win32跟踪模式，记录每一次轮询结果及其被过滤的原因，等同于命令行参数 --trace-win32
+trace_win32 = false
+
+# 记录方式：full 完整记录；minimal 精简记录，只记录前台程序的可执行文件路径，
+# 不读取窗口标题、版本信息等，轮询间隔不小于1秒，适合运行对延迟敏感的游戏时使用
+capture = "full"
+
+# 每隔多少分钟在事件记录中写一条心跳，报告内存占用和缓存大小，0表示不写
+heartbeat_minutes = 10
+
+# 是否检测窗口标题的语言，写入记录的 lang（如 zh、en、ja）。只按文字和常见虚词判断，
+# 标题太短或判断不出时不写；隐私浏览窗口和精简模式下不检测
+detect_language = false
+
+# 与上一次切换相隔不到这么多毫秒的切换（如按住 Alt+Tab 依次经过几个窗口）分为一组，
+# 组内除第一条以外的记录写入 burst，为这一组第一条记录的ID，时间也与第一条相同，分析时可以把整组当作一次切换。0表示不分组
+burst_ms = 0
+
+# 是否记录切换到本程序自己的窗口（运行监视的控制台窗口、浮窗），等同于命令行参数 --track-self。默认不记录，
+# 免得查看一下输出就在统计中多出 foregroundwatcher.exe；切换到这些窗口时，时长仍计入之前的窗口
+track_self = false
+
+# 内存上限，保证长时间运行时内存不会持续增长
+[memory]
+# 内部缓存（如各程序的版本信息）估算占用的上限（KB），超出时淘汰最久没有使用的项
+cache_kb = 1024
+# 进程信息表最多保留的进程数，超出时清空重建
+max_processes = 256
+
+# 状态心跳模式：不再在窗口切换时写一条记录，而是发送当前前台窗口的状态（"kind": "pulse"），
+# 窗口切换时立即发送，之后每隔 interval_seconds 秒重复发送一次。与上一条状态相同、间隔不超过
+# merge_seconds 秒的状态心跳合并为一段，适合对接以 bucket 和 heartbeat 组织数据的时间统计服务
+[pulse]
+enabled = false
+interval_seconds = 10
+# 合并窗口（秒），应大于 interval_seconds
+merge_seconds = 15
+
+# 浮窗：在屏幕一角总在最前显示当前应用和它今天在前台的时长，实时更新。
+# 浮窗不接收鼠标点击（点击会穿透到下面的窗口），也不会成为前台窗口，不影响记录
+[hud]
+enabled = false
+# 放在哪个角：top-left、top-right、bottom-left、bottom-right
+corner = "top-right"
+
+# 补充信息：耗时较长的信息在窗口切换记录写入之后在后台查询，查出后另写一条 "kind": "enrichment" 的记录，
+# 不拖慢窗口切换记录。每个可执行文件只查询一次，结果缓存在内存中（计入 [memory] cache_kb）
+[enrichment]
+# 验证可执行文件的数字签名，记录签名是否有效（signature）和签名者（signer）
+signer = false
+# 同时运行的任务数
+concurrency = 2
+# 单个任务的超时（秒），超时的结果不写入记录
+timeout_seconds = 10
+# 最多排队的任务数，频繁切换窗口导致排满时丢弃新任务
+queue = 64
+
+# 健康告警：监视程序自身出现异常时弹出系统通知，并可以发送 webhook 或执行程序，当天就能发现记录中断。
+# 各项条件默认不开启。同一个条件只在刚成立时告警一次，恢复正常后再次成立才会再告警
+[alarm]
+# 正在使用电脑（1分钟内有键盘鼠标输入）却连续这么多分钟没有成功取得前台窗口时告警，0表示不检查。
+# 长时间只用一个窗口时每次轮询仍然取得前台窗口，不会告警
+no_events_minutes = 0
+# 统计窗口内写入失败的比例超过这个百分比时告警，0表示不检查
+sink_error_percent = 0
+# 统计窗口内有输出目标处理不过来而丢弃记录时告警
+queue_drops = false
+# 统计写入失败和丢弃的窗口（分钟）
+window_minutes = 10
+toast = true
+# 以 POST 发送 {"kind": "alarm", "alarm": 条件, "time": 时间, "message": 说明}，只支持 http://
+# webhook = "http://127.0.0.1:8123/api/webhook/watcher"
+# 执行的程序可以从环境变量 FW_ALARM、FW_TIME、FW_MESSAGE 得到告警的内容
+# command = ["powershell", "-File", 'C:\Scripts\alarm.ps1']
+
+# 本地HTTP接口，供面板等实时获取记录：
+#   GET /events/stream                  Server-Sent Events，支持 Last-Event-ID 断线续传
+#   GET /events/poll?after=N&timeout=S  长轮询，返回序号大于N的记录，没有时最多等待S秒
+#   GET /events?from=&to=&app=&category=&order=&limit=&cursor=  按条件分页查询已经写入的记录
+#   GET /stats                          今天各应用的前台时长
+[http]
+enabled = false
+# 监听地址，记录中包含窗口标题，除非确有需要，不要监听 127.0.0.1 以外的地址
+listen = "127.0.0.1:5600"
+# 保留最近多少条记录，供断线重连和长轮询补发
+buffer = 1000
+
+# 精简模式
+[minimal]
+# 精简模式下的轮询间隔（毫秒），不小于1000
+interval_ms = 1000
+# 这些程序在前台时自动切换到精简模式，切换到其他程序后恢复，如 ["eldenring.exe", "cs2.exe"]
+apps = []
+
+# 按窗口样式和大小过滤：工具提示、输入法候选窗口等短暂出现的窗口偶尔会成为前台窗口，
+# 过滤掉的窗口不写记录，这段时间仍计入之前的窗口。可以先用 inspect 查看窗口的样式
+[filter]
+# 带有其中任一样式的窗口不记录，可选 WS_POPUP/WS_CHILD/WS_MINIMIZE/WS_VISIBLE/WS_DISABLED/WS_MAXIMIZE/WS_CAPTION/WS_THICKFRAME
+styles = []
+# 带有其中任一扩展样式的窗口不记录，如 ["WS_EX_TOOLWINDOW", "WS_EX_NOACTIVATE"]，
+# 可选 WS_EX_TOPMOST/WS_EX_TRANSPARENT/WS_EX_TOOLWINDOW/WS_EX_APPWINDOW/WS_EX_LAYERED/WS_EX_NOACTIVATE
+ex_styles = []
+# 宽或高小于这么多像素的窗口不记录，0表示不检查
+min_size = 0
+
+# 启动窗口：程序启动时先显示的启动画面、加载窗口很快就会被主窗口取代。新启动的程序的窗口先保留 grace_ms，
+# 期间切换到同一程序的其他窗口时不单独记录，时间并入之后的窗口
+[splash]
+# 保留的时间（毫秒），0表示不检查。开启后新启动的程序的记录会推迟这么久写入
+grace_ms = 0
+# 进程启动后这么多秒以内出现的窗口才按启动窗口检查
+startup_seconds = 10
+
+# 归档压缩：每天的运行日志和事件记录在第二天（或下次启动时）自动压缩
+[archive]
+# 压缩算法：gzip/zstd/none
+compression = "gzip"
+# 压缩级别，gzip为0-9；zstd目前只支持1
+level = 6
+
+# 保留期限和定期维护，也可以用 maintain 子命令手动执行
+[maintenance]
+# 事件记录保留天数，0表示永久保留
+events_days = 0
+# 运行日志和崩溃报告保留天数，0表示永久保留
+logs_days = 30
+# 监视程序运行时每隔多少小时自动维护一次，0表示不自动维护
+interval_hours = 24
+
+# 各个输出目标接收的字段，省略或留空表示接收全部字段，time 和 kind 总是保留。
+# 可以让风险较高的输出目标只拿到最少的数据，如本地HTTP接口只接收可执行文件和应用标识：
+#   http = ["exe", "app"]
+# 事件记录文件去掉 exe 后，report 子命令无法按分类统计
+[fields]
+journal = []
+
+# 应用别名：路径匹配正则表达式（不区分大小写）的程序都归为同一个应用，按顺序取第一条匹配的规则。
+# 没有匹配的规则时，路径中的版本号目录（如 app-1.2.3）会被视为同一个，升级后仍算同一个应用。
+# [[alias]]
+# pattern = '\\Discord\\app-[^\\]+\\Discord\.exe$'
+# app = "Discord"
+
+# 记录前台进程的环境变量：只记录路径匹配正则表达式（不区分大小写）的程序的、列出的变量，用于区分
+# 同一个工具连接不同环境的多个实例。默认不记录任何环境变量。
+# [[environment]]
+# pattern = '\\Code\.exe$'
+# variables = ["KUBECONFIG", "AWS_PROFILE"]
+
+# 工作时间（本地时间）：设置后 report 的各项时长都会拆分为工作时间内和工作时间外。
+# days 省略时为周一到周五，可以写 mon、tue、wed、thu、fri、sat、sun；end 不含，跨过午夜的时间段请拆成两段。
+# [[work_hours]]
+# start = "09:00"
+# end = "12:00"
+#
+# [[work_hours]]
+# start = "13:30"
+# end = "18:00"
+
+# 触发规则：每天第一次切换到路径匹配正则表达式（不区分大小写）的程序时，以 POST 向 webhook 发送JSON
+# （只支持 http://），或执行 command，程序可以从环境变量 FW_TRIGGER、FW_TIME、FW_APP、FW_EXE 得到触发的应用。
+# [[trigger]]
+# name = "outlook"
+# pattern = '\\OUTLOOK\.EXE$'
+# webhook = "http://127.0.0.1:8123/api/webhook/outlook"
+# command = ["powershell", "-File", 'C:\Scripts\standup.ps1']
+
+# 定时任务：监视程序运行期间按 cron 表达式（分 时 日 月 星期，本地时间）生成报告或导出并写入 output，
+# 不需要为每个报告在任务计划程序中添加一项。task 可选 report apps、report quality、export daily-matrix、
+# export perfetto、export dot；导出 until（yesterday 或 today）为止的 days 天，output 中的 {date} 替换为 until 的日期，
+# 相对路径相对于数据目录。完成后可以发送 webhook 或执行 command（如发送邮件的脚本），
+# 程序可以从环境变量 FW_SCHEDULE、FW_TIME、FW_OUTPUT 得到任务名和写入的文件。监视程序没有运行时错过的任务不会补做。
+# [[schedule]]
+# name = "weekly"
+# cron = "0 9 * * mon"
+# task = "export daily-matrix"
+# days = 7
+# output = 'reports\week-{date}.csv'
+# command = ["powershell", "-File", 'C:\Scripts\mail-report.ps1']
+"#;
+
+// 把带注释的默认配置写入指定路径
+// 文件已存在时只有 force 为 true 才会覆盖
+pub fn init(path: &Path, force: bool) -> Result<(), Error> {
+    if path.exists() && !force {
+        return Err(Error::Config(ConfigError::Exists(path.to_path_buf())));
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?; // 首次使用时配置目录可能不存在
+    }
+    std::fs::write(path, default_config())?;
+    Ok(())
+}
+
+// 默认配置文件的内容，去掉构建时没有开启的功能对应的部分，否则生成的文件无法通过校验
+fn default_config() -> String {
+    let mut text = DEFAULT_CONFIG.to_string();
+    if !cfg!(feature = "http") {
+        remove_section(&mut text, "# 本地HTTP接口");
+    }
+    text
+}
+
+// 删除从 marker 所在行开始到下一个空行为止的一段
+fn remove_section(text: &mut String, marker: &str) {
+    if let Some(start) = text.find(marker) {
+        let end = text[start..].find("\n\n").map_or(text.len(), |i| start + i + 2);
+        text.replace_range(start..end, "");
+    }
+}
+
+// 读取并校验配置文件
+// required 为 true 表示路径是用户通过 --config 明确指定的，此时文件不存在也要报错；
+// 否则默认路径下没有文件时使用默认配置
+pub fn load(path: &Path, required: bool) -> Result<Config, Error> {
+    if !path.exists() {
+        if required {
+            return Err(Error::Config(ConfigError::Missing { file: ConfigFile::Config, path: path.to_path_buf() }));
+        }
+        return Ok(Config::default()); // 没有配置文件，全部使用默认值
+    }
+    let text = read(ConfigFile::Config, path)?;
+    parse(&text, |table, problems| validate_table(&text, table, SCHEMA, "", problems))
+        .map_err(|problems| Error::Config(ConfigError::Invalid { file: ConfigFile::Config, path: path.to_path_buf(), problems }))
+}
+
+// 读取并校验应用信息文件，文件不存在时返回空表
+// 文件中每个表的键为可执行文件名，内容按 APP_SCHEMA 校验
+pub fn load_apps<T: DeserializeOwned>(path: &Path) -> Result<BTreeMap<String, T>, Error> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = read(ConfigFile::Apps, path)?;
+    parse(&text, |table, problems| validate_apps(&text, table, problems))
+        .map_err(|problems| Error::Config(ConfigError::Invalid { file: ConfigFile::Apps, path: path.to_path_buf(), problems }))
+}
+
+// 读取配置文件或应用信息文件的全部内容
+fn read(file: ConfigFile, path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|source| Error::Config(ConfigError::Unreadable { file, path: path.to_path_buf(), source }))
+}
+
+// 在应用信息文件中为程序设置分类，保留文件中原有的内容和注释：已有这个程序的表时在表头下面加一行，没有时在文件末尾追加一个表
+pub fn set_app_category(path: &Path, exe: &str, category: &str) -> Result<(), Error> {
+    let text = if path.exists() {
+        read(ConfigFile::Apps, path)?
+    } else {
+        String::new()
+    };
+    let table = DeTable::parse(&text)
+        .map_err(|e| Error::Config(ConfigError::Invalid { file: ConfigFile::Apps, path: path.to_path_buf(), problems: vec![syntax_problem(&text, &e)] }))?;
+    let line = format!("category = {}", toml::Value::String(category.to_string()));
+    let existing = table.get_ref().iter().find(|(key, _)| key.get_ref().eq_ignore_ascii_case(exe)).map(|(key, _)| key.span());
+    let updated = match existing {
+        Some(span) => {
+            let line_start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            if !text[line_start..span.start].trim_start().starts_with('[') {
+                return Err(Error::Config(ConfigError::NotTable(exe.to_string())));
+            }
+            let line_end = text[span.end..].find('\n').map_or(text.len(), |i| span.end + i);
+            format!("{}\n{}{}", &text[..line_end], line, &text[line_end..])
+        }
+        None => {
+            let separator = match text.as_str() {
+                "" => "",
+                t if t.ends_with("\n\n") => "",
+                t if t.ends_with('\n') => "\n",
+                _ => "\n\n",
+            };
+            format!("{}{}[{}]\n{}\n", text, separator, toml::Value::String(exe.to_string()), line)
+        }
+    };
+    // 写入之前确认修改后的文件仍然有效，避免把监视程序正在使用的应用信息文件改坏
+    parse::<BTreeMap<String, toml::Value>>(&updated, |table, problems| validate_apps(&updated, table, problems))
+        .map_err(|problems| Error::Config(ConfigError::Edited(problems)))?;
+    std::fs::write(path, updated).map_err(|source| Error::Config(ConfigError::Unwritable { path: path.to_path_buf(), source }))?;
+    Ok(())
+}
+
+// 解析TOML文本，先用 validate 按结构定义校验，全部通过后再反序列化
+// 有误时返回按行号排序的全部问题
+fn parse<T: DeserializeOwned>(text: &str, validate: impl Fn(&DeTable, &mut Vec<Problem>)) -> Result<T, Vec<Problem>> {
+    let table = DeTable::parse(text).map_err(|e| vec![syntax_problem(text, &e)])?;
+
+    let mut problems = Vec::new();
+    validate(table.get_ref(), &mut problems);
+    if !problems.is_empty() {
+        problems.sort_by_key(|p| p.line); // 按出现位置排序，方便对照修改
+        return Err(problems);
+    }
+
+    toml::from_str(text).map_err(|e| {
+        let line = e.span().map_or(1, |span| line_of(text, span.start));
+        vec![Problem { line, message: e.message().to_string() }]
+    })
+}
+
+// TOML语法错误所在的行
+fn syntax_problem(text: &str, e: &toml::de::Error) -> Problem {
+    let line = e.span().map_or(1, |span| line_of(text, span.start));
+    Problem { line, message: format!("TOML语法错误: {}", e.message()) }
+}
+
+// 校验应用信息文件：每一项都必须是表，且同一个程序（不区分大小写）只能出现一次
+fn validate_apps(text: &str, table: &DeTable, problems: &mut Vec<Problem>) {
+    let mut lines: BTreeMap<String, Vec<usize>> = BTreeMap::new(); // 小写的程序名 -> 出现的行号
+    for (key, value) in table.iter() {
+        let name = key.get_ref().as_ref();
+        let line = line_of(text, key.span().start);
+        lines.entry(name.to_lowercase()).or_default().push(line);
+        match value.get_ref().as_table() {
+            Some(sub) => validate_table(text, sub, APP_SCHEMA, &format!("\"{}\".", name), problems),
+            None => problems.push(Problem {
+                line,
+                message: format!("`{}` 应为表，如 [\"{}\"]，实际为{}", name, name, value_type_name(value.get_ref())),
+            }),
+        }
+    }
+    for (name, mut found) in lines {
+        found.sort();
+        for line in found.iter().skip(1) {
+            problems.push(Problem {
+                line: *line,
+                message: format!("{} 与第{}行重复（程序名不区分大小写），请合并成一项", name, found[0]),
+            });
+        }
+    }
+}
+
+// 按结构定义校验一个表，发现的问题追加到 problems 中
+// prefix 为表在配置中的路径（如 "archive."），用于拼出完整键名
+fn validate_table(text: &str, table: &DeTable, schema: &[Field], prefix: &str, problems: &mut Vec<Problem>) {
+    for (key, value) in table.iter() {
+        let key_name = key.get_ref().as_ref();
+        let name = format!("{}{}", prefix, key_name);
+        let line = line_of(text, key.span().start);
+        let Some(field) = schema.iter().find(|f| f.name == key_name) else {
+            let names: Vec<&str> = schema.iter().map(|f| f.name).collect();
+            let message = match suggest(key_name, &names) {
+                Some(s) => format!("未知配置项 `{}`，你是不是想写 `{}{}`？", name, prefix, s),
+                None => format!("未知配置项 `{}`，可用的配置项: {}", name, names.join(", ")),
+            };
+            problems.push(Problem { line, message });
+            continue;
+        };
+        let value_line = line_of(text, value.span().start);
+        if !field.kind.matches(value.get_ref()) {
+            problems.push(Problem {
+                line: value_line,
+                message: format!("`{}` 应为{}，实际为{}", name, field.kind.name(), value_type_name(value.get_ref())),
+            });
+            continue;
+        }
+        if let Some(check) = field.check {
+            if let Err(message) = check(value.get_ref()) {
+                problems.push(Problem { line: value_line, message: format!("`{}`: {}", name, message) });
+            }
+        }
+        if let (Kind::Table(fields, table_check), Some(sub)) = (&field.kind, value.get_ref().as_table()) {
+            validate_table(text, sub, fields, &format!("{}.", name), problems);
+            if let Some(table_check) = table_check {
+                for (offset, message) in table_check(sub) {
+                    problems.push(Problem { line: line_of(text, offset), message });
+                }
+            }
+        }
+        if let (Kind::TableArray(fields, array_check), Some(items)) = (&field.kind, value.get_ref().as_array()) {
+            for item in items.iter() {
+                if let Some(sub) = item.get_ref().as_table() {
+                    validate_table(text, sub, fields, &format!("{}.", name), problems);
+                }
+            }
+            if let Some(array_check) = array_check {
+                for (offset, message) in array_check(items) {
+                    problems.push(Problem { line: line_of(text, offset), message });
+                }
+            }
+        }
+    }
+}
+
+// TOML值类型的中文名称
+fn value_type_name(value: &DeValue) -> &'static str {
+    match value {
+        DeValue::String(_) => "字符串",
+        DeValue::Integer(_) => "整数",
+        DeValue::Float(_) => "浮点数",
+        DeValue::Boolean(_) => "布尔值",
+        DeValue::Datetime(_) => "日期时间",
+        DeValue::Array(_) => "数组",
+        DeValue::Table(_) => "表",
+    }
+}
+
+// 计算字节偏移所在的行号（从1开始）
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+// 在候选项中找出与输入最接近的一个，用于拼写错误提示
+fn suggest<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|c| (edit_distance(input, c), *c))
+        .filter(|(d, c)| *d <= (c.len() / 3).max(1)) // 差异太大的不提示
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+// 两个字符串之间的编辑距离（Levenshtein）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect(); // 上一行的距离
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0]; // 左上角的值
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb { prev } else { 1 + prev.min(row[j]).min(row[j + 1]) };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+use crate::clock::SharedClock; // 记录崩溃时间
+
+// 安装panic钩子，程序崩溃时在崩溃报告目录写入一份包含调用栈的报告
+pub fn install_handler(dir: PathBuf, clock: SharedClock) {
+    let default_hook = std::panic::take_hook(); // 保留默认行为，继续把错误输出到控制台
+    std::panic::set_hook(Box::new(move |info| {
+        let now = clock.now();
+        let path = dir.join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")));
+        let report = format!(
+            "时间: {}\n版本: {}\n{}\n\n调用栈:\n{}\n",
+            now.to_rfc3339(),
+            env!("CARGO_PKG_VERSION"),
+            info,
+            std::backtrace::Backtrace::force_capture()
+        );
+        let _ = std::fs::write(&path, report); // 崩溃时写报告失败也没有更好的处理办法，忽略
+        default_hook(info);
+    }));
+}
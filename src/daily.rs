@@ -0,0 +1,107 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::NaiveDate; // 按本地日期切换文件
+
+use crate::archive::{self, ArchiveConfig}; // 切换文件后压缩归档
+use crate::clock::SharedClock; // 写入日志时的日期
+
+// 按天切换的追加写入文件，文件名为 {prefix}YYYY-MM-DD.{extension}
+// 日期前进到新的一天后，把之前的文件压缩归档；日期更早的迟到内容追加到对应的文件，不切换当前文件
+pub struct DailyFile {
+    dir: PathBuf,                       // 文件所在目录
+    prefix: &'static str,               // 文件名中日期之前的部分
+    extension: &'static str,            // 文件扩展名
+    archive: ArchiveConfig,             // 归档压缩设置
+    current: Option<(NaiveDate, File)>, // 当前打开的文件及其日期
+    clock: SharedClock,                 // 确定“今天”的时间来源
+}
+
+impl DailyFile {
+    pub fn new(dir: PathBuf, prefix: &'static str, extension: &'static str, archive: ArchiveConfig, clock: SharedClock) -> DailyFile {
+        DailyFile { dir, prefix, extension, archive, current: None, clock }
+    }
+
+    // 指定日期对应的文件名
+    fn file_name(&self, date: NaiveDate) -> String {
+        format!("{}{}.{}", self.prefix, date.format("%Y-%m-%d"), self.extension)
+    }
+
+    // 向指定日期的文件追加内容，日期前进时切换文件
+    // 早于当前文件和今天的日期（跨午夜的连续切换组、被调回的系统时钟）按迟到的内容处理，
+    // 与归档互斥地写入对应的文件，当前文件和今天的文件不会因此被压缩
+    pub fn append(&mut self, date: NaiveDate, data: &[u8]) -> std::io::Result<()> {
+        let newest = self.current.as_ref().map_or(self.clock.today(), |(d, _)| (*d).max(self.clock.today()));
+        if date < newest && self.current.as_ref().is_none_or(|(d, _)| *d != date) {
+            return archive::append_late(&self.dir.join(self.file_name(date)), data);
+        }
+        if self.current.as_ref().is_none_or(|(d, _)| *d != date) {
+            let name = self.file_name(date);
+            let file = OpenOptions::new().create(true).append(true).open(self.dir.join(&name))?;
+            let rotated = self.current.is_some(); // 运行中跨天，需要归档之前的文件
+            self.current = Some((date, file));
+            if rotated {
+                archive::compress_old_in_background(self.dir.clone(), vec![name, self.today_name()], self.archive.clone());
+            }
+        }
+        match self.current.as_mut() {
+            Some((_, file)) => file.write_all(data), // 一次写入，避免程序被结束时留下半行
+            None => Ok(()),
+        }
+    }
+
+    // 今天的文件名，启动时归档其余文件要跳过它
+    pub fn today_name(&self) -> String {
+        self.file_name(self.clock.today())
+    }
+}
+
+// 作为日志输出目标时，按写入时的日期切换文件
+impl Write for DailyFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.append(self.clock.today(), buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.current.as_mut() {
+            Some((_, file)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+    use crate::clock::FixedClock;
+
+    // 迟到的前一天的内容追加到前一天的文件，当前文件保持打开，也不会被归档
+    #[test]
+    fn late_record_keeps_current_file() {
+        let dir = std::env::temp_dir().join(format!("foreground-watcher-daily-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let clock = Arc::new(FixedClock(Local.from_local_datetime(&today.and_hms_opt(0, 0, 1).unwrap()).unwrap()));
+        let mut file = DailyFile::new(dir.clone(), "", "jsonl", ArchiveConfig::default(), clock);
+
+        file.append(today, b"a\n").unwrap();
+        file.append(yesterday, b"late\n").unwrap();
+        file.append(today, b"b\n").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200)); // 错误地触发了归档时等它完成
+
+        let read = |date: NaiveDate| std::fs::read_to_string(dir.join(format!("{}.jsonl", date.format("%Y-%m-%d"))));
+        let (today_text, yesterday_text) = (read(today), read(yesterday));
+        let current = file.current.take().map(|(date, _)| date);
+        std::fs::remove_dir_all(&dir).unwrap(); // 先关闭当前文件才能删除目录
+        assert_eq!(today_text.unwrap(), "a\nb\n");
+        assert_eq!(yesterday_text.unwrap(), "late\n");
+        assert_eq!(current, Some(today));
+    }
+}
@@ -0,0 +1,48 @@
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::StationsAndDesktops::{
+    CloseDesktop,
+    GetUserObjectInformationW, // 查询桌面的名称
+    OpenInputDesktop,          // 打开当前接收输入的桌面
+    DESKTOP_CONTROL_FLAGS,
+    DESKTOP_READOBJECTS,
+    UOI_NAME,
+};
+
+// 普通的用户桌面
+const DEFAULT_DESKTOP: &str = "Default";
+
+// 当前接收键盘鼠标输入的桌面
+pub enum InputDesktop {
+    Default,                // 普通的用户桌面
+    Secure(Option<String>), // 安全桌面（UAC 提示、Ctrl+Alt+Del 界面、登录界面），附带能查到的桌面名称，如 Winlogon
+}
+
+// 查询当前接收输入的桌面
+// 安全桌面上没有可以查询的窗口，GetForegroundWindow 返回空句柄；普通程序通常也没有权限打开安全桌面，
+// 打开失败本身就说明输入已经切换到了安全桌面，这时名称未知
+pub fn input() -> InputDesktop {
+    unsafe {
+        let Ok(desktop) = OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_READOBJECTS) else {
+            return InputDesktop::Secure(None);
+        };
+        let mut buffer = [0u16; 256];
+        let mut needed = 0;
+        let name = GetUserObjectInformationW(
+            HANDLE(desktop.0),
+            UOI_NAME,
+            Some(buffer.as_mut_ptr().cast()),
+            (buffer.len() * 2) as u32,
+            Some(&mut needed),
+        )
+        .ok()
+        .map(|()| {
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            String::from_utf16_lossy(&buffer[..len])
+        });
+        let _ = CloseDesktop(desktop);
+        match name {
+            Some(name) if !name.eq_ignore_ascii_case(DEFAULT_DESKTOP) => InputDesktop::Secure(Some(name)),
+            _ => InputDesktop::Default, // 查不到名称时按普通桌面处理，避免误报
+        }
+    }
+}
@@ -0,0 +1,306 @@
+#[cfg(feature = "http")]
+use std::net::TcpListener;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use windows::core::{w, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS, HANDLE, HWND};
+use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use windows::Win32::System::Registry::{
+    RegCloseKey,
+    RegEnumValueW,  // 列出开机启动项
+    RegOpenKeyExW,
+    HKEY,
+    HKEY_CURRENT_USER,
+    HKEY_LOCAL_MACHINE,
+    KEY_READ,
+};
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken}; // 查询本进程是否以管理员身份运行
+use windows::Win32::UI::Accessibility::{
+    SetWinEventHook, // 测试能否注册前台窗口切换的事件钩子
+    UnhookWinEvent,
+    HWINEVENTHOOK,
+};
+use windows::Win32::UI::Shell::FOLDERID_Startup; // “启动”文件夹
+use windows::Win32::UI::WindowsAndMessaging::{EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT};
+
+use crate::clock::Clock; // 判断监视程序是否正在运行
+use crate::config::{self, Config}; // 检查配置文件，按配置检查输出目标
+use crate::paths::{self, Paths}; // 检查数据目录能否写入
+#[cfg(feature = "http")]
+use crate::session; // 监视程序正在运行时HTTP端口被占用是正常的
+use crate::trigger; // 解析 webhook 地址
+
+// 测试连接 webhook 的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 开机启动项所在的注册表键
+const RUN_KEY: windows::core::PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\Run");
+
+// 一项检查的结果
+pub enum Status {
+    Pass, // 通过
+    Warn, // 可以运行，但部分数据或功能受影响
+    Fail, // 无法正常运行
+    Skip, // 当前配置下不需要检查
+}
+
+// 一项检查
+pub struct Check {
+    pub name: &'static str,   // 检查的内容
+    pub status: Status,       // 结果
+    pub detail: String,       // 说明
+    pub hint: Option<String>, // 没有通过时的修正建议
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>, hint: Option<&str>) -> Check {
+        Check { name, status, detail: detail.into(), hint: hint.map(str::to_string) }
+    }
+
+    // 输出为一行结果，没有通过时下一行为修正建议
+    pub fn describe(&self) -> String {
+        let status = match self.status {
+            Status::Pass => "通过",
+            Status::Warn => "注意",
+            Status::Fail => "失败",
+            Status::Skip => "跳过",
+        };
+        let mut text = format!("[{}] {}: {}", status, self.name, self.detail);
+        if let Some(hint) = &self.hint {
+            text.push_str(&format!("\n       建议: {}", hint));
+        }
+        text
+    }
+}
+
+// 检查运行环境：配置文件、钩子、权限、UI Automation、数据目录、网络输出目标和开机启动
+// explicit 为是否用 --config 指定了配置文件
+pub fn run(paths: &Paths, explicit: bool, clock: &dyn Clock) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let config = match config::load(&paths.config, explicit) {
+        Ok(config) => {
+            checks.push(Check::new("配置文件", Status::Pass, paths.config.display().to_string(), None));
+            config
+        }
+        Err(e) => {
+            checks.push(Check::new("配置文件", Status::Fail, e.to_string(), Some("按提示修改配置文件，或用 init --force 重新生成默认配置")));
+            Config::default() // 其余检查按默认配置进行
+        }
+    };
+    checks.push(hook());
+    checks.push(elevation());
+    checks.push(Check::new("UI Automation", Status::Skip, "当前版本不使用 UI Automation（尚不支持获取浏览器地址）", None));
+    for (name, dir) in [("事件记录目录", &paths.events), ("日志目录", &paths.logs), ("缓存目录", &paths.cache)] {
+        checks.push(writable(name, dir));
+    }
+    checks.extend(network(&config, paths, clock));
+    checks.push(if paths.portable {
+        Check::new("开机启动", Status::Skip, "便携模式下不读取注册表和启动文件夹", None)
+    } else {
+        autostart()
+    });
+    checks
+}
+
+// 能否注册前台窗口切换的 WinEvent 钩子（backend = "hybrid" 时需要）
+fn hook() -> Check {
+    unsafe {
+        let hook = SetWinEventHook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND, None, Some(on_event), 0, 0, WINEVENT_OUTOFCONTEXT);
+        if hook.is_invalid() {
+            return Check::new(
+                "WinEvent 钩子",
+                Status::Fail,
+                "注册失败",
+                Some("在没有交互式桌面的会话（如服务、计划任务的“不管用户是否登录都要运行”）中无法注册，请在登录后的桌面中运行，或改用 backend = \"poll\""),
+            );
+        }
+        let _ = UnhookWinEvent(hook);
+    }
+    Check::new("WinEvent 钩子", Status::Pass, "可以注册", None)
+}
+
+// 测试钩子用的回调，不做任何事
+unsafe extern "system" fn on_event(_hook: HWINEVENTHOOK, _event: u32, _hwnd: HWND, _object: i32, _child: i32, _thread: u32, _time: u32) {}
+
+// 是否以管理员身份运行：不是时无法读取以管理员身份运行的程序的路径和版本信息
+fn elevation() -> Check {
+    let elevated = unsafe {
+        let mut token = HANDLE::default();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).is_err() {
+            None
+        } else {
+            let mut elevation = TOKEN_ELEVATION::default();
+            let mut length = 0;
+            let result = GetTokenInformation(
+                token,
+                TokenElevation,
+                Some(&mut elevation as *mut _ as *mut _),
+                std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+                &mut length,
+            );
+            let _ = CloseHandle(token);
+            result.ok().map(|_| elevation.TokenIsElevated != 0)
+        }
+    };
+    match elevated {
+        Some(true) => Check::new("权限", Status::Pass, "以管理员身份运行，可以读取所有程序的信息", None),
+        Some(false) => Check::new(
+            "权限",
+            Status::Warn,
+            "以普通权限运行，以管理员身份运行的程序在前台时记录中没有可执行文件路径",
+            Some("经常使用以管理员身份运行的程序时，请以管理员身份运行监视程序；report quality 中的“未知程序”反映了影响的大小"),
+        ),
+        None => Check::new("权限", Status::Warn, "无法查询本进程的权限", None),
+    }
+}
+
+// 目录能否写入：创建目录，写入并删除一个临时文件
+fn writable(name: &'static str, dir: &Path) -> Check {
+    let probe = dir.join(".doctor");
+    let result = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&probe, b"doctor")).and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::new(name, Status::Pass, format!("{} 可以写入", dir.display()), None),
+        Err(e) => Check::new(
+            name,
+            Status::Fail,
+            format!("{} 无法写入: {}", dir.display(), e),
+            Some("检查目录的权限和磁盘空间，或用 --data-dir 指定其他数据目录"),
+        ),
+    }
+}
+
+// 网络输出目标：本地HTTP接口能否监听，各个 webhook 能否连接
+fn network(config: &Config, paths: &Paths, clock: &dyn Clock) -> Vec<Check> {
+    let mut checks = Vec::new();
+    #[cfg(feature = "http")]
+    checks.push(listen(config, paths, clock));
+    #[cfg(not(feature = "http"))]
+    let _ = (paths, clock); // 没有本地HTTP接口时只检查 webhook
+    let mut webhooks: Vec<(String, &str)> = config
+        .trigger
+        .iter()
+        .filter_map(|t| t.webhook.as_deref().map(|url| (format!("触发规则 {}", t.name.as_deref().unwrap_or(&t.pattern)), url)))
+        .collect();
+    webhooks.extend(config.alarm.webhook.as_deref().map(|url| ("健康告警".to_string(), url)));
+    if webhooks.is_empty() {
+        checks.push(Check::new("webhook", Status::Skip, "没有配置 webhook", None));
+    }
+    for (owner, url) in webhooks {
+        checks.push(connect(&owner, url));
+    }
+    checks
+}
+
+// 本地HTTP接口的监听地址能否监听；监视程序正在运行时地址由它占用，不再检查
+#[cfg(feature = "http")]
+fn listen(config: &Config, paths: &Paths, clock: &dyn Clock) -> Check {
+    if !config.http.enabled {
+        return Check::new("本地HTTP接口", Status::Skip, "没有开启", None);
+    }
+    if session::running(&paths.session, clock.now()) {
+        return Check::new("本地HTTP接口", Status::Skip, format!("监视程序正在运行，{} 由它使用", config.http.listen), None);
+    }
+    match TcpListener::bind(&config.http.listen) {
+        Ok(_) => Check::new("本地HTTP接口", Status::Pass, format!("可以监听 {}", config.http.listen), None),
+        Err(e) => Check::new(
+            "本地HTTP接口",
+            Status::Fail,
+            format!("无法监听 {}: {}", config.http.listen, e),
+            Some("端口可能被其他程序占用，请在 [http] listen 中换一个端口"),
+        ),
+    }
+}
+
+// webhook 的地址能否连接，只建立TCP连接，不发送请求
+fn connect(owner: &str, url: &str) -> Check {
+    let name = "webhook";
+    let (host, _) = match trigger::parse_url(url) {
+        Ok(parsed) => parsed,
+        Err(e) => return Check::new(name, Status::Fail, format!("{}: {}", owner, e), None),
+    };
+    let address = match host.to_socket_addrs().map(|mut a| a.next()) {
+        Ok(Some(address)) => address,
+        Ok(None) | Err(_) => {
+            return Check::new(name, Status::Fail, format!("{}: 无法解析 {}", owner, host), Some("检查主机名是否正确、网络是否可用"));
+        }
+    };
+    match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+        Ok(_) => Check::new(name, Status::Pass, format!("{}: 可以连接 {}", owner, host), None),
+        Err(e) => Check::new(
+            name,
+            Status::Fail,
+            format!("{}: 无法连接 {}: {}", owner, host, e),
+            Some("检查接收的服务是否已经启动、防火墙是否允许连接"),
+        ),
+    }
+}
+
+// 是否设置了开机启动：注册表的 Run 键或“启动”文件夹中有指向本程序的项
+fn autostart() -> Check {
+    let exe = std::env::current_exe().ok().and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase())).unwrap_or_default();
+    let mut found = Vec::new();
+    for (root, name) in [(HKEY_CURRENT_USER, "HKCU"), (HKEY_LOCAL_MACHINE, "HKLM")] {
+        for (value, data) in run_values(root) {
+            if !exe.is_empty() && data.to_lowercase().contains(&exe) {
+                found.push(format!("注册表 {}\\...\\Run 中的 {}", name, value));
+            }
+        }
+    }
+    if let Some(startup) = paths::known_folder(&FOLDERID_Startup) {
+        for entry in std::fs::read_dir(&startup).into_iter().flatten().flatten() {
+            let file = entry.file_name().to_string_lossy().to_lowercase();
+            if file.contains("foregroundwatcher") {
+                found.push(format!("启动文件夹中的 {}", entry.file_name().to_string_lossy()));
+            }
+        }
+    }
+    if found.is_empty() {
+        return Check::new(
+            "开机启动",
+            Status::Warn,
+            "没有设置，登录后需要手动启动监视程序",
+            Some("可以在“启动”文件夹（Win+R 输入 shell:startup）中放一个本程序的快捷方式"),
+        );
+    }
+    Check::new("开机启动", Status::Pass, found.join("，"), None)
+}
+
+// 注册表 Run 键中的所有启动项：名称和命令行
+fn run_values(root: HKEY) -> Vec<(String, String)> {
+    let mut values = Vec::new();
+    unsafe {
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(root, RUN_KEY, None, KEY_READ, &mut key) != ERROR_SUCCESS {
+            return values;
+        }
+        for index in 0.. {
+            let mut name = [0u16; 256];
+            let mut name_len = name.len() as u32;
+            let mut data = [0u8; 2048];
+            let mut data_len = data.len() as u32;
+            let status = RegEnumValueW(
+                key,
+                index,
+                Some(PWSTR(name.as_mut_ptr())),
+                &mut name_len,
+                None,
+                None,
+                Some(data.as_mut_ptr()),
+                Some(&mut data_len),
+            );
+            if status == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            if status != ERROR_SUCCESS {
+                continue; // 名称或内容过长的项跳过
+            }
+            let wide: Vec<u16> = data[..data_len as usize].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            let text = String::from_utf16_lossy(&wide).trim_end_matches('\0').to_string();
+            values.push((String::from_utf16_lossy(&name[..name_len as usize]), text));
+        }
+        let _ = RegCloseKey(key);
+    }
+    values
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use foreground_watcher_types::EnrichmentRecord; // 事件记录中的补充信息
+use log::warn; // 任务超时或被丢弃时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+use crate::clock::SharedClock; // 补充信息的时间
+use crate::lru::LruCache; // 每个可执行文件只验证一次签名
+use crate::signature::{self, Signature}; // 验证数字签名
+
+// [enrichment] 补充信息的设置：耗时较长的信息在窗口切换记录写入之后在后台查询，查出后另写一条记录
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    pub signer: bool,         // 验证可执行文件的数字签名，记录签名是否有效和签名者
+    pub concurrency: usize,   // 同时运行的任务数
+    pub timeout_seconds: u64, // 单个任务的超时（秒），超时的结果丢弃
+    pub queue: usize,         // 最多排队的任务数，超出时丢弃新任务
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        EnrichmentConfig { signer: false, concurrency: 2, timeout_seconds: 10, queue: 64 }
+    }
+}
+
+// 一个补充信息的任务
+struct Task {
+    event_id: String, // 被补充的窗口切换记录的ID
+    exe: String,      // 可执行文件路径
+}
+
+// 签名验证的结果：签名状态和签名者
+type SignerInfo = (&'static str, Option<String>);
+
+// 在后台线程中查询补充信息，主循环通过 submit 提交任务、通过 drain 取回查出的记录后写入事件记录
+// 工作线程不直接写输出目标，事件记录中补充信息的顺序由主循环决定
+pub struct Enricher {
+    tasks: SyncSender<Task>,
+    results: Receiver<EnrichmentRecord>,
+    signers: Arc<Mutex<LruCache<String, SignerInfo>>>, // 可执行文件路径 -> 签名验证的结果
+}
+
+impl Enricher {
+    // 启动工作线程，没有开启任何补充信息时返回None；budget 为签名缓存的内存上限（字节）
+    pub fn start(config: &EnrichmentConfig, budget: usize, clock: SharedClock) -> std::io::Result<Option<Enricher>> {
+        if !config.signer {
+            return Ok(None);
+        }
+        let (tasks, queue) = mpsc::sync_channel::<Task>(config.queue.max(1));
+        let (sender, results) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        let signers = Arc::new(Mutex::new(LruCache::new(budget)));
+        let timeout = Duration::from_secs(config.timeout_seconds.max(1));
+        for index in 0..config.concurrency.max(1) {
+            let (queue, sender, signers, clock) = (Arc::clone(&queue), sender.clone(), Arc::clone(&signers), clock.clone());
+            std::thread::Builder::new().name(format!("enrich-{}", index)).spawn(move || loop {
+                let task = match queue.lock() {
+                    Ok(queue) => match queue.recv() {
+                        Ok(task) => task,
+                        Err(_) => return, // 主循环已经结束
+                    },
+                    Err(_) => return,
+                };
+                let Some((signature, signer)) = signer(&signers, &task.exe, timeout) else {
+                    continue;
+                };
+                let mut fields = BTreeMap::from([("signature".to_string(), signature.into())]);
+                fields.extend(signer.map(|name| ("signer".to_string(), name.into())));
+                let record = EnrichmentRecord { time: clock.now(), event_id: task.event_id, enricher: "signer".to_string(), fields };
+                if sender.send(record).is_err() {
+                    return;
+                }
+            })?;
+        }
+        Ok(Some(Enricher { tasks, results, signers }))
+    }
+
+    // 为一条窗口切换记录提交任务，队列已满时丢弃，不阻塞主循环
+    pub fn submit(&self, event_id: &str, exe: &str) {
+        match self.tasks.try_send(Task { event_id: event_id.to_string(), exe: exe.to_string() }) {
+            Ok(()) => {}
+            Err(TrySendError::Full(task)) => warn!("补充信息的任务过多，已丢弃 {} 的任务", task.exe),
+            Err(TrySendError::Disconnected(_)) => warn!("补充信息的工作线程已经结束"),
+        }
+    }
+
+    // 取回已经查出的补充信息
+    pub fn drain(&self) -> Vec<EnrichmentRecord> {
+        self.results.try_iter().collect()
+    }
+
+    // 签名缓存的项数和估算的占用字节数
+    pub fn usage(&self) -> (usize, usize) {
+        self.signers.lock().map_or((0, 0), |s| (s.count(), s.bytes()))
+    }
+}
+
+// 在工作线程上验证可执行文件的签名，结果缓存；超时返回None，结果仍然写入缓存，下次切换到该程序时直接使用
+// 不另开线程，同时进行的验证不超过工作线程数；验证卡住时只占用这一个工作线程
+fn signer(signers: &Mutex<LruCache<String, SignerInfo>>, exe: &str, timeout: Duration) -> Option<SignerInfo> {
+    if let Some(info) = signers.lock().ok()?.get(exe) {
+        return Some(info.clone());
+    }
+    let started = Instant::now();
+    let info = match signature::query(exe) {
+        Signature::Valid(name) => ("valid", name),
+        Signature::Invalid(name, _) => ("invalid", name),
+        Signature::Unsigned => ("unsigned", None),
+    };
+    if let Ok(mut signers) = signers.lock() {
+        let size = exe.len() + info.1.as_ref().map_or(0, String::len) + std::mem::size_of::<(String, SignerInfo)>() + 64;
+        signers.insert(exe.to_string(), info.clone(), size);
+    }
+    if started.elapsed() > timeout {
+        warn!("验证 {} 的数字签名超过 {} 秒，已放弃这次的结果", exe, timeout.as_secs());
+        return None;
+    }
+    Some(info)
+}
@@ -0,0 +1,67 @@
+#[cfg(feature = "sysinfo")]
+use std::collections::BTreeMap;
+#[cfg(feature = "sysinfo")]
+use std::ffi::OsString;
+
+use regex::Regex; // 匹配可执行文件路径
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+use crate::identity; // 与别名规则使用同一种正则表达式编译方式
+
+// 配置文件中的一条 [[environment]] 规则
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct EnvironmentRule {
+    pub pattern: String,        // 匹配可执行文件完整路径的正则表达式，不区分大小写
+    pub variables: Vec<String>, // 允许记录的环境变量名，不在列表中的一律不记录
+}
+
+// 按配置的规则决定记录前台进程的哪些环境变量，默认不记录任何环境变量
+pub struct EnvironmentRules {
+    rules: Vec<(Regex, Vec<String>)>, // 编译好的规则
+}
+
+impl EnvironmentRules {
+    // 编译规则，配置校验阶段已保证正则表达式有效
+    pub fn new(rules: &[EnvironmentRule]) -> Result<EnvironmentRules, regex::Error> {
+        Ok(EnvironmentRules {
+            rules: rules
+                .iter()
+                .map(|r| identity::compile(&r.pattern).map(|re| (re, r.variables.clone())))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    // 可执行文件允许记录的环境变量名，多条规则匹配时取并集；没有匹配的规则时为空
+    pub fn allowlist(&self, exe: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for (_, variables) in self.rules.iter().filter(|(re, _)| re.is_match(exe)) {
+            for name in variables {
+                if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+}
+
+// 从进程的环境块中取出允许记录的变量，变量名按Windows的习惯不区分大小写，输出时使用配置中的写法
+#[cfg(feature = "sysinfo")]
+pub fn capture(environ: &[OsString], allowlist: &[&str]) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    for entry in environ {
+        let entry = entry.to_string_lossy();
+        // 环境块中还有 "=C:=C:\..." 这类以等号开头的隐藏变量，从第二个字符开始找分隔符
+        let Some(split) = entry.get(1..).and_then(|rest| rest.find('=')).map(|i| i + 1) else {
+            continue;
+        };
+        let (name, value) = (&entry[..split], &entry[split + 1..]);
+        if let Some(allowed) = allowlist.iter().find(|n| n.eq_ignore_ascii_case(name)) {
+            values.insert(allowed.to_string(), value.to_string());
+        }
+    }
+    values
+}
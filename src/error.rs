@@ -0,0 +1,306 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate; // 日期范围有误时的开始和结束
+use thiserror::Error; // 由 #[error] 生成 Display，由 #[source] 和 #[from] 生成 source() 和 From
+
+// 程序中所有可能失败的操作返回的错误，按失败的种类区分，调用者可以用 match 据此决定如何恢复
+// 说明文字在 #[error] 中统一给出，与之前直接返回的字符串相同；包装其他错误类型的变体不另加说明
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),          // 读写文件、网络连接等失败
+    #[error("{0}")]
+    Win32(#[from] windows::core::Error), // Win32 接口调用失败
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),     // 记录不是有效的JSON
+    #[error("{0}")]
+    Regex(#[from] regex::Error),         // 规则中的正则表达式无效
+    #[error("{0}")]
+    Logger(#[from] log::SetLoggerError), // 日志已经初始化过
+    #[cfg(feature = "backup")]
+    #[error("{0}")]
+    Zip(#[from] zip::result::ZipError),  // 备份文件不是有效的zip文件
+    #[error(transparent)]
+    Config(#[from] ConfigError),         // 配置文件或应用信息文件有问题
+    #[error(transparent)]
+    Paths(#[from] PathsError),           // 无法确定数据目录或配置文件的位置
+    #[error("已有监视程序在使用数据目录 {}{}", .data.display(), instance_hint(.instance))]
+    AlreadyRunning {
+        data: PathBuf,            // 正在被使用的数据目录
+        instance: Option<String>, // 用 --instance 指定的实例名称
+    },                                   // 已有监视程序在使用同一数据目录
+    #[error(transparent)]
+    Hook(#[from] HookError),             // WinEvent 钩子无法注册
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] HttpError),             // 本地HTTP接口无法监听
+    #[error(transparent)]
+    Webhook(#[from] WebhookError),       // webhook 的地址无效、无法连接或响应的状态不是 2xx
+    #[error(transparent)]
+    Range(#[from] RangeError),           // 查询或导出的日期范围开始晚于结束，或跨度太大
+    #[cfg(feature = "backup")]
+    #[error(transparent)]
+    Backup(#[from] BackupError),         // 备份文件无法恢复
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Token(#[from] TokenError),           // 令牌文件有误，或没有要吊销的令牌
+    #[error(transparent)]
+    Inspect(#[from] InspectError),       // inspect 子命令找不到要查看的窗口或进程
+    #[error(transparent)]
+    Anonymize(#[from] AnonymizeError),   // 没有满足匿名化的前提
+    #[error("{}失败: {source}", .action.describe(.path))]
+    File {
+        action: FileAction,       // 对文件做的操作
+        path: PathBuf,            // 操作的文件
+        source: std::io::Error,   // 失败的原因
+    },                                   // 读取、写入或改写指定的文件失败
+    #[error("{0}")]
+    Argument(String),                    // 命令行参数或定时任务中的日期、导出格式等写法有误，内容为解析时给出的说明
+    #[error("{0} 项检查没有通过")]
+    ChecksFailed(usize),                 // doctor 子命令有检查项没有通过，内容为没有通过的项数
+}
+
+// 没有用 --instance 指定实例时，提示可以另开一个实例
+fn instance_hint(instance: &Option<String>) -> &'static str {
+    if instance.is_some() { "" } else { "，需要同时运行时请用 --instance 指定另一个实例" }
+}
+
+// 出问题的是配置文件还是应用信息文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFile {
+    Config, // config.toml
+    Apps,   // apps.toml
+}
+
+impl fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigFile::Config => "配置文件",
+            ConfigFile::Apps => "应用信息文件",
+        })
+    }
+}
+
+// 配置文件或应用信息文件的错误
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("配置文件 {} 已存在，如需覆盖请加上 --force", .0.display())]
+    Exists(PathBuf), // init 时配置文件已存在
+    #[error("{file} {} 不存在", .path.display())]
+    Missing {
+        file: ConfigFile,
+        path: PathBuf,
+    }, // 用 --config 指定的文件不存在
+    #[error("无法读取{file} {}: {source}", .path.display())]
+    Unreadable {
+        file: ConfigFile,
+        path: PathBuf,
+        source: std::io::Error,
+    }, // 文件存在但无法读取
+    #[error("{file} {} 有误:\n{}", .path.display(), describe(.problems))]
+    Invalid {
+        file: ConfigFile,
+        path: PathBuf,
+        problems: Vec<Problem>, // 按行号排序的全部问题
+    }, // 内容不是有效的TOML，或没有通过校验
+    #[error("apps.toml 中的 {0} 不是 [\"{0}\"] 形式的表，请手动设置分类")]
+    NotTable(String), // 设置分类时，apps.toml 中这个程序的项不是 ["程序名"] 形式的表
+    #[error("修改后的应用信息文件有误:\n{}", describe(.0))]
+    Edited(Vec<Problem>), // 设置分类后的应用信息文件没有通过校验，没有写入
+    #[error("无法写入应用信息文件 {}: {source}", .path.display())]
+    Unwritable {
+        path: PathBuf,
+        source: std::io::Error,
+    }, // 无法写入应用信息文件
+}
+
+// 校验配置时发现的一个问题
+#[derive(Debug)]
+pub struct Problem {
+    pub line: usize,     // 所在行号，从1开始
+    pub message: String, // 问题说明
+}
+
+// 每个问题一行，带上行号
+fn describe(problems: &[Problem]) -> String {
+    let lines: Vec<String> = problems.iter().map(|p| format!("  第{}行: {}", p.line, p.message)).collect();
+    lines.join("\n")
+}
+
+// 无法确定的位置
+#[derive(Debug, Error)]
+pub enum PathsError {
+    #[error("无法获取 LocalAppData 目录，请使用 --data-dir 指定数据目录")]
+    LocalAppData,                          // 无法获取 LocalAppData 目录
+    #[error("无法获取 AppData 目录，请使用 --config 指定配置文件")]
+    AppData,                               // 无法获取 AppData 目录
+    #[error("无法获取本程序的路径: {0}")]
+    CurrentExe(#[source] std::io::Error),  // 便携模式下无法获取本程序的路径
+    #[error("无法获取本程序所在的目录")]
+    ExeDir,                                // 本程序的路径没有所在目录
+}
+
+// WinEvent 钩子无法注册的原因
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("WinEvent 钩子已经注册")]
+    AlreadyRegistered, // 本进程已经注册过钩子
+    #[error("注册 WinEvent 钩子失败")]
+    Register,          // SetWinEventHook 失败
+}
+
+// 本地HTTP接口无法监听的原因
+#[cfg(feature = "http")]
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("无效的监听地址 {addr}: {source}")]
+    Listen {
+        addr: String,
+        source: std::net::AddrParseError,
+    }, // listen 不是有效的地址
+    #[error("无法监听 {addr}: {source}")]
+    Bind {
+        addr: std::net::SocketAddr,
+        source: std::io::Error,
+    }, // 地址已被占用或没有权限
+}
+
+// webhook 发送失败的原因
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("{0}")]
+    Url(String),     // 地址无效，内容为解析地址时给出的说明
+    #[error("无法解析 {0}")]
+    Resolve(String), // 无法解析主机名
+    #[error("响应的状态不是 2xx: {0}")]
+    Status(String),  // 响应的状态不是 2xx，内容为状态行
+}
+
+// 日期范围有误
+#[derive(Debug, Error)]
+pub enum RangeError {
+    #[error("开始日期 {from} 晚于结束日期 {to}")]
+    Dates {
+        from: NaiveDate,
+        to: NaiveDate,
+    }, // 开始日期晚于结束日期
+    #[cfg(feature = "http")]
+    #[error("开始时间 {} 晚于结束时间 {}", .from.to_rfc3339(), .to.to_rfc3339())]
+    Times {
+        from: chrono::DateTime<chrono::Local>,
+        to: chrono::DateTime<chrono::Local>,
+    }, // 开始时间晚于结束时间
+    #[error("一次最多导出或查询 {0} 天")]
+    TooLong(u64), // 跨度超过允许的天数
+}
+
+// 备份文件无法恢复的原因
+#[cfg(feature = "backup")]
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("{} 不是 ForegroundWatcher 的备份文件（缺少 manifest.json）", .0.display())]
+    NotBackup(PathBuf),   // 不是本程序生成的备份（缺少清单）
+    #[error("备份中包含不安全的路径: {0}")]
+    UnsafePath(String),   // 包含指向备份目录之外的路径
+    #[error("备份中包含未知文件: {0}")]
+    UnknownFile(String),  // 包含不知道恢复到哪里的文件
+    #[error("{} 已存在，如需覆盖请加上 --force", .0.display())]
+    Exists(PathBuf),      // 恢复的目标已存在，且没有指定 --force
+    #[error("备份文件不完整：清单记录 {expected} 个文件，实际 {found} 个")]
+    Incomplete {
+        expected: usize, // 清单记录的文件数
+        found: usize,    // 实际的文件数
+    }, // 文件数与清单不符
+}
+
+// 令牌操作失败的原因
+#[cfg(feature = "http")]
+#[derive(Debug, Error)]
+pub enum TokenError {
+    #[error("读取令牌文件 {} 失败: {source}", .path.display())]
+    Unreadable {
+        path: PathBuf,
+        source: std::io::Error,
+    }, // 令牌文件无法读取
+    #[error("令牌文件 {} 格式有误: {source}", .path.display())]
+    Invalid {
+        path: PathBuf,
+        source: serde_json::Error,
+    }, // 令牌文件格式有误
+    #[error("已经有名为 {0} 的令牌，请换一个名称，或先吊销原来的令牌")]
+    Duplicate(String),                       // 已经有同名的令牌
+    #[error("没有 id 或名称为 {0} 的令牌，可以用 token list 查看")]
+    NotFound(String),                        // 没有 id 或名称匹配的令牌
+    #[error("生成令牌失败: {0}")]
+    Random(#[source] windows::core::Error),  // 生成随机数失败
+    #[error("计算散列值失败: {0}")]
+    Hash(#[source] windows::core::Error),    // 计算散列值失败
+}
+
+// inspect 子命令找不到要查看的对象
+#[derive(Debug, Error)]
+pub enum InspectError {
+    #[error("当前没有前台窗口（可能处于安全桌面）")]
+    NoForeground,         // 当前没有前台窗口
+    #[error("0x{0:X} 不是有效的窗口句柄")]
+    InvalidWindow(isize), // 指定的句柄不是窗口
+    #[error("无法获取窗口所属的进程ID")]
+    NoProcess,            // 无法获取窗口所属的进程ID
+}
+
+// 拒绝匿名化的原因
+#[derive(Debug, Error)]
+pub enum AnonymizeError {
+    #[error("匿名化会就地改写所有事件记录，不可恢复，建议先用 backup 备份；确认后加上 --force 执行")]
+    NotForced, // 没有加 --force 确认
+}
+
+// 对文件做的操作，用于说明哪一步失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    Read,        // 读取
+    Write,       // 写入
+    Rewrite,     // 就地改写
+    ClearTotals, // 删除缓存中按天汇总的时长
+}
+
+impl FileAction {
+    // 对 path 做的操作，后面接“失败”
+    fn describe(&self, path: &Path) -> String {
+        match self {
+            FileAction::Read => format!("读取 {} ", path.display()),
+            FileAction::Write => format!("写入 {} ", path.display()),
+            FileAction::Rewrite => format!("改写 {} ", path.display()),
+            FileAction::ClearTotals => format!("删除缓存 {} 中按天汇总的时长", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    // 说明文字与之前相同，包装的错误可以通过 source() 一层层取到
+    #[test]
+    fn messages_and_sources() {
+        let io = || std::io::Error::other("磁盘已满");
+        let file = Error::File { action: FileAction::ClearTotals, path: PathBuf::from("daily.json"), source: io() };
+        assert_eq!(file.to_string(), "删除缓存 daily.json 中按天汇总的时长失败: 磁盘已满");
+        assert_eq!(file.source().unwrap().to_string(), "磁盘已满");
+        let read = Error::File { action: FileAction::Read, path: PathBuf::from("a.jsonl"), source: io() };
+        assert_eq!(read.to_string(), "读取 a.jsonl 失败: 磁盘已满");
+
+        let config = Error::Config(ConfigError::Unreadable { file: ConfigFile::Apps, path: PathBuf::from("apps.toml"), source: io() });
+        assert_eq!(config.to_string(), "无法读取应用信息文件 apps.toml: 磁盘已满");
+        assert_eq!(config.source().unwrap().to_string(), "磁盘已满");
+        let paths = Error::from(PathsError::CurrentExe(io()));
+        assert_eq!(paths.source().unwrap().to_string(), "磁盘已满");
+
+        let running = Error::AlreadyRunning { data: PathBuf::from("data"), instance: None };
+        assert_eq!(running.to_string(), "已有监视程序在使用数据目录 data，需要同时运行时请用 --instance 指定另一个实例");
+        assert!(running.source().is_none());
+    }
+}
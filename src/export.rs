@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate}; // 导出的日期范围
+use foreground_watcher_types::FocusInterval; // 前台窗口区间
+use serde::Serialize; // 导出为JSON的格式
+
+use crate::aggregate::{self, AppTotal}; // 按天汇总的缓存
+use crate::clock::Clock; // 今天只统计到现在
+use crate::error::{Error, RangeError}; // 日期范围有误
+use crate::history; // 读取事件记录
+use crate::metadata::Metadata; // 应用的分类和显示名称
+use crate::report; // 与报告使用相同的分类和显示名称
+
+// 支持的导出格式
+pub const FORMATS: &[&str] = &["daily-matrix", "perfetto", "dot"];
+
+// 一次最多导出或查询的天数，避免写错日期时读取多年的记录
+const MAX_DAYS: u64 = 3660;
+
+// 每日矩阵的列
+#[derive(Clone, Copy)]
+pub enum Pivot {
+    App,      // 每个应用一列
+    Category, // 每个分类一列
+}
+
+impl Pivot {
+    // 解析 --by 参数，没有指定时使用导出格式的默认值：perfetto 每个分类一条轨道，其余每个应用一列
+    pub fn parse(text: Option<&str>, format: &str) -> Result<Pivot, String> {
+        match text {
+            None if format == "perfetto" => Ok(Pivot::Category),
+            None | Some("app") => Ok(Pivot::App),
+            Some("category") => Ok(Pivot::Category),
+            Some(other) => Err(format!("--by 不支持 {}，可选值: app/category", other)),
+        }
+    }
+
+    // 区间所属的列：应用的显示名称或分类
+    fn column(self, metadata: &Metadata, interval: &FocusInterval) -> String {
+        let exe = interval.event.exe.as_deref();
+        match self {
+            Pivot::App => exe.map_or_else(|| interval.event.identity().id, |e| metadata.display_name(e, interval.event.product.as_deref())),
+            Pivot::Category => report::category(metadata, exe),
+        }
+    }
+
+    // 按天汇总中的应用所属的列，与 column 相同
+    fn total_column(self, metadata: &Metadata, total: &AppTotal) -> String {
+        let exe = total.exe.as_deref();
+        match self {
+            Pivot::App => exe.map_or_else(|| total.app.clone(), |e| metadata.display_name(e, total.product.as_deref())),
+            Pivot::Category => report::category(metadata, exe),
+        }
+    }
+}
+
+// 检查导出或查询的日期范围
+pub fn check_range(from: NaiveDate, to: NaiveDate) -> Result<(), Error> {
+    if from > to {
+        return Err(Error::Range(RangeError::Dates { from, to }));
+    }
+    if (to - from).num_days() as u64 >= MAX_DAYS {
+        return Err(Error::Range(RangeError::TooLong(MAX_DAYS)));
+    }
+    Ok(())
+}
+
+// 每日矩阵：每天一行，每个应用或分类一列，值为在前台的分钟数，保留一位小数
+// 列按总时长从长到短排列，没有记录的天也输出一行全为0的记录，便于在表格软件中直接使用
+// 使用缓存目录中按天汇总的缓存，只重新读取有变化的那几天的事件记录
+pub fn daily_matrix(
+    events: &std::path::Path,
+    cache: &std::path::Path,
+    metadata: &Metadata,
+    from: NaiveDate,
+    to: NaiveDate,
+    pivot: Pivot,
+    clock: &dyn Clock,
+) -> Result<String, Error> {
+    check_range(from, to)?;
+    let mut days: Vec<(NaiveDate, BTreeMap<String, i64>)> = Vec::new();
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    for (day, apps) in aggregate::range(cache, events, from, to, clock)? {
+        let mut columns: BTreeMap<String, i64> = BTreeMap::new();
+        for app in &apps {
+            let column = pivot.total_column(metadata, app);
+            *columns.entry(column.clone()).or_default() += app.duration_ms;
+            *totals.entry(column).or_default() += app.duration_ms;
+        }
+        days.push((day, columns));
+    }
+
+    let mut ranked: Vec<(String, i64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut text = String::from("date");
+    for (name, _) in &ranked {
+        text.push(',');
+        text.push_str(&csv_field(name));
+    }
+    text.push('\n');
+    for (day, columns) in &days {
+        text.push_str(&day.format("%Y-%m-%d").to_string());
+        for (name, _) in &ranked {
+            let ms = columns.get(name).copied().unwrap_or_default();
+            text.push_str(&format!(",{:.1}", ms as f64 / 60_000.0));
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+// Chrome tracing 格式（Perfetto UI 可以直接打开）中的一个事件
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,          // 区间：应用的显示名称；元数据：固定的名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cat: Option<String>,   // 分类
+    ph: &'static str,      // X 为有时长的区间，M 为元数据
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<i64>,       // 开始时间（微秒，Unix时间）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<i64>,      // 时长（微秒）
+    pid: u32,              // 固定为1，所有轨道属于同一个进程
+    tid: usize,            // 轨道，每个分类或应用一条
+    args: BTreeMap<&'static str, serde_json::Value>, // 区间的窗口标题、可执行文件路径；元数据的内容
+}
+
+// Chrome tracing 格式的JSON，每个前台窗口区间为一个事件，每个分类（--by app 时每个应用）一条轨道，
+// 轨道按总时长从长到短排列；可以在 Perfetto UI（ui.perfetto.dev）中缩放时间轴、按标题搜索
+pub fn perfetto(
+    events: &std::path::Path,
+    metadata: &Metadata,
+    from: NaiveDate,
+    to: NaiveDate,
+    pivot: Pivot,
+    clock: &dyn Clock,
+) -> Result<String, Error> {
+    check_range(from, to)?;
+    let start = history::day_start(from);
+    let end = history::day_start(to + Days::new(1)).min(clock.now());
+    let intervals = if start < end { history::intervals(events, start, end)? } else { Vec::new() };
+
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    for interval in &intervals {
+        *totals.entry(pivot.column(metadata, interval)).or_default() += interval.duration_ms();
+    }
+    let mut ranked: Vec<(String, i64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let tracks: BTreeMap<&str, usize> = ranked.iter().enumerate().map(|(i, (name, _))| (name.as_str(), i + 1)).collect();
+
+    let metadata_event = |name: &str, tid: usize, args: BTreeMap<&'static str, serde_json::Value>| TraceEvent {
+        name: name.to_string(),
+        cat: None,
+        ph: "M",
+        ts: None,
+        dur: None,
+        pid: 1,
+        tid,
+        args,
+    };
+    let mut trace = vec![metadata_event("process_name", 0, BTreeMap::from([("name", "ForegroundWatcher".into())]))];
+    for (name, tid) in &tracks {
+        trace.push(metadata_event("thread_name", *tid, BTreeMap::from([("name", (*name).into())])));
+        trace.push(metadata_event("thread_sort_index", *tid, BTreeMap::from([("sort_index", (*tid).into())])));
+    }
+    for interval in &intervals {
+        let exe = interval.event.exe.as_deref();
+        let mut args = BTreeMap::new();
+        args.extend(interval.event.title.clone().map(|t| ("title", t.into())));
+        args.extend(exe.map(|e| ("exe", e.into())));
+        trace.push(TraceEvent {
+            name: exe.map_or_else(|| interval.event.identity().id, |e| metadata.display_name(e, interval.event.product.as_deref())),
+            cat: Some(report::category(metadata, exe)),
+            ph: "X",
+            ts: Some(interval.start.timestamp_micros()),
+            dur: Some((interval.end - interval.start).num_microseconds().unwrap_or_default()),
+            pid: 1,
+            tid: tracks.get(pivot.column(metadata, interval).as_str()).copied().unwrap_or_default(),
+            args,
+        });
+    }
+    let mut text = serde_json::to_string(&serde_json::json!({ "traceEvents": trace, "displayTimeUnit": "ms" }))?;
+    text.push('\n');
+    Ok(text)
+}
+
+// Graphviz 的 DOT 格式：应用之间的切换图，每个应用（--by category 时每个分类）一个节点，标注总时长，
+// 从一个应用切换到另一个应用记为一条边，边的权重和标签为切换次数；记录中断前后的两段不算切换
+pub fn dot(
+    events: &std::path::Path,
+    metadata: &Metadata,
+    from: NaiveDate,
+    to: NaiveDate,
+    pivot: Pivot,
+    clock: &dyn Clock,
+) -> Result<String, Error> {
+    check_range(from, to)?;
+    let start = history::day_start(from);
+    let end = history::day_start(to + Days::new(1)).min(clock.now());
+    let intervals = if start < end { history::intervals(events, start, end)? } else { Vec::new() };
+
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    let mut edges: BTreeMap<(String, String), u64> = BTreeMap::new();
+    let mut previous: Option<(String, &FocusInterval)> = None; // 上一段及其节点
+    for interval in &intervals {
+        let node = pivot.column(metadata, interval);
+        *totals.entry(node.clone()).or_default() += interval.duration_ms();
+        if let Some((last, _)) = previous.filter(|(last, i)| i.end == interval.start && *last != node) {
+            *edges.entry((last, node.clone())).or_default() += 1;
+        }
+        previous = Some((node, interval));
+    }
+
+    let most = edges.values().copied().max().unwrap_or(1) as f64;
+    let mut text = format!("// ForegroundWatcher {} 至 {} 的切换图\ndigraph transitions {{\n", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"));
+    text.push_str("    rankdir=LR;\n    node [shape=box, style=rounded];\n");
+    for (node, ms) in &totals {
+        text.push_str(&format!("    {} [label={}];\n", dot_id(node), dot_id(&format!("{}\n{}", node, report::format_duration(*ms)))));
+    }
+    for ((source, target), count) in &edges {
+        let width = 1.0 + 4.0 * *count as f64 / most; // 线宽 1 到 5，按切换次数缩放
+        text.push_str(&format!("    {} -> {} [label=\"{}\", weight={}, penwidth={:.1}];\n", dot_id(source), dot_id(target), count, count, width));
+    }
+    text.push_str("}\n");
+    Ok(text)
+}
+
+// DOT中带引号的ID，引号和反斜杠需要转义
+fn dot_id(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+// CSV字段，含逗号、引号或换行时加引号，引号写成两个
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
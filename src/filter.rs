@@ -0,0 +1,88 @@
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowLongPtrW, // 查询窗口样式
+    GetWindowRect,     // 查询窗口大小
+    GWL_EXSTYLE,
+    GWL_STYLE,
+};
+
+// 窗口样式中对编写规则有用的几项，[filter] 的 styles 只能使用这些名称
+pub const STYLES: &[(u32, &str)] = &[
+    (0x8000_0000, "WS_POPUP"),
+    (0x4000_0000, "WS_CHILD"),
+    (0x2000_0000, "WS_MINIMIZE"),
+    (0x1000_0000, "WS_VISIBLE"),
+    (0x0800_0000, "WS_DISABLED"),
+    (0x0100_0000, "WS_MAXIMIZE"),
+    (0x00C0_0000, "WS_CAPTION"),
+    (0x0004_0000, "WS_THICKFRAME"),
+];
+
+// 扩展窗口样式中对编写规则有用的几项，[filter] 的 ex_styles 只能使用这些名称
+pub const EX_STYLES: &[(u32, &str)] = &[
+    (0x0000_0008, "WS_EX_TOPMOST"),
+    (0x0000_0020, "WS_EX_TRANSPARENT"),
+    (0x0000_0080, "WS_EX_TOOLWINDOW"),
+    (0x0004_0000, "WS_EX_APPWINDOW"),
+    (0x0008_0000, "WS_EX_LAYERED"),
+    (0x0800_0000, "WS_EX_NOACTIVATE"),
+];
+
+// [filter] 按窗口样式和大小过滤：工具提示、输入法候选窗口等短暂出现的窗口偶尔会成为前台窗口，不应该产生记录
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct FilterConfig {
+    pub styles: Vec<String>,    // 带有其中任一样式的窗口不记录，如 "WS_CHILD"
+    pub ex_styles: Vec<String>, // 带有其中任一扩展样式的窗口不记录，如 "WS_EX_TOOLWINDOW"、"WS_EX_NOACTIVATE"
+    pub min_size: u32,          // 宽或高小于这么多像素的窗口不记录，0表示不检查
+}
+
+// 按 [filter] 判断前台窗口是否应该记录
+pub struct WindowFilter {
+    styles: Vec<(u32, &'static str)>,    // 要过滤的样式
+    ex_styles: Vec<(u32, &'static str)>, // 要过滤的扩展样式
+    min_size: i32,
+}
+
+impl WindowFilter {
+    // 样式名称已由配置校验检查过，不认识的名称不会出现
+    pub fn new(config: &FilterConfig) -> WindowFilter {
+        let pick = |names: &[String], table: &[(u32, &'static str)]| {
+            table.iter().filter(|(_, name)| names.iter().any(|n| n == name)).copied().collect()
+        };
+        WindowFilter {
+            styles: pick(&config.styles, STYLES),
+            ex_styles: pick(&config.ex_styles, EX_STYLES),
+            min_size: config.min_size.min(i32::MAX as u32) as i32,
+        }
+    }
+
+    // 窗口被过滤的原因，如“扩展样式 WS_EX_TOOLWINDOW”，不过滤时返回None
+    pub fn reason(&self, hwnd: HWND) -> Option<String> {
+        if let Some(name) = matched(&self.styles, unsafe { GetWindowLongPtrW(hwnd, GWL_STYLE) } as u32) {
+            return Some(format!("样式 {}", name));
+        }
+        if let Some(name) = matched(&self.ex_styles, unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32) {
+            return Some(format!("扩展样式 {}", name));
+        }
+        if self.min_size > 0 {
+            let mut rect = RECT::default();
+            if unsafe { GetWindowRect(hwnd, &mut rect) }.is_ok() {
+                let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+                if width < self.min_size || height < self.min_size {
+                    return Some(format!("大小 {}x{}", width, height));
+                }
+            }
+        }
+        None
+    }
+}
+
+// 窗口带有的第一个要过滤的样式，多位的样式（如 WS_CAPTION）需要各位都有
+fn matched(filter: &[(u32, &'static str)], value: u32) -> Option<&'static str> {
+    filter.iter().find(|(bit, _)| value & bit == *bit).map(|(_, name)| *name)
+}
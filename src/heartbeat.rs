@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local}; // 记录时间
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 从配置文件读取设置，序列化心跳记录
+
+use crate::metrics::MetricsReport; // 热路径延迟统计
+
+// [memory] 内存上限设置
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub cache_kb: u64,        // 内部缓存（如版本信息）估算占用的上限（KB），超出时淘汰最久没有使用的项
+    pub max_processes: usize, // sysinfo 进程表最多保留的进程数，超出时清空重建
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig { cache_kb: 1024, max_processes: 256 }
+    }
+}
+
+// 一项缓存的使用情况
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct CacheUsage {
+    pub entries: usize, // 项数
+    pub bytes: usize,   // 估算的占用字节数
+}
+
+// 定期写入事件记录的心跳，报告程序自身的运行状况，用于发现长时间运行后的内存增长
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct HeartbeatRecord {
+    pub time: DateTime<Local>,
+    pub kind: &'static str,      // 固定为 "heartbeat"，用于与窗口切换记录区分
+    pub memory: Option<u64>,     // 本进程的工作集大小（字节），无法获取时为空
+    pub cache_budget: usize,     // 缓存的内存上限（字节）
+    pub caches: BTreeMap<&'static str, CacheUsage>, // 各个缓存的使用情况
+    pub processes: usize,        // sysinfo 进程表中的进程数
+    pub latency: Option<MetricsReport>, // 自上次心跳以来各个环节的延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrections: Option<u64>, // 钩子和轮询混合方式下，自上次心跳以来核对发现钩子漏报的次数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_restarts: Option<u64>, // 钩子和轮询混合方式下，自上次心跳以来看门狗重新注册钩子的次数
+}
+
+impl HeartbeatRecord {
+    pub fn new(time: DateTime<Local>) -> HeartbeatRecord {
+        HeartbeatRecord { time, kind: "heartbeat", memory: None, cache_budget: 0, caches: BTreeMap::new(), processes: 0, latency: None, corrections: None, hook_restarts: None }
+    }
+}
+
+// 按固定间隔触发心跳
+pub struct Heartbeat {
+    interval: Option<Duration>, // 心跳间隔，为None时不写心跳
+    last: Instant,              // 上次心跳的时间
+}
+
+impl Heartbeat {
+    // minutes 为0时不写心跳
+    pub fn new(minutes: u64) -> Heartbeat {
+        Heartbeat { interval: (minutes > 0).then(|| Duration::from_secs(minutes * 60)), last: Instant::now() }
+    }
+
+    // 是否到了写心跳的时间，到期时从现在开始计算下一个间隔
+    pub fn due(&mut self) -> bool {
+        match self.interval {
+            Some(interval) if self.last.elapsed() >= interval => {
+                self.last = Instant::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // 距下一次心跳还有多久，不写心跳时为None
+    pub fn remaining(&self) -> Option<Duration> {
+        self.interval.map(|interval| interval.saturating_sub(self.last.elapsed()))
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Days, Local, NaiveDate, TimeDelta, TimeZone}; // 按天读取事件记录
+use foreground_watcher_types::{EnrichmentRecord, FocusEvent, FocusInterval, PulseRecord, SessionMarker, SessionRecord}; // 事件记录中的各类记录
+use serde::Deserialize; // 区分记录的种类
+
+use crate::archive; // 读取已经压缩归档的事件记录
+
+// 事件记录中对统计有用的内容
+pub enum Entry {
+    Focus(Box<FocusEvent>), // 窗口切换
+    Stop(DateTime<Local>),  // 记录在这个时间中断（正常退出、睡眠、上一个会话没有正常结束或切换到安全桌面）
+}
+
+impl Entry {
+    pub fn time(&self) -> DateTime<Local> {
+        match self {
+            Entry::Focus(event) => event.time,
+            Entry::Stop(time) => *time,
+        }
+    }
+}
+
+// 只取出记录的种类和时间，窗口切换记录没有 kind
+#[derive(Deserialize)]
+struct Kind {
+    kind: Option<String>,
+    time: Option<DateTime<Local>>,
+}
+
+// 逐行解析一个事件记录文件，读取整天的记录和实时汇总今天的时长共用同一套规则
+// 状态心跳合并为窗口切换记录：与上一条状态相同且在合并窗口内的跳过，超过合并窗口没有下一条时在合并窗口结束处中断
+#[derive(Default)]
+pub struct Parser {
+    pulse_until: Option<DateTime<Local>>, // 上一条状态心跳的合并窗口结束时间
+}
+
+impl Parser {
+    // 解析一行，得到的记录追加到 entries，entries 的最后一条用于判断状态心跳能否合并；
+    // 写到一半的行（程序被结束时）跳过，补充信息记录原样返回，由调用者决定如何合并
+    pub fn line(&mut self, line: &str, entries: &mut Vec<Entry>) -> Option<EnrichmentRecord> {
+        let Kind { kind, time } = serde_json::from_str::<Kind>(line).ok()?;
+        if let Some(until) = self.pulse_until.filter(|until| time.is_some_and(|t| t > *until)) {
+            entries.push(Entry::Stop(until));
+            self.pulse_until = None;
+        }
+        match kind.as_deref() {
+            None => {
+                entries.extend(serde_json::from_str::<FocusEvent>(line).ok().map(|event| Entry::Focus(Box::new(event))));
+                self.pulse_until = None;
+            }
+            Some("pulse") => {
+                let PulseRecord { state, merge_ms } = serde_json::from_str::<PulseRecord>(line).ok()?;
+                let until = state.time + TimeDelta::milliseconds(merge_ms as i64);
+                let merged = self.pulse_until.is_some()
+                    && matches!(entries.last(), Some(Entry::Focus(last)) if FocusEvent { time: state.time, ..last.as_ref().clone() } == state);
+                if !merged {
+                    entries.push(Entry::Focus(Box::new(state)));
+                }
+                self.pulse_until = Some(until);
+            }
+            Some("session") => {
+                let record = serde_json::from_str::<SessionRecord>(line).ok()?;
+                // 结束标记在写入时中断；开始标记带有 last_seen 时，上一个会话在 last_seen 就已经中断
+                let time = match record.marker {
+                    SessionMarker::End => record.time,
+                    SessionMarker::Begin => record.last_seen.unwrap_or(record.time),
+                };
+                entries.push(Entry::Stop(time));
+                self.pulse_until = None;
+            }
+            Some("secure_desktop") => {
+                entries.extend(time.map(Entry::Stop)); // 安全桌面上的时间不属于之前的前台程序
+                self.pulse_until = None;
+            }
+            Some("enrichment") => return serde_json::from_str::<EnrichmentRecord>(line).ok(),
+            Some(_) => {} // 心跳等记录与前台窗口无关
+        }
+        None
+    }
+
+    // 还在合并窗口内的状态心跳的合并窗口结束时间，没有下一条记录时在这里中断
+    pub fn pulse_until(&self) -> Option<DateTime<Local>> {
+        self.pulse_until
+    }
+
+    // 文件读完，最后一条状态心跳在合并窗口结束处中断
+    pub fn finish(self, entries: &mut Vec<Entry>) {
+        entries.extend(self.pulse_until.map(Entry::Stop));
+    }
+}
+
+// 读取某一天的事件记录，文件不存在时为空，解析规则见 Parser
+// 补充信息合并到同一天中ID相同的窗口切换记录的 enrichment 中，对应的记录不在这一天时忽略
+pub fn read_day(dir: &Path, date: NaiveDate) -> std::io::Result<Vec<Entry>> {
+    let path = day_path(dir, date);
+    let Some(text) = archive::read_to_string(&path)? else {
+        return Ok(Vec::new());
+    };
+    let mut entries = Vec::new();
+    let mut parser = Parser::default();
+    let mut patches = Vec::new(); // 补充信息，读完后再合并，不影响状态心跳的合并
+    for line in text.lines() {
+        patches.extend(parser.line(line, &mut entries));
+    }
+    parser.finish(&mut entries);
+    if !patches.is_empty() {
+        let mut ids: HashMap<String, Vec<usize>> = HashMap::new(); // 状态心跳没有合并时，同一个ID可能有多条
+        for (i, entry) in entries.iter().enumerate() {
+            let Entry::Focus(event) = entry else {
+                continue;
+            };
+            if let Some(id) = &event.id {
+                ids.entry(id.clone()).or_default().push(i);
+            }
+        }
+        for patch in patches {
+            for &i in ids.get(&patch.event_id).into_iter().flatten() {
+                if let Entry::Focus(event) = &mut entries[i] {
+                    event.enrichment.extend(patch.fields.clone());
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// 读取某一天事件记录中的会话标记，文件不存在时为空
+pub fn sessions(dir: &Path, date: NaiveDate) -> std::io::Result<Vec<SessionRecord>> {
+    let path = day_path(dir, date);
+    let Some(text) = archive::read_to_string(&path)? else {
+        return Ok(Vec::new());
+    };
+    Ok(text
+        .lines()
+        .filter(|line| serde_json::from_str::<Kind>(line).is_ok_and(|k| k.kind.as_deref() == Some("session")))
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// 一段时间内的前台窗口区间，区间在下一条记录或记录中断处结束，超出 [start, end) 的部分截掉
+// 会多读前一天的记录，使跨过零点的区间从零点开始计算
+pub fn intervals(dir: &Path, start: DateTime<Local>, end: DateTime<Local>) -> std::io::Result<Vec<FocusInterval>> {
+    let mut entries = Vec::new();
+    let mut date = start.date_naive() - Days::new(1);
+    while date <= end.date_naive() {
+        entries.extend(read_day(dir, date)?);
+        date = date + Days::new(1);
+    }
+    let mut intervals = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let Entry::Focus(event) = entry else {
+            continue;
+        };
+        let until = entries.get(i + 1).map_or(end, Entry::time);
+        let (from, until) = (event.time.max(start), until.min(end));
+        if from < until {
+            intervals.push(FocusInterval { start: from, end: until, event: event.as_ref().clone() });
+        }
+    }
+    Ok(intervals)
+}
+
+// 某一天的事件记录文件，已经压缩归档时实际的文件名还带有 .gz / .zst
+pub fn day_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("{}.jsonl", date.format("%Y-%m-%d")))
+}
+
+// 某一天零点的本地时间，夏令时切换导致零点不存在时取最早的有效时间
+pub fn day_start(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local.from_local_datetime(&midnight).earliest().unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
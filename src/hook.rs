@@ -0,0 +1,183 @@
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::OnceLock;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn}; // 记录重新注册钩子
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId; // 重新注册时向旧的钩子线程发送退出消息
+use windows::Win32::UI::Accessibility::{
+    SetWinEventHook, // 注册前台窗口切换的事件钩子
+    UnhookWinEvent,  // 钩子线程退出前注销钩子
+    HWINEVENTHOOK,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW,
+    GetMessageW, // 钩子的回调在注册它的线程的消息循环中执行
+    PeekMessageW,
+    PostThreadMessageW,
+    TranslateMessage,
+    EVENT_SYSTEM_FOREGROUND, // 前台窗口切换事件
+    MSG,
+    PM_NOREMOVE,
+    WINEVENT_OUTOFCONTEXT,   // 回调在本进程中执行，不注入其他进程
+    WM_QUIT,
+};
+
+use crate::error::{Error, HookError}; // 钩子无法注册
+use crate::shutdown; // 等待期间收到退出请求时提前返回
+
+// 等待钩子事件时检查退出请求的间隔，退出时控制台只给几秒钟写入结束标记
+const SHUTDOWN_CHECK: Duration = Duration::from_millis(250);
+
+// 核对连续发现这么多次漏报时，认为钩子已经失效，重新注册
+const MAX_MISSES: u32 = 3;
+
+// 两次重新注册之间的最短间隔，注册一直失败时不频繁重试
+const RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+// 发现前台窗口切换的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Poll,   // 按 interval_ms 轮询 GetForegroundWindow
+    Hybrid, // 以 WinEvent 钩子为主，每隔 verify_seconds 秒轮询一次核对
+}
+
+impl Backend {
+    // 名称，用于 capabilities 的 backend
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Poll => "poll",
+            Backend::Hybrid => "hybrid",
+        }
+    }
+}
+
+// 主循环被唤醒的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wake {
+    Hook,   // 钩子报告了前台窗口切换
+    Verify, // 等待超时（到了核对或状态心跳等定时任务的时间），钩子期间没有报告
+}
+
+// 钩子回调通知主循环的队列，回调没有上下文参数，只能放在全局变量中
+static EVENTS: OnceLock<SyncSender<()>> = OnceLock::new();
+
+// 前台窗口切换的 WinEvent 钩子，在独立的线程中注册并运行消息循环
+// 钩子线程退出或核对连续发现漏报时，由看门狗注销钩子并在新的线程中重新注册
+pub struct Hook {
+    events: Receiver<()>,          // 有切换时收到通知，多次切换合并为一次
+    thread: JoinHandle<()>,        // 注册钩子并运行消息循环的线程
+    thread_id: u32,                // 钩子线程的ID，重新注册后向它发送 WM_QUIT 注销旧的钩子
+    misses: u32,                   // 核对连续发现漏报的次数，钩子报告切换后清零
+    restarts: u64,                 // 自上次心跳以来重新注册的次数
+    last_restart: Option<Instant>, // 上一次尝试重新注册的时间
+}
+
+impl Hook {
+    // 注册钩子，注册失败时返回错误
+    pub fn start() -> Result<Hook, Error> {
+        let (sender, events) = sync_channel(1); // 容量为1：主循环还没处理时，后续的通知合并到同一次唤醒
+        if EVENTS.set(sender).is_err() {
+            return Err(Error::Hook(HookError::AlreadyRegistered));
+        }
+        let (thread, thread_id) = register()?;
+        Ok(Hook { events, thread, thread_id, misses: 0, restarts: 0, last_restart: None })
+    }
+
+    // 等待钩子报告前台窗口切换，最多等待 timeout；收到退出请求时提前返回
+    pub fn wait(&mut self, timeout: Duration) -> Wake {
+        let deadline = Instant::now() + timeout;
+        while !shutdown::requested() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            match self.events.recv_timeout((deadline - now).min(SHUTDOWN_CHECK)) {
+                Ok(()) => {
+                    self.misses = 0;
+                    return Wake::Hook;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Wake::Verify
+    }
+
+    // 核对发现钩子漏报了一次切换
+    pub fn missed(&mut self) {
+        self.misses += 1;
+    }
+
+    // 看门狗：钩子线程已经退出，或核对连续 MAX_MISSES 次发现漏报时，在新的线程中重新注册钩子，成功后注销旧的钩子
+    // 重新注册失败时保留旧的钩子，RESTART_BACKOFF 之后再试
+    pub fn watchdog(&mut self) {
+        let reason = if self.thread.is_finished() {
+            "线程已经退出"
+        } else if self.misses >= MAX_MISSES {
+            "连续漏报了窗口切换"
+        } else {
+            return;
+        };
+        if self.last_restart.is_some_and(|last| last.elapsed() < RESTART_BACKOFF) {
+            return;
+        }
+        self.last_restart = Some(Instant::now());
+        warn!("WinEvent 钩子{}，重新注册", reason);
+        match register() {
+            Ok((thread, thread_id)) => {
+                unsafe {
+                    let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)); // 旧的线程收到后注销钩子并退出
+                }
+                self.thread = thread;
+                self.thread_id = thread_id;
+                self.misses = 0;
+                self.restarts += 1;
+                info!("WinEvent 钩子已重新注册");
+            }
+            Err(e) => error!("重新注册 WinEvent 钩子失败，{} 秒后重试: {}", RESTART_BACKOFF.as_secs(), e),
+        }
+    }
+
+    // 自上次心跳以来重新注册的次数，读取后清零
+    pub fn take_restarts(&mut self) -> u64 {
+        std::mem::take(&mut self.restarts)
+    }
+}
+
+// 在新的线程中注册钩子并运行消息循环，收到 WM_QUIT 或取消息出错时注销钩子后退出；返回线程和线程ID
+fn register() -> Result<(JoinHandle<()>, u32), Error> {
+    let (ready, registered) = channel();
+    let thread = std::thread::Builder::new().name("hook".to_string()).spawn(move || unsafe {
+        let mut msg = MSG::default();
+        let _ = PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE); // 先创建消息队列，之后才能收到 PostThreadMessageW 发来的 WM_QUIT
+        let hook = SetWinEventHook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND, None, Some(on_event), 0, 0, WINEVENT_OUTOFCONTEXT);
+        let _ = ready.send((!hook.is_invalid()).then(|| GetCurrentThreadId()));
+        if hook.is_invalid() {
+            return;
+        }
+        while GetMessageW(&mut msg, None, 0, 0).0 > 0 { // 返回0为 WM_QUIT，-1为出错
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        let _ = UnhookWinEvent(hook);
+    })?;
+    match registered.recv() {
+        Ok(Some(thread_id)) => Ok((thread, thread_id)),
+        _ => Err(Error::Hook(HookError::Register)),
+    }
+}
+
+// 钩子回调：只通知主循环，由主循环查询前台窗口，回调中不做耗时的操作
+unsafe extern "system" fn on_event(_hook: HWINEVENTHOOK, _event: u32, _hwnd: HWND, _object: i32, _child: i32, _thread: u32, _time: u32) {
+    if let Some(events) = EVENTS.get() {
+        let _ = events.try_send(()); // 队列已满说明已经有一次唤醒在等待处理
+    }
+}
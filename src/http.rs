@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{debug, info}; // 记录连接情况
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+use crate::aggregate::Live; // /stats 返回今天的实时汇总
+use crate::error::{Error, HttpError}; // 无法监听
+use crate::metrics::Metrics; // /metrics 返回的延迟统计
+use crate::query::{Query, Store}; // 查询已经写入的记录
+use crate::sink::{Event, Sink}; // 作为输出目标接收记录
+use crate::tokens::Tokens; // 校验请求中的令牌
+
+// SSE 连接在没有新记录时发送注释行的间隔，用于保持经过代理的连接不被断开，并及时发现客户端已断开
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+// 长轮询最长等待时间
+const MAX_POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+// 请求头的最大长度，超出时直接断开
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+// 同时处理的连接数上限，超出时直接返回 503，避免大量保持不断的 SSE 连接占满线程
+const MAX_CONNECTIONS: usize = 64;
+
+// [http] 本地HTTP接口的设置
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct HttpConfig {
+    pub enabled: bool,  // 是否开启
+    pub listen: String, // 监听地址，默认只允许本机访问
+    pub buffer: usize,  // 保留最近多少条记录，供断线重连和长轮询补发
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig { enabled: false, listen: "127.0.0.1:5600".to_string(), buffer: 1000 }
+    }
+}
+
+// 最近的记录，每条带递增的序号，供各个连接读取
+struct Buffer {
+    next: u64,                       // 下一条记录的序号，从1开始
+    events: VecDeque<(u64, Arc<Event>)>, // 序号和记录，超出容量时丢弃最早的
+    capacity: usize,
+}
+
+// 输出目标与HTTP连接之间共享的记录缓冲区，有新记录时唤醒等待中的连接
+struct Hub {
+    buffer: Mutex<Buffer>,
+    changed: Condvar,
+    capabilities: String, // /capabilities 返回的JSON
+    store: Store,         // /events 查询已经写入事件记录文件的记录
+    tokens: Mutex<Tokens>, // 用 token create 创建了令牌后，所有请求都需要带上有效的令牌
+    metrics: Arc<Mutex<Metrics>>, // 热路径延迟统计，/metrics 读取，不影响心跳中的统计
+    live: Arc<Mutex<Live>>,       // 今天的实时汇总，/stats 读取
+    connections: AtomicUsize, // 正在处理的连接数
+}
+
+impl Hub {
+    // 序号大于 after 的记录；没有时最多等待 timeout，返回记录和当前最新的序号
+    // 序号在程序重新启动后从1开始，after 大于当前最新的序号时是上一次运行的序号，从缓冲区中最早的记录开始返回
+    fn wait_after(&self, after: u64, timeout: Duration) -> (Vec<(u64, Arc<Event>)>, u64) {
+        let deadline = Instant::now() + timeout;
+        let mut buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        let after = if after >= buffer.next { 0 } else { after };
+        loop {
+            let events: Vec<(u64, Arc<Event>)> = buffer.events.iter().filter(|(seq, _)| *seq > after).cloned().collect();
+            let now = Instant::now();
+            if !events.is_empty() || now >= deadline {
+                return (events, buffer.next - 1);
+            }
+            buffer = self.changed.wait_timeout(buffer, deadline - now).unwrap_or_else(|e| e.into_inner()).0;
+        }
+    }
+
+    // 当前最新的序号
+    fn latest(&self) -> u64 {
+        self.buffer.lock().unwrap_or_else(|e| e.into_inner()).next - 1
+    }
+}
+
+// 把记录放入缓冲区的输出目标
+pub struct HttpSink {
+    hub: Arc<Hub>,
+}
+
+impl Sink for HttpSink {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn write(&mut self, event: &Event) -> Result<(), Error> {
+        let mut buffer = self.hub.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = buffer.next;
+        buffer.next += 1;
+        buffer.events.push_back((seq, Arc::new(event.clone())));
+        while buffer.events.len() > buffer.capacity {
+            buffer.events.pop_front();
+        }
+        drop(buffer);
+        self.hub.changed.notify_all();
+        Ok(())
+    }
+}
+
+// 启动本地HTTP接口，返回向它提供记录的输出目标
+// 提供两种实时获取记录的方式，适用于无法使用 WebSocket 的环境（如某些代理之后的面板）：
+//   GET /events/stream             Server-Sent Events，支持 Last-Event-ID 断线续传
+//   GET /events/poll?after=N&timeout=S  长轮询，返回序号大于N的记录，没有时最多等待S秒
+//   GET /capabilities              记录中各个字段能否提供
+//   GET /metrics                   自上次心跳以来热路径各个环节的延迟统计
+//   GET /stats                     今天各应用的前台时长，来自实时汇总，不读取事件记录文件
+//   GET /events?from=&to=&app=&category=&order=&limit=&cursor=  按条件分页查询已经写入的记录
+// capabilities 为 /capabilities 返回的JSON，store 用于 /events 查询
+// 创建了令牌时，请求需要带上 Authorization: Bearer <令牌>，或查询参数 token=<令牌>（EventSource 无法设置请求头）
+pub fn start(
+    config: &HttpConfig,
+    capabilities: String,
+    store: Store,
+    tokens: Tokens,
+    metrics: Arc<Mutex<Metrics>>,
+    live: Arc<Mutex<Live>>,
+) -> Result<HttpSink, Error> {
+    let addr: SocketAddr = config.listen.parse().map_err(|source| Error::Http(HttpError::Listen { addr: config.listen.clone(), source }))?;
+    let listener = TcpListener::bind(addr).map_err(|source| Error::Http(HttpError::Bind { addr, source }))?;
+    let hub = Arc::new(Hub {
+        buffer: Mutex::new(Buffer { next: 1, events: VecDeque::new(), capacity: config.buffer.max(1) }),
+        changed: Condvar::new(),
+        capabilities,
+        store,
+        tokens: Mutex::new(tokens),
+        metrics,
+        live,
+        connections: AtomicUsize::new(0),
+    });
+    let server = Arc::clone(&hub);
+    std::thread::Builder::new().name("http".to_string()).spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if server.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                server.connections.fetch_sub(1, Ordering::SeqCst);
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let _ = respond(&mut stream, "503 Service Unavailable", "text/plain; charset=utf-8", "连接数过多，请稍后重试\n");
+                continue;
+            }
+            let hub = Arc::clone(&server);
+            // 每个连接一个线程，SSE 连接会一直保持
+            let spawned = std::thread::Builder::new().name("http-conn".to_string()).spawn(move || {
+                let peer = stream.peer_addr().ok();
+                if let Err(e) = handle(stream, &hub) {
+                    debug!("HTTP 连接 {:?} 已结束: {}", peer, e);
+                }
+                hub.connections.fetch_sub(1, Ordering::SeqCst);
+            });
+            if spawned.is_err() {
+                server.connections.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    })?;
+    info!("本地HTTP接口已启动: http://{}/events/stream", addr);
+    Ok(HttpSink { hub })
+}
+
+// 解析出的请求
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    last_event_id: Option<u64>, // SSE 断线重连时浏览器带上的最后一条记录的序号
+    token: Option<String>,      // Authorization: Bearer 请求头或 token 查询参数中的令牌
+}
+
+impl Request {
+    // 查询参数的值
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+// 读取请求头，只支持不带请求体的 GET 请求
+fn read_request(stream: &TcpStream) -> std::io::Result<Request> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream);
+    let mut lines = Vec::new();
+    let mut total = 0;
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line)?;
+        total += n;
+        if n == 0 || total > MAX_REQUEST_BYTES {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "请求头不完整或过长"));
+        }
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    let mut parts = lines.first().map(|l| l.split_whitespace()).into_iter().flatten();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .filter(|(k, _)| !k.is_empty())
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect();
+    let header = |wanted: &str| {
+        lines.iter().skip(1).find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case(wanted).then(|| value.trim())
+        })
+    };
+    let last_event_id = header("last-event-id").and_then(|v| v.parse().ok());
+    let bearer = header("authorization").and_then(|v| v.strip_prefix("Bearer ")).map(|t| t.trim().to_string());
+    let mut request = Request { method, path: path.to_string(), query, last_event_id, token: None };
+    request.token = bearer.or_else(|| request.param("token").map(str::to_string));
+    Ok(request)
+}
+
+// 解码查询参数中的 %XX，解码后不是有效的UTF-8时按原样保留
+// 不把 + 当作空格，时间参数中的时区偏移（如 +08:00）可以直接写
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+// 处理一个连接
+fn handle(mut stream: TcpStream, hub: &Hub) -> std::io::Result<()> {
+    let request = read_request(&stream)?;
+    if request.method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain; charset=utf-8", "只支持 GET 请求\n");
+    }
+    let mut tokens = hub.tokens.lock().unwrap_or_else(|e| e.into_inner());
+    if tokens.required() && !request.token.as_deref().is_some_and(|t| tokens.accepts(t)) {
+        drop(tokens);
+        return respond(&mut stream, "401 Unauthorized", "text/plain; charset=utf-8", "需要有效的令牌：Authorization: Bearer <令牌>，或查询参数 token=<令牌>\n");
+    }
+    drop(tokens);
+    match request.path.as_str() {
+        "/events/stream" => stream_events(&mut stream, hub, &request),
+        "/events/poll" => poll_events(&mut stream, hub, &request),
+        "/events" => query_events(&mut stream, hub, &request),
+        "/capabilities" => respond(&mut stream, "200 OK", "application/json; charset=utf-8", &hub.capabilities),
+        "/metrics" => metrics(&mut stream, hub),
+        "/stats" => stats(&mut stream, hub),
+        _ => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "可用的接口: /events, /events/stream, /events/poll, /capabilities, /metrics, /stats\n",
+        ),
+    }
+}
+
+// 自上次心跳以来各个环节的延迟统计，与心跳中的 latency 格式相同
+fn metrics(stream: &mut TcpStream, hub: &Hub) -> std::io::Result<()> {
+    let report = hub.metrics.lock().unwrap_or_else(|e| e.into_inner()).report();
+    let body = serde_json::to_string(&report).map_err(std::io::Error::other)?;
+    respond(stream, "200 OK", "application/json; charset=utf-8", &body)
+}
+
+// 今天各应用的前台时长，[fields] 中 http 的设置不包含 exe 时拒绝，汇总结果中的应用标识可能就是可执行文件路径
+fn stats(stream: &mut TcpStream, hub: &Hub) -> std::io::Result<()> {
+    let live = hub.live.lock().unwrap_or_else(|e| e.into_inner());
+    match hub.store.stats(&live) {
+        Ok(Some(body)) => {
+            drop(live);
+            respond(stream, "200 OK", "application/json; charset=utf-8", &body)
+        }
+        Ok(None) => respond(stream, "403 Forbidden", "text/plain; charset=utf-8", "[fields] 中 http 的设置不允许返回可执行文件路径，无法按应用统计\n"),
+        Err(e) => respond(stream, "500 Internal Server Error", "text/plain; charset=utf-8", &format!("统计失败: {}\n", e)),
+    }
+}
+
+// 输出一个完整的响应
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+// Server-Sent Events：先补发 Last-Event-ID 之后仍在缓冲区中的记录，然后持续推送新记录
+fn stream_events(stream: &mut TcpStream, hub: &Hub, request: &Request) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream; charset=utf-8\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nX-Accel-Buffering: no\r\n\r\n"
+    )?;
+    stream.write_all(b"retry: 3000\n\n")?; // 断开后浏览器3秒后重连
+    let mut last = request.last_event_id.unwrap_or_else(|| hub.latest());
+    loop {
+        let (events, _) = hub.wait_after(last, KEEPALIVE);
+        if events.is_empty() {
+            stream.write_all(b": keepalive\n\n")?; // 写入失败说明客户端已断开
+            continue;
+        }
+        for (seq, event) in events {
+            write!(stream, "id: {}\ndata: {}\n\n", seq, event.line)?;
+            last = seq;
+        }
+        stream.flush()?;
+    }
+}
+
+// 长轮询：返回 {"next": 最新序号, "events": [...]}，下一次请求把 next 作为 after 传回
+// 不带 after 时只等待新记录；after 早于缓冲区中最早的记录时，之间的记录已经丢失
+// after 大于最新的序号时（程序重新启动过），从缓冲区中最早的记录开始返回
+fn poll_events(stream: &mut TcpStream, hub: &Hub, request: &Request) -> std::io::Result<()> {
+    let after = match request.param("after").map(str::parse::<u64>) {
+        Some(Ok(after)) => after,
+        Some(Err(_)) => return respond(stream, "400 Bad Request", "text/plain; charset=utf-8", "after 应为整数\n"),
+        None => hub.latest(),
+    };
+    let timeout = match request.param("timeout").map(str::parse::<u64>) {
+        Some(Ok(seconds)) => Duration::from_secs(seconds).min(MAX_POLL_TIMEOUT),
+        Some(Err(_)) => return respond(stream, "400 Bad Request", "text/plain; charset=utf-8", "timeout 应为秒数\n"),
+        None => Duration::from_secs(30),
+    };
+    let (events, latest) = hub.wait_after(after, timeout);
+    let next = events.last().map_or(latest, |(seq, _)| *seq);
+    let lines: Vec<&str> = events.iter().map(|(_, e)| e.line.as_str()).collect();
+    let body = format!("{{\"next\":{},\"events\":[{}]}}", next, lines.join(","));
+    respond(stream, "200 OK", "application/json; charset=utf-8", &body)
+}
+
+// 查询已经写入的记录：返回 {"total": 符合条件的总数, "next": 下一页的cursor或null, "events": [...]}
+// 下一次请求带上相同的条件，并把 next 作为 cursor 传回
+fn query_events(stream: &mut TcpStream, hub: &Hub, request: &Request) -> std::io::Result<()> {
+    let query = match Query::parse(&request.query) {
+        Ok(query) => query,
+        Err(e) => return respond(stream, "400 Bad Request", "text/plain; charset=utf-8", &format!("{}\n", e)),
+    };
+    let page = match hub.store.query(&query) {
+        Ok(page) => page,
+        Err(e @ Error::Range(_)) => return respond(stream, "400 Bad Request", "text/plain; charset=utf-8", &format!("{}\n", e)),
+        Err(e) => return respond(stream, "500 Internal Server Error", "text/plain; charset=utf-8", &format!("查询失败: {}\n", e)),
+    };
+    let next = page.next.map_or("null".to_string(), |c| format!("\"{}\"", c));
+    let body = format!("{{\"total\":{},\"next\":{},\"events\":[{}]}}", page.total, next, page.lines.join(","));
+    respond(stream, "200 OK", "application/json; charset=utf-8", &body)
+}
@@ -0,0 +1,292 @@
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Local}; // 统计到现在的时长
+use foreground_watcher_types::{AppIdentity, FocusEvent, PulseRecord}; // 从记录中取出当前前台程序
+use log::warn; // 创建窗口失败时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取，区分记录的种类
+use windows::core::w;
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint,
+    CreateSolidBrush,
+    DeleteObject,
+    DrawTextW, // 绘制当前应用和今天的时长
+    EndPaint,
+    FillRect,
+    GetStockObject,
+    InvalidateRect, // 内容变化时重新绘制
+    SelectObject,
+    SetBkMode,
+    SetTextColor,
+    DEFAULT_GUI_FONT,
+    DT_END_ELLIPSIS,
+    DT_SINGLELINE,
+    DT_VCENTER,
+    PAINTSTRUCT,
+    TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW; // 注册窗口类所需的模块句柄
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW,
+    DefWindowProcW,
+    DispatchMessageW,
+    PeekMessageW, // 处理浮窗的消息，避免被系统视为无响应
+    RegisterClassW,
+    SetLayeredWindowAttributes, // 半透明
+    ShowWindow,
+    SystemParametersInfoW, // 查询不含任务栏的工作区，确定浮窗的位置
+    TranslateMessage,
+    LWA_ALPHA,
+    MSG,
+    PM_REMOVE,
+    SPI_GETWORKAREA,
+    SW_SHOWNOACTIVATE,
+    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    WM_PAINT,
+    WNDCLASSW,
+    WS_EX_LAYERED,
+    WS_EX_NOACTIVATE,    // 不会成为前台窗口，不影响记录
+    WS_EX_TOOLWINDOW,    // 不出现在任务栏和 Alt+Tab 中
+    WS_EX_TOPMOST,       // 总在最前
+    WS_EX_TRANSPARENT,   // 鼠标点击穿透到下面的窗口
+    WS_POPUP,
+};
+
+use crate::aggregate::Live; // 今天各应用的时长
+use crate::clock::SharedClock; // 确定“今天”和当前时长
+use crate::error::Error; // 记录无法解析
+use crate::metadata::Metadata; // 应用的显示名称
+use crate::report::format_duration; // 时长的可读形式
+use crate::sink::{Event, Sink}; // 作为输出目标接收记录
+
+// 浮窗的大小和距屏幕边缘的距离（像素）
+const WIDTH: i32 = 320;
+const HEIGHT: i32 = 28;
+const MARGIN: i32 = 12;
+
+// 浮窗的不透明度（0-255）
+const ALPHA: u8 = 200;
+
+// 没有新记录时刷新时长的间隔
+const REFRESH: Duration = Duration::from_secs(1);
+
+// 浮窗当前显示的文字，窗口过程没有上下文参数，只能放在全局变量中
+static TEXT: Mutex<String> = Mutex::new(String::new());
+
+// 浮窗放在屏幕的哪个角
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// [hud] 浮窗的设置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct HudConfig {
+    pub enabled: bool,  // 是否显示浮窗
+    pub corner: Corner, // 放在屏幕的哪个角
+}
+
+// 输出目标交给浮窗线程的变化
+enum Update {
+    Focus { id: String, name: String }, // 切换到了某个应用
+    Stop,                               // 不再有前台程序（会话结束、安全桌面）
+}
+
+// 只取出记录的种类，窗口切换记录没有 kind
+#[derive(Deserialize)]
+struct Kind {
+    kind: Option<String>,
+}
+
+// 浮窗显示的内容：当前应用，以及它今天在前台的时长（来自事件记录的实时汇总）
+struct Today {
+    live: Arc<Mutex<Live>>,           // 今天各应用的时长，由事件记录输出目标更新
+    current: Option<(String, String)>, // 当前应用的标识和显示名称
+}
+
+impl Today {
+    // 处理一次变化
+    fn update(&mut self, update: Update) {
+        self.current = match update {
+            Update::Focus { id, name } => Some((id, name)),
+            Update::Stop => None,
+        };
+    }
+
+    // 浮窗中显示的文字：当前应用和它今天在前台的时长，过了午夜从零开始
+    fn text(&self, now: DateTime<Local>) -> String {
+        match &self.current {
+            Some((id, name)) => {
+                let totals = self.live.lock().unwrap_or_else(|e| e.into_inner()).totals(now);
+                let ms: i64 = totals.iter().filter(|app| app.app == *id).map(|app| app.duration_ms).sum();
+                format!("{}  今天 {}", name, format_duration(ms))
+            }
+            None => "未在记录".to_string(),
+        }
+    }
+}
+
+// 浮窗的输出目标：从记录中取出当前前台程序，交给浮窗线程显示
+pub struct HudSink {
+    metadata: Metadata,           // 应用的显示名称
+    updates: SyncSender<Update>, // 浮窗线程处理不过来时丢弃，下一条记录会带上最新状态
+}
+
+impl Sink for HudSink {
+    fn name(&self) -> &'static str {
+        "hud"
+    }
+
+    fn write(&mut self, event: &Event) -> Result<(), Error> {
+        let Kind { kind } = serde_json::from_str(&event.line)?;
+        let state: Option<FocusEvent> = match kind.as_deref() {
+            None => Some(serde_json::from_str(&event.line)?),
+            Some("pulse") => Some(serde_json::from_str::<PulseRecord>(&event.line)?.state),
+            Some("session") | Some("secure_desktop") => None,
+            Some(_) => return Ok(()), // 心跳等记录不影响前台程序
+        };
+        let update = match state {
+            Some(state) => {
+                self.metadata.refresh(); // 应用信息文件有修改时重新加载
+                let name = match state.exe.as_deref() {
+                    Some(exe) => self.metadata.display_name(exe, state.product.as_deref()),
+                    None => AppIdentity::UNKNOWN.to_string(),
+                };
+                Update::Focus { id: state.identity().id, name }
+            }
+            None => Update::Stop,
+        };
+        let _ = self.updates.try_send(update);
+        Ok(())
+    }
+}
+
+// 启动浮窗线程，返回向它提供记录的输出目标
+// 浮窗不接收输入、不会成为前台窗口，不影响监视结果
+pub fn start(config: &HudConfig, live: Arc<Mutex<Live>>, metadata: Metadata, clock: SharedClock) -> std::io::Result<HudSink> {
+    let today = Today { live, current: None };
+    let (sender, updates) = sync_channel(64);
+    let corner = config.corner;
+    std::thread::Builder::new().name("hud".to_string()).spawn(move || run(corner, today, updates, clock))?;
+    Ok(HudSink { metadata, updates: sender })
+}
+
+// 浮窗线程：窗口必须在处理其消息的线程中创建
+fn run(corner: Corner, mut today: Today, updates: Receiver<Update>, clock: SharedClock) {
+    let hwnd = match create_window(corner) {
+        Ok(hwnd) => hwnd,
+        Err(e) => {
+            warn!("创建浮窗失败: {}", e);
+            return;
+        }
+    };
+    loop {
+        match updates.recv_timeout(REFRESH) {
+            Ok(update) => {
+                today.update(update);
+                while let Ok(update) = updates.try_recv() {
+                    today.update(update);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break, // 程序退出
+        }
+        let text = today.text(clock.now());
+        let mut shown = TEXT.lock().unwrap_or_else(|e| e.into_inner());
+        if *shown != text {
+            *shown = text;
+            drop(shown);
+            unsafe {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+        pump_messages();
+    }
+}
+
+// 注册窗口类并在指定的角创建浮窗
+fn create_window(corner: Corner) -> windows::core::Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = w!("ForegroundWatcherHud");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: class,
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+        let mut area = RECT::default();
+        SystemParametersInfoW(SPI_GETWORKAREA, 0, Some((&mut area as *mut RECT).cast()), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))?;
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => area.left + MARGIN,
+            Corner::TopRight | Corner::BottomRight => area.right - WIDTH - MARGIN,
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => area.top + MARGIN,
+            Corner::BottomLeft | Corner::BottomRight => area.bottom - HEIGHT - MARGIN,
+        };
+        let hwnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            class,
+            w!("ForegroundWatcher HUD"),
+            WS_POPUP,
+            x,
+            y,
+            WIDTH,
+            HEIGHT,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )?;
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), ALPHA, LWA_ALPHA)?;
+        let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+        Ok(hwnd)
+    }
+}
+
+// 处理浮窗线程中积压的消息
+fn pump_messages() {
+    unsafe {
+        let mut msg = MSG::default();
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// 浮窗的窗口过程：深色背景上绘制白色文字，其余消息交给默认处理
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg != WM_PAINT {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+    let mut paint = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut paint);
+    let mut rect = RECT { left: 0, top: 0, right: WIDTH, bottom: HEIGHT };
+    let background = CreateSolidBrush(COLORREF(0x202020));
+    FillRect(hdc, &rect, background);
+    let _ = DeleteObject(background.into());
+    SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(0xFFFFFF));
+    let mut text: Vec<u16> = TEXT.lock().unwrap_or_else(|e| e.into_inner()).encode_utf16().collect();
+    rect.left += 8;
+    rect.right -= 8;
+    DrawTextW(hdc, &mut text, &mut rect, DT_SINGLELINE | DT_VCENTER | DT_END_ELLIPSIS);
+    let _ = EndPaint(hwnd, &paint);
+    LRESULT(0)
+}
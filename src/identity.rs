@@ -0,0 +1,51 @@
+use regex::{Regex, RegexBuilder}; // 别名规则和版本号目录的匹配
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+// 配置文件中的一条 [[alias]] 规则
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct AliasRule {
+    pub pattern: String, // 匹配可执行文件完整路径的正则表达式，不区分大小写
+    pub app: String,     // 匹配时使用的应用标识
+}
+
+// 按不区分大小写的方式编译正则表达式，配置校验和规则匹配使用同一种方式
+pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+// 把可执行文件路径解析为稳定的应用标识，同一个程序升级、换安装目录后仍得到相同的标识
+pub struct Identities {
+    rules: Vec<(Regex, String)>, // 编译好的别名规则，按配置中的顺序匹配
+    version_dir: Regex,          // 以版本号结尾的目录名，如 app-1.2.3、IntelliJ IDEA 2024.1.2
+}
+
+impl Identities {
+    // 编译别名规则，配置校验阶段已保证正则表达式有效
+    pub fn new(rules: &[AliasRule]) -> Result<Identities, regex::Error> {
+        Ok(Identities {
+            rules: rules
+                .iter()
+                .map(|r| compile(&r.pattern).map(|re| (re, r.app.clone())))
+                .collect::<Result<_, _>>()?,
+            version_dir: Regex::new(r"^(.*?)v?\d+(?:\.\d+)+$")?,
+        })
+    }
+
+    // 应用标识：第一条匹配的别名规则对应的应用；没有匹配时为把版本号目录替换为 * 后的小写路径
+    pub fn resolve(&self, exe: &str) -> String {
+        if let Some((_, app)) = self.rules.iter().find(|(re, _)| re.is_match(exe)) {
+            return app.clone();
+        }
+        let mut components: Vec<String> = exe.split(['\\', '/']).map(str::to_lowercase).collect();
+        let last = components.len().saturating_sub(1); // 可执行文件名本身不替换
+        for component in &mut components[..last] {
+            if let Some(prefix) = self.version_dir.captures(component).and_then(|c| c.get(1)) {
+                *component = format!("{}*", prefix.as_str());
+            }
+        }
+        components.join("\\")
+    }
+}
@@ -0,0 +1,136 @@
+use foreground_watcher_types::AppIdentity; // 无法得知是哪个程序时使用的标识
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetAncestor,       // 查询根窗口
+    GetClassNameW,     // 查询窗口类名
+    GetWindow,         // 查询所有者窗口
+    GetWindowLongPtrW, // 查询窗口样式
+    GetWindowRect,     // 查询窗口位置
+    GetWindowThreadProcessId,
+    IsWindow,          // 检查句柄是否有效
+    IsWindowVisible,
+    GA_ROOT,
+    GWL_EXSTYLE,
+    GWL_STYLE,
+    GW_OWNER,
+};
+
+use crate::capture; // 查询可执行文件路径
+use crate::config::Config; // 别名、环境变量和精简模式规则
+use crate::environment::EnvironmentRules; // 会记录哪些环境变量
+use crate::error::{Error, InspectError}; // 找不到要查看的窗口或进程
+use crate::filter::{WindowFilter, EX_STYLES, STYLES}; // 窗口样式的名称，[filter] 是否会过滤
+use crate::identity::Identities; // 应用标识
+use crate::metadata::Metadata; // 用户设置的显示名称和分类
+use crate::signature; // 数字签名
+use crate::versioninfo; // 版本资源
+
+// 要查看的对象
+pub enum Target {
+    Foreground,    // 当前前台窗口
+    Window(isize), // 指定窗口句柄
+    Process(u32),  // 指定进程ID
+}
+
+// 列出能查到的关于窗口或进程的全部信息，每行为“名称: 值”，用于编写过滤和分类规则
+pub fn inspect(target: Target, config: &Config, metadata: &Metadata) -> Result<Vec<(String, String)>, Error> {
+    let mut lines = Vec::new();
+    let pid = match target {
+        Target::Process(pid) => pid,
+        Target::Foreground => {
+            let hwnd = crate::get_active_window_handle().filter(|h| !h.is_invalid()).ok_or(Error::Inspect(InspectError::NoForeground))?;
+            window(hwnd, config, &mut lines)?
+        }
+        Target::Window(handle) => {
+            let hwnd = HWND(handle as *mut _);
+            if !unsafe { IsWindow(Some(hwnd)) }.as_bool() {
+                return Err(Error::Inspect(InspectError::InvalidWindow(handle)));
+            }
+            window(hwnd, config, &mut lines)?
+        }
+    };
+    process(pid, config, metadata, &mut lines)?;
+    Ok(lines)
+}
+
+// 窗口本身的信息，返回窗口所属的进程ID
+fn window(hwnd: HWND, config: &Config, lines: &mut Vec<(String, String)>) -> Result<u32, Error> {
+    let mut add = |name: &str, value: String| lines.push((name.to_string(), value));
+    let mut pid = 0u32;
+    let thread = unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    add("窗口句柄", format!("0x{:X}", hwnd.0 as usize));
+    add("窗口标题", crate::get_window_text(hwnd).map_or_else(|| "（无）".to_string(), |t| t.to_string()));
+    add("窗口类", class_name(hwnd));
+    add("线程ID", thread.to_string());
+    let owner = unsafe { GetWindow(hwnd, GW_OWNER) }.ok().filter(|h| !h.is_invalid());
+    add("所有者窗口", owner.map_or("（无）".to_string(), describe));
+    let root = unsafe { GetAncestor(hwnd, GA_ROOT) };
+    add("根窗口", if root.is_invalid() || root == hwnd { "（本身）".to_string() } else { describe(root) });
+    let style = unsafe { GetWindowLongPtrW(hwnd, GWL_STYLE) } as u32;
+    add("样式", flags(style, STYLES));
+    let ex_style = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32;
+    add("扩展样式", flags(ex_style, EX_STYLES));
+    add("可见", if unsafe { IsWindowVisible(hwnd) }.as_bool() { "是" } else { "否" }.to_string());
+    let mut rect = RECT::default();
+    if unsafe { GetWindowRect(hwnd, &mut rect) }.is_ok() {
+        add("位置", format!("({}, {}) - ({}, {})，{}x{}", rect.left, rect.top, rect.right, rect.bottom, rect.right - rect.left, rect.bottom - rect.top));
+    }
+    let filtered = WindowFilter::new(&config.filter).reason(hwnd);
+    add("[filter]", filtered.map_or("不过滤".to_string(), |reason| format!("过滤（{}）", reason)));
+    if pid == 0 {
+        return Err(Error::Inspect(InspectError::NoProcess));
+    }
+    Ok(pid)
+}
+
+// 进程的信息，以及按当前配置记录时会得到的应用标识、显示名称和分类
+fn process(pid: u32, config: &Config, metadata: &Metadata, lines: &mut Vec<(String, String)>) -> Result<(), regex::Error> {
+    let mut add = |name: &str, value: String| lines.push((name.to_string(), value));
+    add("进程ID", pid.to_string());
+    let Some(exe) = capture::process_image(pid) else {
+        add("可执行文件", "无法读取（进程已结束，或以更高权限运行）".to_string());
+        add("应用标识", format!("{}（无法得知是哪个程序时使用）", AppIdentity::UNKNOWN));
+        return Ok(());
+    };
+    add("可执行文件", exe.clone());
+    let version = versioninfo::query(&exe).unwrap_or_default();
+    let unset = || "（无）".to_string();
+    add("产品名称", version.product.clone().unwrap_or_else(unset));
+    add("公司名称", version.company.clone().unwrap_or_else(unset));
+    add("文件版本", version.version.clone().unwrap_or_else(unset));
+    add("数字签名", signature::query(&exe).describe());
+    add("应用标识", Identities::new(&config.alias)?.resolve(&exe));
+    add("显示名称", metadata.display_name(&exe, version.product.as_deref()));
+    let app = metadata.get(&exe);
+    add("分类", app.and_then(|a| a.category.clone()).unwrap_or_else(|| "未分类（apps.toml 中没有设置）".to_string()));
+    add("精简模式", if config.minimal.is_listed(&exe) { "在 [minimal] apps 中，前台时自动切换" } else { "否" }.to_string());
+    let allowlist = EnvironmentRules::new(&config.environment)?.allowlist(&exe).join(", ");
+    add("记录的环境变量", if allowlist.is_empty() { "（无）".to_string() } else { allowlist });
+    Ok(())
+}
+
+// 窗口类名
+fn class_name(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 256]; // 窗口类名最长256个字符
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) };
+    if len <= 0 {
+        return "（未知）".to_string();
+    }
+    String::from_utf16_lossy(&buffer[..len as usize])
+}
+
+// 用句柄、类名和标题简要描述另一个窗口
+fn describe(hwnd: HWND) -> String {
+    let title = crate::get_window_text(hwnd);
+    format!("0x{:X} {} \"{}\"", hwnd.0 as usize, class_name(hwnd), title.as_deref().map_or("", String::as_str))
+}
+
+// 样式的十六进制值以及其中已知的各项
+fn flags(value: u32, names: &[(u32, &str)]) -> String {
+    let set: Vec<&str> = names.iter().filter(|(bit, _)| value & bit == *bit).map(|(_, name)| *name).collect();
+    if set.is_empty() {
+        format!("0x{:08X}", value)
+    } else {
+        format!("0x{:08X} ({})", value, set.join(" | "))
+    }
+}
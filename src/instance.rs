@@ -0,0 +1,39 @@
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE};
+use windows::Win32::System::Threading::CreateMutexW; // 按数据目录命名的互斥量
+
+use crate::error::Error; // 已有监视程序在运行
+use crate::paths::Paths; // 互斥量按数据目录命名
+
+// 同一个数据目录只允许一个监视程序写入：按数据目录命名的互斥量，进程退出（包括崩溃）时由系统释放
+// 不同实例（--instance）或不同 --data-dir 的数据目录不同，互斥量也不同，可以同时运行
+pub struct InstanceLock(HANDLE);
+
+impl InstanceLock {
+    // 取得数据目录的互斥量，已有监视程序使用同一数据目录时返回错误
+    pub fn acquire(paths: &Paths) -> Result<InstanceLock, Error> {
+        let name = HSTRING::from(mutex_name(paths));
+        unsafe {
+            let handle = CreateMutexW(None, true, &name)?;
+            if GetLastError() == ERROR_ALREADY_EXISTS {
+                let _ = CloseHandle(handle);
+                return Err(Error::AlreadyRunning { data: paths.data.clone(), instance: paths.instance.clone() });
+            }
+            Ok(InstanceLock(handle))
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+// 互斥量的名称：Local\ 下以数据目录命名，名称中不能有反斜杠，换成 /
+fn mutex_name(paths: &Paths) -> String {
+    let dir = paths.data.to_string_lossy().to_lowercase().replace('\\', "/");
+    format!("Local\\ForegroundWatcher:{}", dir.trim_end_matches('/'))
+}
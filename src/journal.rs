@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::aggregate::Live; // 写入后更新今天的实时汇总
+use crate::archive::{self, ArchiveConfig}; // 归档压缩设置
+use crate::clock::SharedClock; // 确定今天的文件
+use crate::daily::DailyFile; // 按天分文件
+use crate::error::Error; // 写入失败的原因
+use crate::sink::{Event, Sink}; // 作为输出目标接收记录
+
+// 保存今天的实时汇总的最短间隔，不为每条记录重写一次缓存文件；退出时由主循环再保存一次
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// 按天分文件追加写入的事件记录，文件名为 YYYY-MM-DD.jsonl
+pub struct Journal {
+    file: DailyFile,
+    live: Arc<Mutex<Live>>, // 今天的实时汇总，与文件中的内容保持一致
+    saved: Instant,         // 上次保存实时汇总的时间
+}
+
+impl Journal {
+    // 打开事件记录目录，并压缩归档之前几天的文件
+    pub fn new(dir: PathBuf, archive: ArchiveConfig, clock: SharedClock, live: Arc<Mutex<Live>>) -> Journal {
+        let file = DailyFile::new(dir.clone(), "", "jsonl", archive.clone(), clock);
+        archive::compress_old_in_background(dir, vec![file.today_name()], archive);
+        Journal { file, live, saved: Instant::now() }
+    }
+}
+
+impl Sink for Journal {
+    fn name(&self) -> &'static str {
+        "journal"
+    }
+
+    // 追加一行记录，日期变化时自动切换到新文件；写入后更新今天的实时汇总，距上次保存超过 SAVE_INTERVAL 时保存
+    fn write(&mut self, event: &Event) -> Result<(), Error> {
+        let mut line = event.line.clone();
+        line.push('\n');
+        self.file.append(event.time.date_naive(), line.as_bytes())?;
+        let mut live = self.live.lock().unwrap_or_else(|e| e.into_inner());
+        live.push(event.time, &event.line);
+        if self.saved.elapsed() >= SAVE_INTERVAL {
+            live.save();
+            self.saved = Instant::now();
+        }
+        Ok(())
+    }
+}
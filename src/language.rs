@@ -0,0 +1,112 @@
+// 窗口标题的语言检测：只用文字所属的书写系统和常见虚词判断，不依赖语言模型，开销可以忽略
+// 标题通常很短，判断不出时返回None，而不是猜一个
+
+// 拉丁字母语言的常见虚词，标题中出现得越多越可能是这种语言
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "in", "for", "with", "on", "is", "how", "what", "your", "new", "from", "my"]),
+    ("de", &["der", "die", "das", "und", "ist", "mit", "für", "nicht", "ein", "eine", "auf", "von", "zu", "neue", "unbenannt"]),
+    ("fr", &["le", "la", "les", "des", "et", "est", "pour", "dans", "une", "du", "sur", "avec", "au", "nouveau", "sans"]),
+    ("es", &["el", "los", "las", "del", "y", "es", "para", "con", "una", "por", "en", "que", "nuevo", "sin", "como"]),
+    ("pt", &["o", "os", "as", "do", "da", "dos", "e", "para", "com", "uma", "em", "que", "não", "novo", "sem"]),
+    ("it", &["il", "lo", "gli", "di", "e", "è", "per", "con", "una", "che", "del", "della", "nuovo", "senza", "non"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "voor", "met", "op", "niet", "naar", "nieuw", "zonder", "te", "dat"]),
+];
+
+// 只在某种语言中使用的字母，出现一次按两个虚词计
+const LETTERS: &[(&str, &[char])] = &[
+    ("de", &['ß', 'ä', 'ö', 'ü']),
+    ("fr", &['è', 'ê', 'ë', 'î', 'œ', 'ç']),
+    ("es", &['ñ', '¿', '¡']),
+    ("pt", &['ã', 'õ']),
+];
+
+// 文字的书写系统
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Thai,
+    Devanagari,
+}
+
+// 字符所属的书写系统，数字、标点等不计
+fn script(c: char) -> Option<Script> {
+    match c {
+        'a'..='z' | 'A'..='Z' | '\u{c0}'..='\u{24f}' if c.is_alphabetic() => Some(Script::Latin),
+        '\u{3040}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' => Some(Script::Kana),
+        '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' | '\u{f900}'..='\u{faff}' => Some(Script::Han),
+        '\u{ac00}'..='\u{d7af}' | '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' => Some(Script::Hangul),
+        '\u{400}'..='\u{4ff}' => Some(Script::Cyrillic),
+        '\u{370}'..='\u{3ff}' => Some(Script::Greek),
+        '\u{600}'..='\u{6ff}' => Some(Script::Arabic),
+        '\u{590}'..='\u{5ff}' => Some(Script::Hebrew),
+        '\u{e00}'..='\u{e7f}' => Some(Script::Thai),
+        '\u{900}'..='\u{97f}' => Some(Script::Devanagari),
+        _ => None,
+    }
+}
+
+// 检测标题的语言，返回 ISO 639-1 代码，如 zh、en；字母太少或判断不出时返回None
+pub fn detect(title: &str) -> Option<&'static str> {
+    let scripts: Vec<Script> = title.chars().filter_map(script).collect();
+    if scripts.len() < 3 {
+        return None;
+    }
+    let count = |script: Script| scripts.iter().filter(|s| **s == script).count();
+    if count(Script::Kana) > 0 {
+        return Some("ja"); // 日文标题常常夹有汉字，有假名就是日文
+    }
+    let all = [
+        Script::Latin,
+        Script::Han,
+        Script::Hangul,
+        Script::Cyrillic,
+        Script::Greek,
+        Script::Arabic,
+        Script::Hebrew,
+        Script::Thai,
+        Script::Devanagari,
+    ];
+    // 汉字一个字就是一个词，按两个字母计，避免中文标题被其中的英文程序名盖过
+    let weight = |script: Script| if script == Script::Han { count(script) * 2 } else { count(script) };
+    let main = all.into_iter().max_by_key(|s| weight(*s))?;
+    let has = |letters: &[char]| title.chars().any(|c| letters.contains(&c));
+    match main {
+        Script::Han => Some("zh"),
+        Script::Hangul => Some("ko"),
+        Script::Cyrillic if has(&['і', 'ї', 'є', 'ґ', 'І', 'Ї', 'Є', 'Ґ']) => Some("uk"),
+        Script::Cyrillic => Some("ru"),
+        Script::Greek => Some("el"),
+        Script::Arabic if has(&['پ', 'چ', 'ژ', 'گ', 'ی']) => Some("fa"),
+        Script::Arabic => Some("ar"),
+        Script::Hebrew => Some("he"),
+        Script::Thai => Some("th"),
+        Script::Devanagari => Some("hi"),
+        Script::Latin | Script::Kana => latin(title),
+    }
+}
+
+// 拉丁字母的标题按虚词和特有字母打分，最高分唯一时才采用
+fn latin(title: &str) -> Option<&'static str> {
+    let lower = title.to_lowercase();
+    let words: Vec<&str> = lower.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).collect();
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(lang, stopwords)| {
+            let hits = words.iter().filter(|w| stopwords.contains(w)).count();
+            let letters = LETTERS.iter().filter(|(l, _)| l == lang).flat_map(|(_, letters)| letters.iter()).filter(|c| lower.contains(**c)).count();
+            (*lang, hits + letters * 2)
+        })
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    match scores.as_slice() {
+        [(lang, best), (_, second), ..] if *best > 0 && best > second => Some(lang),
+        _ => None,
+    }
+}
@@ -0,0 +1,64 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+// 按估算的内存占用限制大小的LRU缓存，超出上限时淘汰最久没有使用的项
+// 用于长时间运行时不断积累的缓存（如每个可执行文件的版本信息），避免运行几个月后内存持续增长
+pub struct LruCache<K, V> {
+    entries: HashMap<K, (V, u64, usize)>, // 键 -> 值、最近使用的序号、估算的占用字节数
+    order: BTreeMap<u64, K>,              // 最近使用的序号 -> 键，序号最小的最久没有使用
+    tick: u64,                            // 下一次使用的序号
+    bytes: usize,                         // 所有项估算的占用字节数之和
+    budget: usize,                        // 内存上限（字节）
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(budget: usize) -> LruCache<K, V> {
+        LruCache { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, bytes: 0, budget }
+    }
+
+    // 查找并标记为最近使用
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (key, used) = self.entries.get_key_value(key).map(|(k, (_, used, _))| (k.clone(), *used))?;
+        self.order.remove(&used);
+        self.order.insert(self.tick, key.clone());
+        let entry = self.entries.get_mut::<K>(&key)?;
+        entry.1 = self.tick;
+        self.tick += 1;
+        Some(&entry.0)
+    }
+
+    // 插入一项，size 为该项估算的占用字节数，插入后超出上限时淘汰最久没有使用的项
+    pub fn insert(&mut self, key: K, value: V, size: usize) {
+        if let Some((_, used, old_size)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+            self.bytes -= old_size;
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick, size));
+        self.tick += 1;
+        self.bytes += size;
+        while self.bytes > self.budget && self.entries.len() > 1 {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((_, _, size)) = self.entries.remove(&oldest) {
+                self.bytes -= size;
+            }
+        }
+    }
+
+    // 缓存中的项数
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    // 估算的占用字节数
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
@@ -1,11 +1,105 @@
-use std::thread::sleep;
-use std::time::Duration;
+mod alarm;
+mod aggregate;
+mod anonymize;
+mod archive;
+mod burst;
+mod capabilities;
+mod capture;
+mod categorize;
+#[cfg(feature = "backup")]
+mod backup;
+mod cli;
+mod completions;
+mod clock;
+mod config;
+mod crash;
+mod daily;
+mod desktop;
+mod doctor;
+mod enrich;
+mod error;
+mod environment;
+mod export;
+mod filter;
+mod heartbeat;
+mod history;
+mod hook;
+mod hud;
+#[cfg(feature = "http")]
+mod http;
+mod identity;
+mod inspect;
+mod instance;
+mod journal;
+mod language;
+mod lru;
+mod maintain;
+mod metrics;
+mod metadata;
+mod notify;
+mod paths;
+#[cfg(feature = "schema")]
+mod schema;
+mod session;
+mod shutdown;
+mod signature;
+mod sink;
+mod soak;
+mod splash;
+mod presentation;
+mod private;
+mod process;
+mod pulse;
+#[cfg(feature = "http")]
+mod query;
+mod report;
+mod rules;
+mod schedule;
+mod timer;
+#[cfg(feature = "http")]
+mod tokens;
+mod trigger;
+mod versioninfo;
+mod workhours;
 
-use chrono::Local; // 用于获取和格式化当前时间
-use log::{info, LevelFilter}; // 日志宏和日志级别过滤器
-use simplelog::{ColorChoice, Config, TermLogger, TerminalMode}; // 简单日志库，用于配置和初始化日志记录
-use sysinfo::{Pid, ProcessesToUpdate, System}; // 系统信息库，用于获取进程信息
+
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{error, info, trace, warn, LevelFilter}; // 日志宏和日志级别过滤器
+use simplelog::{ColorChoice, CombinedLogger, Config, TermLogger, TerminalMode, WriteLogger}; // 简单日志库，用于配置和初始化日志记录
+use cli::{Args, Command}; // 命令行参数
+use clock::{SharedClock, SystemClock}; // 当前时间的来源
+use archive::ArchiveConfig; // 归档压缩设置
+use burst::Bursts; // 连续快速的切换分组
+use capture::CaptureMode; // 记录方式
+use daily::DailyFile; // 按天切换的日志文件
+use error::{AnonymizeError, Error, FileAction}; // 各个子命令失败的原因
+use desktop::InputDesktop; // 安全桌面检测
+use environment::EnvironmentRules; // 环境变量记录规则
+use heartbeat::{CacheUsage, Heartbeat, HeartbeatRecord}; // 心跳记录
+use hook::{Backend, Hook, Wake}; // 钩子和轮询混合方式
+use identity::Identities; // 应用标识解析
+use foreground_watcher_types::{CorrectionRecord, FocusEvent, SecureDesktopRecord, SessionMarker, SessionReason, SessionRecord}; // 事件记录中的一行
+use journal::Journal; // 事件记录
+use metadata::Metadata; // 用户设置的应用显示信息
+use metrics::Metrics; // 热路径延迟统计
+use paths::Paths; // 数据目录
+use sink::Pipeline; // 把记录分发给各个输出目标
+use presentation::PresentationMonitor; // 演示模式检测
+use process::Processes; // 进程信息
+use pulse::Pulse; // 状态心跳模式
+#[cfg(feature = "http")]
+use query::Store; // 本地HTTP接口查询已经写入的记录
+use session::Session; // 监视会话
+use soak::Soak; // 压力测试
+use splash::Splash; // 启动窗口的合并
+use timer::Timer; // 允许合并唤醒的轮询计时器
+use versioninfo::VersionCache; // 可执行文件的版本信息
 use windows::Win32::Foundation::HWND; // Windows句柄类型
+use zeroize::Zeroizing; // 隐私浏览窗口的标题用完后清零
+use windows::Win32::System::Console::GetConsoleWindow; // 本程序的控制台窗口
 use windows::Win32::UI::WindowsAndMessaging::{
     GetForegroundWindow, // 获取当前活动窗口的句柄
     GetWindowThreadProcessId, // 获取窗口所属进程的ID
@@ -13,14 +107,20 @@ use windows::Win32::UI::WindowsAndMessaging::{
     GetWindowTextLengthW, // 获取窗口标题的长度（宽字符）
 };
 
-// 配置日志记录，仅输出到控制台
-fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
-    TermLogger::init(
-        LevelFilter::Info, // 设置日志级别为Info
-        Config::default(), // 使用默认的日志配置
-        TerminalMode::Mixed, // 日志输出模式为混合模式（根据终端类型选择）
-        ColorChoice::Auto, // 自动选择颜色显示
-    )?;
+// 配置日志记录，同时输出到控制台和日志目录下按天切换的日志文件，并压缩归档之前的日志
+fn setup_logging(level: LevelFilter, paths: &Paths, archive: &ArchiveConfig, clock: &SharedClock) -> Result<(), Error> {
+    let log_file = DailyFile::new(paths.logs.clone(), "foregroundwatcher-", "log", archive.clone(), clock.clone());
+    let today = log_file.today_name();
+    CombinedLogger::init(vec![
+        TermLogger::new(
+            level, // 日志级别，默认为Info，跟踪模式下为Trace
+            Config::default(), // 使用默认的日志配置
+            TerminalMode::Mixed, // 日志输出模式为混合模式（根据终端类型选择）
+            ColorChoice::Auto, // 自动选择颜色显示
+        ),
+        WriteLogger::new(level, Config::default(), log_file), // 日志文件与控制台使用同样的级别
+    ])?;
+    archive::compress_old_in_background(paths.logs.clone(), vec![today], archive.clone()); // 日志初始化后再压缩，才能记录压缩结果
     Ok(())
 }
 
@@ -29,24 +129,26 @@ fn get_active_window_handle() -> Option<HWND> {
     unsafe { GetForegroundWindow().into() } // 调用Windows API获取当前活动窗口句柄
 }
 
-// 获取窗口标题
-fn get_window_text(hwnd: HWND) -> Option<String> {
+// 获取窗口标题，标题可能属于隐私浏览窗口，释放时清零，不留在进程内存中
+fn get_window_text(hwnd: HWND) -> Option<Zeroizing<String>> {
     unsafe {
         let length = GetWindowTextLengthW(hwnd) + 1; // 获取窗口标题的长度，并加1以包含终止符
         if length == 0 {
             return None; // 如果长度为0，则没有标题
         }
-        let mut buffer = vec![0u16; length as usize]; // 创建一个缓冲区存储宽字符标题
+        let mut buffer = Zeroizing::new(vec![0u16; length as usize]); // 创建一个缓冲区存储宽字符标题，释放时清零
         let copied = GetWindowTextW(hwnd, &mut buffer); // 获取窗口标题
         if copied == 0 {
             return None; // 如果复制的字符数为0，表示获取失败
         }
-        // 转换UTF-16为Rust字符串
-        Some(
-            String::from_utf16_lossy(&buffer[..copied as usize]) // 将UTF-16编码转换为Rust的String
-                .trim_end_matches('\u{0}') // 去除末尾的空字符
-                .to_string(),
-        )
+        // 转换UTF-16为Rust字符串，不产生中间的副本
+        // 每个UTF-16单元最多对应3个UTF-8字节，预先分配足够的容量，避免扩容时在旧的内存中留下副本
+        let mut title = Zeroizing::new(String::with_capacity(copied as usize * 3));
+        title.extend(char::decode_utf16(buffer[..copied as usize].iter().copied()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+        while title.ends_with('\u{0}') {
+            title.pop(); // 去除末尾的空字符
+        }
+        Some(title)
     }
 }
 
@@ -63,44 +165,617 @@ fn get_process_id(hwnd: HWND) -> Option<u32> {
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 初始化日志
-    setup_logging()?;
+// 窗口是否属于监视程序自身：本进程的窗口（浮窗等），或本程序所在的控制台窗口
+// 控制台窗口由 conhost 创建，不一定能按进程ID认出，所以再和 GetConsoleWindow 比较
+fn own_window(hwnd: HWND, pid: u32) -> bool {
+    pid == std::process::id() || unsafe { GetConsoleWindow() } == hwnd
+}
+
+// 经过启动窗口的检查后写入窗口切换记录：新启动的进程的窗口先保留，很快被同一进程的其他窗口取代时并入之后的窗口
+fn submit(pipeline: &Pipeline, metrics: &Mutex<Metrics>, detected: Instant, event: FocusEvent, splash: Option<&mut Splash>, mut pulse: Option<&mut Pulse>) {
+    let Some(splash) = splash else {
+        return record(pipeline, metrics, detected, &event, pulse);
+    };
+    for (event, detected) in splash.push(event, detected) {
+        record(pipeline, metrics, detected, &event, pulse.as_deref_mut());
+    }
+}
+
+// 把记录交给各个输出目标，失败时只记录错误，不中断监视
+// detected 为发现窗口切换的时间，用于统计生成记录的延迟，写入的延迟由各个输出目标的工作线程统计
+// 状态心跳模式下改为发送当前状态的状态心跳
+fn record(pipeline: &Pipeline, metrics: &Mutex<Metrics>, detected: Instant, record: &FocusEvent, pulse: Option<&mut Pulse>) {
+    if let Ok(mut metrics) = metrics.lock() {
+        metrics.emit.add(detected.elapsed());
+    }
+    let sent = match pulse {
+        Some(pulse) => pipeline.send(record.time, &pulse.switch(record)),
+        None => pipeline.send(record.time, record),
+    };
+    if let Err(e) = sent {
+        error!("序列化事件记录失败: {}", e);
+    }
+}
+
+// 记录会话标记，并在日志中说明会话开始或结束的原因
+fn record_session(pipeline: &Pipeline, record: &SessionRecord) {
+    let last_seen = record.last_seen.map_or("未知".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+    match (record.marker, record.reason) {
+        (SessionMarker::Begin, SessionReason::CrashRecovered) => info!(
+            "会话 {} 开始，上一个会话 {} 没有正常结束，最后确认运行于 {}",
+            record.session,
+            record.previous.as_deref().unwrap_or("未知"),
+            last_seen
+        ),
+        (SessionMarker::Begin, SessionReason::Sleep) => {
+            info!("系统从睡眠中恢复，会话 {} 开始，睡眠前最后一次轮询于 {}", record.session, last_seen)
+        }
+        (SessionMarker::Begin, _) => info!("会话 {} 开始", record.session),
+        (SessionMarker::End, _) => info!("会话 {} 结束", record.session),
+    }
+    if let Err(e) = pipeline.send(record.time, record) {
+        error!("序列化会话标记失败: {}", e);
+    }
+}
+
+// 失败时输出说明并以非0状态退出
+fn main() -> ExitCode {
+    match execute() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e); // 用 Display 输出，多行的说明（如配置文件中的问题）逐行显示
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// 执行命令行指定的子命令
+fn execute() -> Result<(), Error> {
+    let args = cli::parse_args()?; // 解析命令行参数
+    let paths = Paths::resolve(args.data_dir.as_deref(), args.config.as_deref(), args.portable, args.instance.as_deref())?; // 确定各个文件的位置
+    let clock: SharedClock = Arc::new(SystemClock); // 所有需要当前时间的地方都从这里获取
+    match args.command {
+        Command::Run => run(&args, &paths, &clock),
+        Command::Init { force } => {
+            config::init(&paths.config, force)?;
+            println!("已生成默认配置文件: {}", paths.config.display());
+            Ok(())
+        }
+        Command::Paths => {
+            if paths.portable {
+                println!("便携模式");
+            }
+            if let Some(instance) = &paths.instance {
+                println!("实例: {}", instance);
+            }
+            for (name, path) in paths.describe() {
+                println!("{}: {}", name, path.display());
+            }
+            Ok(())
+        }
+        #[cfg(feature = "backup")]
+        Command::Backup { ref to } => {
+            let files = backup::backup(&paths, to, clock.as_ref())?;
+            println!("已备份 {} 个文件到 {}", files, to.display());
+            Ok(())
+        }
+        #[cfg(feature = "backup")]
+        Command::Restore { ref from, force } => {
+            let _lock = instance::InstanceLock::acquire(&paths)?; // 监视程序正在写入的文件被替换后，之后的记录会写进已删除的文件
+            let files = backup::restore(&paths, from, force)?;
+            println!("已从 {} 恢复 {} 个文件", from.display(), files);
+            Ok(())
+        }
+        Command::Maintain => {
+            let config = config::load(&paths.config, args.config.is_some())?; // 保留期限和压缩设置来自配置文件
+            let report = maintain::run(&paths, &config.maintenance, &config.archive, clock.as_ref())?;
+            println!("{}", report.summary());
+            Ok(())
+        }
+        Command::Anonymize { keep_categories, force } => {
+            if !force {
+                return Err(Error::Anonymize(AnonymizeError::NotForced));
+            }
+            let _lock = instance::InstanceLock::acquire(&paths)?; // 改写期间不允许监视程序启动并写入同一数据目录
+            let config = config::load(&paths.config, args.config.is_some())?; // 重新压缩时使用的压缩级别
+            let summary = anonymize::anonymize(&paths.events, &paths.cache, keep_categories, &config.archive)?;
+            println!(
+                "已匿名化 {} 个文件中的 {} 条记录{}，并删除了缓存中按天汇总的时长",
+                summary.files,
+                summary.records,
+                if summary.dropped > 0 { format!("，删除了 {} 行不完整的记录", summary.dropped) } else { String::new() }
+            );
+            println!("注意：运行日志目录 {} 中仍然保留原始的窗口标题和可执行文件路径，分享数据目录前请一并删除", paths.logs.display());
+            Ok(())
+        }
+        #[cfg(feature = "http")]
+        Command::TokenCreate(ref name) => {
+            std::fs::create_dir_all(&paths.data)?;
+            let (token, entry) = tokens::create(&paths.tokens, name, clock.now())?;
+            println!("已创建令牌 {}（id {}），它只显示这一次，请妥善保存:", entry.name, entry.id);
+            println!("{}", token);
+            Ok(())
+        }
+        #[cfg(feature = "http")]
+        Command::TokenList => {
+            let entries = tokens::load(&paths.tokens)?;
+            if entries.is_empty() {
+                println!("还没有创建令牌，本地HTTP接口不要求令牌");
+            }
+            for entry in entries {
+                println!("{}  {}  {}", entry.id, entry.created.format("%Y-%m-%d %H:%M:%S"), entry.name);
+            }
+            Ok(())
+        }
+        #[cfg(feature = "http")]
+        Command::TokenRevoke(ref key) => {
+            let entry = tokens::revoke(&paths.tokens, key)?;
+            println!("已吊销令牌 {}（id {}）", entry.name, entry.id);
+            if tokens::load(&paths.tokens)?.is_empty() {
+                println!("已经没有令牌，本地HTTP接口不再要求令牌");
+            }
+            Ok(())
+        }
+        Command::Capabilities => {
+            let config = config::load(&paths.config, args.config.is_some())?; // 能提供哪些数据取决于配置
+            println!("{}", serde_json::to_string_pretty(&capabilities::capabilities(&config))?);
+            Ok(())
+        }
+        Command::Categorize { interactive, days } => {
+            let metadata = Metadata::load(&paths.apps)?; // 已有的分类
+            let apps = categorize::uncategorized(&paths.events, &paths.cache, &metadata, days, clock.as_ref())?;
+            if !interactive || apps.is_empty() {
+                print!("{}", categorize::list(&apps, days));
+                return Ok(());
+            }
+            let assigned = categorize::interactive(&paths.apps, &metadata, &apps)?;
+            println!("\n已设置 {} 个程序的分类，写入 {}", assigned, paths.apps.display());
+            Ok(())
+        }
+        Command::Doctor => {
+            let checks = doctor::run(&paths, args.config.is_some(), clock.as_ref());
+            for check in &checks {
+                println!("{}", check.describe());
+            }
+            let failed = checks.iter().filter(|c| matches!(c.status, doctor::Status::Fail)).count();
+            if failed > 0 {
+                return Err(Error::ChecksFailed(failed));
+            }
+            Ok(())
+        }
+        Command::Inspect { hwnd, pid } => {
+            let config = config::load(&paths.config, args.config.is_some())?; // 按当前配置给出应用标识和分类
+            let metadata = Metadata::load(&paths.apps)?;
+            let target = match (hwnd, pid) {
+                (Some(hwnd), _) => inspect::Target::Window(hwnd),
+                (_, Some(pid)) => inspect::Target::Process(pid),
+                _ => inspect::Target::Foreground,
+            };
+            for (name, value) in inspect::inspect(target, &config, &metadata)? {
+                println!("{}: {}", name, value);
+            }
+            Ok(())
+        }
+        Command::RulesTest { ref exe, ref title } => {
+            let config = config::load(&paths.config, args.config.is_some())?;
+            let metadata = Metadata::load(&paths.apps)?;
+            for (name, value) in rules::test(exe, title.as_deref(), &config, &metadata, clock.as_ref())? {
+                println!("{}: {}", name, value);
+            }
+            Ok(())
+        }
+        Command::Report { ref kind, ref day, ref drill_down, weeks } => {
+            let day = report::parse_day(day, clock.as_ref()).map_err(Error::Argument)?;
+            let drill_down = drill_down.as_deref().map(report::DrillDown::parse).transpose().map_err(Error::Argument)?;
+            let metadata = Metadata::load(&paths.apps)?; // 分类来自应用信息文件
+            let config = config::load(&paths.config, args.config.is_some())?;
+            let work = workhours::WorkHours::new(&config.work_hours);
+            let text = match kind.as_str() {
+                "timeline" => report::timeline(&paths.events, &metadata, &work, day, clock.as_ref())?,
+                "apps" => report::apps(&paths.events, &metadata, &work, day, drill_down.as_ref(), clock.as_ref())?,
+                "quality" => report::quality(&paths.events, day, weeks, clock.as_ref())?,
+                _ => unreachable!("参数解析阶段已检查报告名称"),
+            };
+            print!("{}", text);
+            Ok(())
+        }
+        Command::Export { ref format, ref from, ref to, ref by, ref output } => {
+            let pivot = export::Pivot::parse(by.as_deref(), format).map_err(Error::Argument)?;
+            let to = to.as_deref().map_or(Ok(clock.today()), |d| report::parse_day(d, clock.as_ref())).map_err(Error::Argument)?;
+            let from = from.as_deref().map_or(Ok(to - chrono::Days::new(29)), |d| report::parse_day(d, clock.as_ref())).map_err(Error::Argument)?;
+            let metadata = Metadata::load(&paths.apps)?; // 分类和显示名称来自应用信息文件
+            let text = match format.as_str() {
+                "daily-matrix" => export::daily_matrix(&paths.events, &paths.cache, &metadata, from, to, pivot, clock.as_ref())?,
+                "perfetto" => export::perfetto(&paths.events, &metadata, from, to, pivot, clock.as_ref())?,
+                "dot" => export::dot(&paths.events, &metadata, from, to, pivot, clock.as_ref())?,
+                _ => unreachable!("参数解析阶段已检查导出格式"),
+            };
+            match output {
+                // CSV写入文件时加上 BOM，Excel 才会按 UTF-8 打开含中文的列名
+                Some(path) => {
+                    let bom = if format == "daily-matrix" { "\u{feff}" } else { "" };
+                    std::fs::write(path, format!("{}{}", bom, text)).map_err(|source| Error::File { action: FileAction::Write, path: path.clone(), source })?;
+                    println!("已导出 {} 到 {}", format, path.display());
+                }
+                None => print!("{}", text),
+            }
+            Ok(())
+        }
+        #[cfg(feature = "schema")]
+        Command::Schema(ref kind) => {
+            print!("{}", schema::generate(kind));
+            Ok(())
+        }
+        Command::Completions(ref shell) => {
+            print!("{}", completions::script(shell));
+            Ok(())
+        }
+        Command::Help => {
+            print!("{}", cli::usage());
+            Ok(())
+        }
+    }
+}
+
+// 监视前台窗口，直到收到 Ctrl+C、关闭控制台窗口等退出请求
+fn run(args: &Args, paths: &Paths, clock: &SharedClock) -> Result<(), Error> {
+    let config = config::load(&paths.config, args.config.is_some())?; // 读取并校验配置文件，有误时直接退出
+    let trace_win32 = args.trace_win32 || config.trace_win32; // 命令行和配置文件任一开启即生效
+    let track_self = args.track_self || config.track_self || args.soak.is_some(); // 压力测试的测试窗口属于本进程，需要记录
+
+    let _lock = instance::InstanceLock::acquire(paths)?; // 同一数据目录只允许一个监视程序，运行期间一直持有
+    paths.create_dirs()?; // 首次运行时创建数据目录
+    crash::install_handler(paths.crash.clone(), clock.clone()); // 崩溃时写入崩溃报告
+
+    // 初始化日志，跟踪模式下输出Trace级别
+    setup_logging(if trace_win32 { LevelFilter::Trace } else { config.log_level() }, paths, &config.archive, clock)?;
 
     info!("程序启动"); // 记录程序启动信息
+    if let Some(instance) = &paths.instance {
+        info!("实例: {}", instance);
+    }
+    if trace_win32 {
+        info!("已开启Win32跟踪模式，将记录每一次轮询结果及其被过滤的原因");
+    }
 
+    let window_filter = filter::WindowFilter::new(&config.filter); // 按窗口样式和大小过滤
     let mut last_hwnd: Option<HWND> = None; // 存储上一个活动窗口的句柄，以检测窗口变化
-    let mut system = System::new(); // 创建一个System对象，用于获取系统信息
+    let mut processes = Processes::new(config.memory.max_processes); // 查询进程信息
+    let metrics = Arc::new(Mutex::new(Metrics::default())); // 热路径延迟统计，每次心跳时报告并清空
+    let mut pipeline = Pipeline::new(Arc::clone(&metrics)); // 记录的输出目标，各自在独立的线程中写入
+    let live = Arc::new(Mutex::new(aggregate::Live::load(&paths.events, &paths.cache, clock.as_ref()))); // 今天各应用的时长，启动时读取一次事件记录，之后随写入更新
+    pipeline.add(Box::new(Journal::new(paths.events.clone(), config.archive.clone(), clock.clone(), Arc::clone(&live))), &config.fields.journal)?; // 事件记录，每次窗口切换追加一行
+    #[cfg(feature = "http")]
+    if config.http.enabled {
+        let capabilities = serde_json::to_string(&capabilities::capabilities(&config))?;
+        let store = Store::new(paths.events.clone(), Metadata::load(&paths.apps)?, config.fields.http.clone(), clock.clone());
+        pipeline.add(Box::new(http::start(&config.http, capabilities, store, tokens::Tokens::new(paths.tokens.clone()), Arc::clone(&metrics), Arc::clone(&live))?), &config.fields.http)?; // 本地HTTP接口，监听失败时直接退出
+    }
+    let mut alarms = alarm::Alarms::new(&config.alarm, pipeline.totals()); // 记录中断、写入失败等异常时告警
+    let mut scheduler = schedule::Scheduler::new(&config.schedule, paths, &config.work_hours, clock.clone()); // [[schedule]] 定时任务
+    let mut metadata = Metadata::load(&paths.apps)?; // 应用信息文件有误时直接退出，运行中修改则自动重新加载
+    if config.hud.enabled {
+        let hud = hud::start(&config.hud, Arc::clone(&live), Metadata::load(&paths.apps)?, clock.clone())?;
+        pipeline.add(Box::new(hud), &[])?; // 浮窗需要完整的记录，不受 [fields] 限制
+    }
+    if !config.trigger.is_empty() {
+        let triggers = trigger::TriggerSink::new(&config.trigger, &paths.events, clock)?;
+        pipeline.add(Box::new(triggers), &[])?; // 触发规则按可执行文件路径匹配，不受 [fields] 限制
+    }
+    let identities = Identities::new(&config.alias)?; // 应用别名规则
+    let environment = EnvironmentRules::new(&config.environment)?; // 需要记录的环境变量
+    let cache_budget = config.memory.cache_kb as usize * 1024; // 缓存的内存上限（字节）
+    let mut versions = VersionCache::new(cache_budget); // 可执行文件版本信息的缓存
+    let enricher = enrich::Enricher::start(&config.enrichment, cache_budget, clock.clone())?; // 在后台查询数字签名等补充信息
+    let mut heartbeat = Heartbeat::new(if args.soak.is_some() { 1 } else { config.heartbeat_minutes }); // 定期报告内存占用和延迟，压力测试时每分钟一次
+    let mut last_poll = Instant::now(); // 上一次轮询的时间，用于估算发现窗口切换的延迟
+    let mut presentation = PresentationMonitor::new(); // 是否有程序正在演示
+    let mut last_maintenance = Instant::now(); // 上次定期维护的时间，启动后满一个周期才第一次维护
+    let mut secure_desktop = false; // 输入是否在安全桌面上，已经写过记录时不再重复检测
+    let mut hook = if config.backend == Backend::Hybrid { Some(Hook::start()?) } else { None }; // 注册失败时直接退出
+    let verify = Duration::from_secs(config.verify_seconds); // 混合方式下核对的间隔
+    if hook.is_some() {
+        info!("使用 WinEvent 钩子发现窗口切换，每 {} 秒轮询一次核对", config.verify_seconds);
+    }
+    let mut wake = Wake::Verify; // 混合方式下本次被唤醒的原因，第一次按核对处理
+    let mut corrections = 0; // 自上次心跳以来核对发现钩子漏报的次数
 
-    loop {
+    if !config.priority_boost {
+        timer::disable_priority_boost();
+    }
+    let timer = Timer::new(config.timer_tolerance_ms); // 轮询间隔的等待
+    let mut minimal = config.capture == CaptureMode::Minimal; // 当前是否处于精简模式
+    if minimal {
+        info!("记录方式为精简模式，只记录前台程序的可执行文件路径");
+    }
+    shutdown::install(); // 退出时写入会话结束标记
+    let (mut session, begin) = Session::begin(paths.session.clone(), clock.now()); // 本次运行的会话
+    record_session(&pipeline, &begin);
+    let start_memory = processes.own_memory(); // 压力测试结束时对比内存占用
+    let soak = args.soak.map(Soak::start).transpose()?; // 到时后请求退出
+    let mut pulse = config.pulse.enabled.then(|| Pulse::new(&config.pulse)); // 状态心跳模式下按固定间隔重复发送当前状态
+    let mut bursts = Bursts::new(config.burst_ms); // 连续快速的切换分为一组
+    let mut splash = Splash::new(&config.splash); // 新启动的进程的窗口先保留，启动画面并入之后的主窗口
+    if pulse.is_some() {
+        info!(
+            "记录方式为状态心跳，每 {} 秒发送一次当前前台窗口，合并窗口 {} 秒",
+            config.pulse.interval_seconds, config.pulse.merge_seconds
+        );
+    }
+
+    while !shutdown::requested() {
+        let now = clock.now(); // 每次循环只读一次时钟，这一轮写入的记录使用同一个时间；连续快速切换的组内记录使用组内第一条的时间
+        if let Some(state) = if minimal { None } else { presentation.poll() } { // 精简模式下不查询演示状态
+            info!("{} | 演示状态变化: {}", now.format("%Y-%m-%d %H:%M:%S"), state.name());
+            last_hwnd = None; // 即使前台窗口没变也重新记录一条，使记录之间的区间带上新的演示状态
+        }
+        let polled = Instant::now();
+        let mut since_last_poll = polled - std::mem::replace(&mut last_poll, polled); // 窗口切换发生在这段时间内
+        let longest = if hook.is_some() { verify } else { Duration::from_millis(config.interval_ms.max(config.minimal.interval_ms)) }; // 两次轮询之间正常的最长间隔
+        if session::slept(since_last_poll, longest) {
+            // 两次轮询之间没有记录，结束当前会话，使分析时能看出这段时间没有覆盖
+            let last_seen = now - chrono::TimeDelta::from_std(since_last_poll).unwrap_or_default();
+            if let Some((event, detected)) = splash.as_mut().and_then(Splash::take) {
+                record(&pipeline, &metrics, detected, &event, pulse.as_mut()); // 保留的记录在会话结束之前写入
+            }
+            record_session(&pipeline, &session.resume(now, last_seen));
+            bursts.reset();
+            last_hwnd = None; // 在新会话中重新记录当前前台窗口
+            if let Some(pulse) = pulse.as_mut() {
+                pulse.reset(); // 不把睡眠前的状态带到新会话
+            }
+            since_last_poll = Duration::ZERO; // 睡眠的时间不计入发现延迟
+        }
+        session.touch(now);
         if let Some(hwnd) = get_active_window_handle() { // 获取当前活动窗口句柄
-            if Some(hwnd) != last_hwnd { // 检查是否与上一次的句柄不同，表示窗口发生变化
+            if hwnd.is_invalid() {
+                trace!("轮询: GetForegroundWindow 返回空句柄（可能正在切换窗口或处于安全桌面）");
+                // 切换窗口时也会短暂返回空句柄，只有输入确实在安全桌面上才写记录，之后不再把时间算给之前的前台程序
+                if !secure_desktop {
+                    if let InputDesktop::Secure(desktop) = desktop::input() {
+                        info!("{} | 切换到安全桌面: {}", now.format("%Y-%m-%d %H:%M:%S"), desktop.as_deref().unwrap_or("未知"));
+                        if let Some((event, detected)) = splash.as_mut().and_then(Splash::take) {
+                            record(&pipeline, &metrics, detected, &event, pulse.as_mut());
+                        }
+                        if let Err(e) = pipeline.send(now, &SecureDesktopRecord { time: now, desktop }) {
+                            error!("序列化安全桌面记录失败: {}", e);
+                        }
+                        if let Some(pulse) = pulse.as_mut() {
+                            pulse.reset(); // 不再重复发送之前的状态
+                        }
+                        secure_desktop = true;
+                        last_hwnd = Some(hwnd);
+                    }
+                }
+            } else {
+                secure_desktop = false;
+                if let Some(alarms) = alarms.as_mut() {
+                    alarms.observed(); // 监视仍在进行，停留在同一个窗口时也不告警
+                }
+            }
+            if Some(hwnd) == last_hwnd {
+                trace!("轮询: 句柄 {:?} 未变化，已过滤", hwnd.0); // 与上一次相同，不输出事件
+            } else { // 与上一次的句柄不同，表示窗口发生变化
+                if hook.is_some() && wake == Wake::Verify && last_hwnd.is_some() && !hwnd.is_invalid() {
+                    // 钩子没有报告这次切换，由定期核对发现
+                    corrections += 1;
+                    if let Some(hook) = hook.as_mut() {
+                        hook.missed();
+                    }
+                    let since = now - chrono::TimeDelta::from_std(since_last_poll).unwrap_or_default();
+                    warn!("{} | 钩子漏报了一次窗口切换，由定期核对发现，切换发生在 {} 之后", now.format("%Y-%m-%d %H:%M:%S"), since.format("%H:%M:%S"));
+                    if let Err(e) = pipeline.send(now, &CorrectionRecord { time: now, since }) {
+                        error!("序列化纠正记录失败: {}", e);
+                    }
+                }
                 last_hwnd = Some(hwnd); // 更新最后一个窗口句柄
-                if let Some(pid_value) = get_process_id(hwnd) { // 获取窗口所属进程的ID
-                    let pid = Pid::from(pid_value as usize); // 将u32类型的PID转换为sysinfo库的Pid类型
-                    // 刷新特定进程的信息，第二个参数决定是否移除已经结束的进程
-                    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
-                    if let Some(process) = system.process(pid) { // 获取进程信息
-                        let exe_path = process
-                            .exe()
-                            .map_or("未知路径".to_string(), |p| p.to_string_lossy().to_string()); // 获取可执行文件路径，如果不可用则标记为“未知路径”
-                        let window_title = get_window_text(hwnd).map_or("未知窗口".to_string(), |title| title); // 获取窗口标题，如果获取失败则标记为“未知窗口”
-                        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S"); // 获取当前时间并格式化
-                        info!(
-                            "{} | 进程ID: {} | 窗口标题: {} | 执行路径: {}",
-                            timestamp, pid_value, window_title, exe_path
-                        ); // 记录日志信息，包括时间、进程ID、窗口标题和执行路径
+                if hook.is_none() || wake == Wake::Verify {
+                    // 钩子报告的切换几乎没有发现延迟，距上一次轮询的时间不能作为上限
+                    if let Ok(mut metrics) = metrics.lock() {
+                        metrics.detect.add(since_last_poll);
+                    }
+                }
+                let pid = get_process_id(hwnd); // 获取窗口所属进程的ID
+                if pid.is_some_and(|pid| !track_self && own_window(hwnd, pid)) {
+                    trace!("轮询: 句柄 {:?} 是本程序自己的窗口，已过滤", hwnd.0); // 打开控制台或浮窗不计入统计
+                } else if let Some(reason) = window_filter.reason(hwnd) {
+                    trace!("轮询: 句柄 {:?} 的{}，已被 [filter] 过滤", hwnd.0, reason); // 工具提示、输入法候选窗口等
+                } else if let Some(pid_value) = pid {
+                    // 列出了自动切换的程序时，先用开销最小的接口查出可执行文件，再决定用哪种方式记录
+                    let image = if minimal || !config.minimal.apps.is_empty() { capture::process_image(pid_value) } else { None };
+                    let listed = image.as_deref().is_some_and(|e| config.minimal.is_listed(e));
+                    if (config.capture == CaptureMode::Minimal || listed) != minimal {
+                        minimal = !minimal;
+                        info!("{}精简模式", if minimal { "进入" } else { "退出" });
+                    }
+                    if minimal {
+                        // 精简模式只记录进程ID和可执行文件路径，不读取窗口标题、版本信息等
+                        info!("{} | 进程ID: {} | 执行路径: {}", now.format("%Y-%m-%d %H:%M:%S"), pid_value, image.as_deref().unwrap_or("未知路径"));
+                        let id = session.event_id();
+                        let (burst, time) = bursts.mark(polled, &id, now);
+                        let event = FocusEvent { exe: image, id: Some(id), burst, ..FocusEvent::bare(time, pid_value) };
+                        submit(&pipeline, &metrics, polled, event, splash.as_mut(), pulse.as_mut());
                     } else {
-                        // 如果进程可能已经结束
-                        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S"); // 获取当前时间并格式化
-                        info!(
-                            "{} | 进程ID: {} 不存在或已结束",
-                            timestamp, pid_value
-                        ); // 记录进程不存在或已结束的信息
+                        if let Some(exe) = processes.exe(pid_value) { // 获取进程的可执行文件路径
+                            let exe_path = exe.clone().unwrap_or_else(|| "未知路径".to_string()); // 如果不可用则标记为“未知路径”
+                            let title = get_window_text(hwnd); // 获取窗口标题
+                            // 隐私浏览窗口不记录原标题，日志中也不输出
+                            let private = exe.as_deref().zip(title.as_deref()).is_some_and(|(e, t)| private::is_private(e, t));
+                            let title = if private {
+                                drop(title); // 原标题清零后释放，不留在内存中
+                                Some(private::PRIVATE_TITLE.to_string())
+                            } else {
+                                title.map(|mut t| std::mem::take(&mut *t)) // 要写入记录的标题移出，不复制
+                            };
+                            let timestamp = now.format("%Y-%m-%d %H:%M:%S"); // 格式化当前时间
+                            let version = exe.as_deref().and_then(|e| versions.get(e)).cloned().unwrap_or_default(); // 产品名称、公司名称、文件版本
+                            metadata.refresh(); // 应用信息文件有修改时重新加载
+                            let app_name = metadata.display_name(&exe_path, version.product.as_deref()); // 用户设置的名称 > 产品名称 > 可执行文件名
+                            let category = metadata
+                                .get(&exe_path)
+                                .and_then(|app| app.category.as_deref())
+                                .map_or(String::new(), |c| format!(" | 分类: {}", c)); // 设置了分类时一并输出
+                            info!(
+                                "{} | 进程ID: {} | 应用: {}{} | 窗口标题: {} | 执行路径: {}",
+                                timestamp, pid_value, app_name, category, title.as_deref().unwrap_or("未知窗口"), exe_path // 标题获取失败时标记为“未知窗口”，借用而不复制标题
+                            ); // 记录日志信息，包括时间、进程ID、应用、窗口标题和执行路径
+                            let app = exe.as_deref().map(|e| identities.resolve(e)); // 稳定的应用标识
+                            let lang = if config.detect_language && !private { title.as_deref().and_then(language::detect) } else { None };
+                            let allowlist = exe.as_deref().map_or(Vec::new(), |e| environment.allowlist(e));
+                            let env = if allowlist.is_empty() {
+                                Default::default() // 没有配置的程序不读取环境块
+                            } else {
+                                processes.environ(pid_value, &allowlist)
+                            };
+                            if !env.is_empty() {
+                                let vars: Vec<String> = env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                                info!("{} | 进程ID: {} | 环境变量: {}", timestamp, pid_value, vars.join(", "));
+                            }
+                            let id = session.event_id();
+                            let (burst, time) = bursts.mark(polled, &id, now);
+                            if let Some((enricher, exe)) = enricher.as_ref().zip(exe.as_deref()) {
+                                enricher.submit(&id, exe); // 查出的结果在之后的循环中写入，总在这条记录之后
+                            }
+                            submit(
+                                &pipeline,
+                                &metrics,
+                                polled,
+                                FocusEvent {
+                                    time,
+                                    pid: pid_value,
+                                    title,
+                                    exe,
+                                    app,
+                                    product: version.product,
+                                    company: version.company,
+                                    version: version.version,
+                                    presenting: presentation.state().presenting(),
+                                    env,
+                                    lang: lang.map(str::to_string),
+                                    id: Some(id),
+                                    burst,
+                                    enrichment: Default::default(),
+                                },
+                                splash.as_mut(),
+                                pulse.as_mut(),
+                            );
+                        } else {
+                            // 如果进程可能已经结束
+                            let timestamp = now.format("%Y-%m-%d %H:%M:%S"); // 格式化当前时间
+                            info!(
+                                "{} | 进程ID: {} 不存在或已结束",
+                                timestamp, pid_value
+                            ); // 记录进程不存在或已结束的信息
+                            let id = session.event_id();
+                            let (burst, time) = bursts.mark(polled, &id, now);
+                            submit(
+                                &pipeline,
+                                &metrics,
+                                polled,
+                                FocusEvent {
+                                    presenting: presentation.state().presenting(),
+                                    burst,
+                                    id: Some(id),
+                                    ..FocusEvent::bare(time, pid_value)
+                                },
+                                splash.as_mut(),
+                                pulse.as_mut(),
+                            );
+                        }
                     }
+                } else {
+                    trace!("轮询: 句柄 {:?} 已变化，但 GetWindowThreadProcessId 未返回有效进程ID，已过滤", hwnd.0);
                 }
             }
         }
-        sleep(Duration::from_millis(10)); // 休眠10毫秒，作为下次检查的间隔
+        if heartbeat.due() {
+            let mut record = HeartbeatRecord::new(now);
+            record.memory = processes.own_memory();
+            let (entries, bytes) = versions.usage();
+            record.caches.insert("versions", CacheUsage { entries, bytes });
+            if let Some(enricher) = enricher.as_ref() {
+                let (entries, bytes) = enricher.usage();
+                record.caches.insert("signers", CacheUsage { entries, bytes });
+            }
+            record.cache_budget = cache_budget;
+            record.processes = processes.count();
+            record.latency = metrics.lock().ok().map(|mut m| m.take_report());
+            record.corrections = hook.is_some().then(|| std::mem::take(&mut corrections));
+            record.hook_restarts = hook.as_mut().map(Hook::take_restarts);
+            info!(
+                "心跳 | 内存: {} | 版本信息缓存: {} 项 {} | 进程表: {} 个进程",
+                record.memory.map_or("未知".to_string(), maintain::format_size),
+                entries,
+                maintain::format_size(bytes as u64),
+                record.processes
+            );
+            if let Some(latency) = &record.latency {
+                let p95 = |l: &Option<metrics::LatencyReport>| l.as_ref().map_or("-".to_string(), |l| format!("{:.1}ms", l.p95_ms));
+                info!("心跳 | 延迟p95 | 发现: {} | 生成记录: {}", p95(&latency.detect), p95(&latency.emit));
+                for (name, sink) in &latency.sinks {
+                    info!("心跳 | 延迟p95 | 写入 {}: {:.1}ms", name, sink.p95_ms);
+                }
+            }
+            if let Err(e) = pipeline.send(record.time, &record) {
+                error!("序列化心跳记录失败: {}", e);
+            }
+        }
+        if let Some((event, detected)) = splash.as_mut().and_then(Splash::due) {
+            record(&pipeline, &metrics, detected, &event, pulse.as_mut()); // 保留期间没有被同一进程的其他窗口取代
+        }
+        if let Some(record) = pulse.as_mut().and_then(|p| p.due(now)) {
+            if let Err(e) = pipeline.send(record.state.time, &record) {
+                error!("序列化状态心跳失败: {}", e);
+            }
+        }
+        for record in enricher.iter().flat_map(|e| e.drain()) {
+            if let Err(e) = pipeline.send(record.time, &record) {
+                error!("序列化补充信息失败: {}", e);
+            }
+        }
+        if let Some(alarms) = alarms.as_mut() {
+            alarms.check(pipeline.totals(), now);
+        }
+        maintain::schedule(paths, &config.maintenance, &config.archive, clock, &mut last_maintenance); // 到期时在后台执行定期维护
+        if let Some(scheduler) = scheduler.as_mut() {
+            scheduler.tick(now);
+        }
+        // 休眠一个轮询间隔，作为下次检查的间隔，精简模式下不小于 [minimal] 中设置的间隔
+        // 混合方式下等待钩子报告切换，最多等待到核对、状态心跳、启动窗口保留和心跳中最早到期的一个
+        let interval = if minimal { config.interval_ms.max(config.minimal.interval_ms) } else { config.interval_ms };
+        match hook.as_mut() {
+            Some(hook) => {
+                hook.watchdog(); // 钩子线程退出或连续漏报时重新注册
+                let deadlines = [pulse.as_ref().and_then(Pulse::remaining), splash.as_ref().and_then(Splash::remaining), heartbeat.remaining()];
+                wake = hook.wait(deadlines.into_iter().flatten().fold(verify, Duration::min));
+            }
+            None => timer.sleep(Duration::from_millis(interval)),
+        }
     }
-}
\ No newline at end of file
+
+    info!("收到退出请求");
+    if let Some(soak) = soak {
+        let (attempts, switched) = soak.finish();
+        let memory = |m: Option<u64>| m.map_or("未知".to_string(), maintain::format_size);
+        info!(
+            "压力测试结束 | 切换 {} 次，成功 {} 次 | 内存: {} -> {}",
+            attempts,
+            switched,
+            memory(start_memory),
+            memory(processes.own_memory())
+        );
+        if let Some(latency) = metrics.lock().ok().map(|mut m| m.take_report()) {
+            let max = |l: &Option<metrics::LatencyReport>| l.as_ref().map_or("-".to_string(), |l| format!("{:.1}ms", l.max_ms));
+            info!("压力测试结束 | 最后一分钟的最大延迟 | 发现: {} | 生成记录: {}", max(&latency.detect), max(&latency.emit));
+        }
+    }
+    if let Some((event, detected)) = splash.as_mut().and_then(Splash::take) {
+        record(&pipeline, &metrics, detected, &event, pulse.as_mut());
+    }
+    record_session(&pipeline, &session.end(clock.now()));
+    pipeline.close(); // 等待结束标记等剩余的记录写入完成
+    live.lock().unwrap_or_else(|e| e.into_inner()).save(); // 保存最后一次保存之后写入的记录
+    info!("程序退出");
+    shutdown::finished();
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate}; // 按日期判断文件是否过期
+use log::{error, info}; // 在监视程序中定期维护时记录结果
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+use crate::archive::{self, ArchiveConfig}; // 压缩尚未归档的旧文件
+use crate::clock::{Clock, SharedClock}; // 判断“今天”
+use crate::paths::Paths; // 需要维护的目录
+
+// 配置文件中的 [maintenance] 部分
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub events_days: u32,    // 事件记录保留天数，0表示永久保留
+    pub logs_days: u32,      // 运行日志和崩溃报告保留天数，0表示永久保留
+    pub interval_hours: u32, // 监视程序运行时每隔多少小时自动维护一次，0表示不自动维护
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        MaintenanceConfig { events_days: 0, logs_days: 30, interval_hours: 24 }
+    }
+}
+
+// 一次维护的结果
+#[derive(Default)]
+pub struct Report {
+    pub pruned: usize,     // 超过保留期限被删除的文件数
+    pub compressed: usize, // 补充压缩归档的文件数
+    pub cleaned: usize,    // 删除的残留临时文件和空文件数
+    pub bytes_before: u64, // 维护前占用的空间
+    pub bytes_after: u64,  // 维护后占用的空间
+}
+
+impl Report {
+    // 一行文字的维护结果说明
+    pub fn summary(&self) -> String {
+        format!(
+            "删除过期文件 {} 个，压缩归档 {} 个，清理残留文件 {} 个，释放空间 {}（{} -> {}）",
+            self.pruned,
+            self.compressed,
+            self.cleaned,
+            format_size(self.bytes_before.saturating_sub(self.bytes_after)),
+            format_size(self.bytes_before),
+            format_size(self.bytes_after),
+        )
+    }
+}
+
+// 临时文件超过这个时间没有更新，就认为是之前中断留下的残留
+const STALE_TEMP: Duration = Duration::from_secs(60 * 60);
+
+// 执行一次维护：按保留期限删除旧文件、清理中断留下的临时文件和空文件、压缩尚未归档的旧文件
+pub fn run(paths: &Paths, config: &MaintenanceConfig, archive: &ArchiveConfig, clock: &dyn Clock) -> std::io::Result<Report> {
+    let dirs = [(&paths.events, config.events_days), (&paths.logs, config.logs_days), (&paths.crash, config.logs_days)];
+    let today = clock.today();
+    let mut report = Report {
+        bytes_before: dirs.iter().map(|(dir, _)| dir_size(dir)).sum(),
+        ..Report::default()
+    };
+
+    for (dir, keep_days) in dirs {
+        for path in files(dir) {
+            let date = file_date(&path);
+            if keep_days > 0 && date.is_some_and(|d| (today - d).num_days() >= keep_days as i64) {
+                std::fs::remove_file(&path)?;
+                report.pruned += 1;
+            } else if is_stale_temp(&path) || (date.is_some_and(|d| d < today) && is_empty(&path)) {
+                std::fs::remove_file(&path)?;
+                report.cleaned += 1;
+            } else if date.is_some_and(|d| d < today) && archive::is_archivable(&path) {
+                // 今天的文件还在写入，只归档之前的文件
+                match archive::compress_file(&path, archive) {
+                    Ok(_) => report.compressed += 1,
+                    Err(e) => error!("压缩归档 {} 失败: {}", path.display(), e),
+                }
+            }
+        }
+    }
+
+    report.bytes_after = dirs.iter().map(|(dir, _)| dir_size(dir)).sum();
+    Ok(report)
+}
+
+// 在监视程序中定期维护，last 为上次维护的时间，到期时更新
+// 维护在后台线程中进行，不影响窗口切换的检测
+pub fn schedule(paths: &Paths, config: &MaintenanceConfig, archive: &ArchiveConfig, clock: &SharedClock, last: &mut Instant) {
+    if config.interval_hours == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(config.interval_hours as u64 * 60 * 60);
+    if last.elapsed() < interval {
+        return;
+    }
+    *last = Instant::now();
+    let (paths, config, archive, clock) = (paths.clone(), config.clone(), archive.clone(), clock.clone());
+    std::thread::spawn(move || match run(&paths, &config, &archive, clock.as_ref()) {
+        Ok(report) => info!("定期维护完成: {}", report.summary()),
+        Err(e) => error!("定期维护失败: {}", e),
+    });
+}
+
+// 目录下的所有文件，目录不存在时为空
+fn files(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect())
+        .unwrap_or_default()
+}
+
+// 目录下所有文件的总大小
+fn dir_size(dir: &Path) -> u64 {
+    files(dir).iter().filter_map(|p| p.metadata().ok()).map(|m| m.len()).sum()
+}
+
+// 文件对应的日期：优先取文件名中的 YYYY-MM-DD 或 YYYYMMDD，取不到时用修改时间
+fn file_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    for (len, format) in [(10, "%Y-%m-%d"), (8, "%Y%m%d")] {
+        for start in 0..name.len().saturating_sub(len - 1) {
+            if let Some(date) = name.get(start..start + len).and_then(|s| NaiveDate::parse_from_str(s, format).ok()) {
+                return Some(date);
+            }
+        }
+    }
+    let modified: DateTime<Local> = path.metadata().ok()?.modified().ok()?.into();
+    Some(modified.date_naive())
+}
+
+// 是否为之前中断（压缩归档、备份、恢复）留下的临时文件
+fn is_stale_temp(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "tmp")
+        && path
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|m| m.elapsed().is_ok_and(|e| e > STALE_TEMP))
+}
+
+// 文件是否为空
+fn is_empty(path: &Path) -> bool {
+    path.metadata().is_ok_and(|m| m.len() == 0)
+}
+
+// 把字节数格式化为便于阅读的大小
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::{error, info}; // 记录重新加载的结果
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从应用信息文件读取
+
+use crate::config; // 校验并读取应用信息文件
+use crate::error::Error; // 应用信息文件有误
+
+// 用户为某个程序设置的显示信息，所有项都可以省略
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct AppMetadata {
+    pub name: Option<String>,     // 显示名称，如把 msedgewebview2.exe 显示为 Teams
+    pub color: Option<String>,    // 报告中使用的颜色，#RRGGBB
+    pub icon: Option<String>,     // 图标文件路径
+    pub category: Option<String>, // 分类
+}
+
+// 应用信息表，来自用户编辑的 apps.toml，按可执行文件名（不区分大小写）查找
+// 文件修改后会在下一次查找时自动重新加载，不需要重启监视程序
+pub struct Metadata {
+    path: PathBuf,                       // apps.toml 的路径
+    modified: Option<SystemTime>,        // 上次加载时文件的修改时间
+    apps: HashMap<String, AppMetadata>,  // 小写的可执行文件名 -> 应用信息
+}
+
+impl Metadata {
+    // 加载应用信息文件，文件有误时返回错误
+    pub fn load(path: &Path) -> Result<Metadata, Error> {
+        let mut metadata = Metadata { path: path.to_path_buf(), modified: None, apps: HashMap::new() };
+        metadata.reload()?;
+        Ok(metadata)
+    }
+
+    // 重新读取文件
+    fn reload(&mut self) -> Result<(), Error> {
+        self.modified = modified_time(&self.path);
+        let apps = config::load_apps::<AppMetadata>(&self.path)?;
+        self.apps = apps.into_iter().map(|(exe, app)| (exe.to_lowercase(), app)).collect();
+        Ok(())
+    }
+
+    // 文件有变化时重新加载，文件有误时保留之前的内容并记录错误
+    pub fn refresh(&mut self) {
+        if modified_time(&self.path) == self.modified {
+            return;
+        }
+        match self.reload() {
+            Ok(()) => info!("已重新加载应用信息文件 {}，共 {} 项", self.path.display(), self.apps.len()),
+            Err(e) => error!("{}，继续使用之前的应用信息", e),
+        }
+    }
+
+    // 查找可执行文件对应的应用信息，exe 可以是完整路径或文件名
+    pub fn get(&self, exe: &str) -> Option<&AppMetadata> {
+        self.apps.get(&exe_name(exe).to_lowercase())
+    }
+
+    // 已经使用的全部分类
+    pub fn categories(&self) -> BTreeSet<String> {
+        self.apps.values().filter_map(|app| app.category.clone()).collect()
+    }
+
+    // 用户为应用设置的颜色（#RRGGBB）的红绿蓝分量，没有设置时为None
+    pub fn color(&self, exe: &str) -> Option<(u8, u8, u8)> {
+        let hex = self.get(exe)?.color.as_deref()?.strip_prefix('#')?;
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        Some((channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    // 应用的显示名称：优先使用用户设置的名称，其次是版本资源中的产品名称，最后是可执行文件名
+    pub fn display_name(&self, exe: &str, product: Option<&str>) -> String {
+        self.get(exe)
+            .and_then(|app| app.name.clone())
+            .or_else(|| product.map(str::to_string))
+            .unwrap_or_else(|| exe_name(exe).to_string())
+    }
+}
+
+// 从完整路径中取出可执行文件名
+pub fn exe_name(exe: &str) -> &str {
+    exe.rsplit(['\\', '/']).next().unwrap_or(exe)
+}
+
+// 文件的修改时间，文件不存在时为None
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
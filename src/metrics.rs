@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Serialize; // 写入心跳记录
+
+// 延迟分布的桶上限（毫秒），超过最后一个的归入溢出桶
+const BUCKETS_MS: &[u64] = &[1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 5000];
+
+// 一段时间内某一环节延迟的统计
+#[derive(Default)]
+pub struct Latency {
+    count: u64,           // 样本数
+    total: Duration,      // 总和，用于计算平均值
+    max: Duration,        // 最大值
+    buckets: Vec<u64>,    // 各个桶的样本数，最后一个为溢出桶
+}
+
+impl Latency {
+    // 记录一个样本
+    pub fn add(&mut self, latency: Duration) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; BUCKETS_MS.len() + 1];
+        }
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        let ms = latency.as_secs_f64() * 1000.0;
+        let bucket = BUCKETS_MS.iter().position(|b| ms <= *b as f64).unwrap_or(BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+    }
+
+    // 生成统计报告，没有样本时返回None
+    fn report(&self) -> Option<LatencyReport> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(LatencyReport {
+            count: self.count,
+            mean_ms: self.total.as_secs_f64() * 1000.0 / self.count as f64,
+            p95_ms: self.percentile(0.95),
+            max_ms: self.max.as_secs_f64() * 1000.0,
+        })
+    }
+
+    // 按桶估算的百分位数，取样本所在桶的上限，落在溢出桶时取最大值
+    fn percentile(&self, p: f64) -> f64 {
+        let target = (self.count as f64 * p).ceil() as u64;
+        let mut seen = 0;
+        for (i, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return BUCKETS_MS.get(i).map_or(self.max.as_secs_f64() * 1000.0, |b| *b as f64);
+            }
+        }
+        self.max.as_secs_f64() * 1000.0
+    }
+}
+
+// 延迟统计报告，写入心跳记录
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct LatencyReport {
+    pub count: u64,   // 样本数
+    pub mean_ms: f64, // 平均值（毫秒）
+    pub p95_ms: f64,  // 95百分位数（毫秒），按分桶估算
+    pub max_ms: f64,  // 最大值（毫秒）
+}
+
+// 采集热路径上各个环节的延迟：
+// detect 为系统切换前台窗口到被发现的间隔，轮询方式下无法得知确切的切换时间，取距上一次轮询的时间作为上限；
+// emit 为发现切换到生成记录的间隔，即查询进程信息、标题、版本信息等的开销；
+// sinks 为生成记录到各个输出目标写入完成的间隔
+#[derive(Default)]
+pub struct Metrics {
+    pub detect: Latency,
+    pub emit: Latency,
+    sinks: BTreeMap<&'static str, Latency>,
+}
+
+impl Metrics {
+    // 某个输出目标的延迟统计
+    pub fn sink(&mut self, name: &'static str) -> &mut Latency {
+        self.sinks.entry(name).or_default()
+    }
+
+    // 自上次心跳以来的统计，不清空，用于 /metrics
+    pub fn report(&self) -> MetricsReport {
+        MetricsReport {
+            detect: self.detect.report(),
+            emit: self.emit.report(),
+            sinks: self.sinks.iter().filter_map(|(name, l)| l.report().map(|r| (*name, r))).collect(),
+        }
+    }
+
+    // 生成自上次报告以来的统计，并清空重新开始
+    pub fn take_report(&mut self) -> MetricsReport {
+        let report = self.report();
+        *self = Metrics::default();
+        report
+    }
+}
+
+// 写入心跳记录的延迟统计，统计区间为上一次心跳到这一次心跳
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct MetricsReport {
+    pub detect: Option<LatencyReport>,
+    pub emit: Option<LatencyReport>,
+    pub sinks: BTreeMap<&'static str, LatencyReport>,
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use log::warn; // 显示失败时记录日志
+use windows::core::w;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW; // 注册窗口类所需的模块句柄
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, // 在通知区域添加图标并弹出通知，Windows 10 起显示为系统通知
+    NIF_ICON,
+    NIF_INFO,
+    NIF_TIP,
+    NIIF_WARNING,
+    NIM_ADD,
+    NIM_DELETE,
+    NOTIFYICONDATAW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, // 接收通知区域图标消息的窗口
+    DefWindowProcW,
+    DestroyWindow,
+    DispatchMessageW,
+    LoadIconW,
+    PeekMessageW,
+    RegisterClassW,
+    TranslateMessage,
+    HWND_MESSAGE, // 只接收消息、不显示的窗口
+    IDI_WARNING,
+    MSG,
+    PM_REMOVE,
+    WINDOW_EX_STYLE,
+    WINDOW_STYLE,
+    WNDCLASSW,
+};
+
+// 通知图标保留的时间，之后从通知区域移除，通知本身仍留在操作中心
+const SHOW_FOR: Duration = Duration::from_secs(10);
+
+// 弹出一条系统通知，在独立的线程中显示，不阻塞调用者
+pub fn toast(title: &str, text: &str) {
+    let (title, text) = (title.to_string(), text.to_string());
+    let spawned = std::thread::Builder::new().name("toast".to_string()).spawn(move || {
+        if let Err(e) = show(&title, &text) {
+            warn!("显示通知失败: {}", e);
+        }
+    });
+    if let Err(e) = spawned {
+        warn!("显示通知失败: {}", e);
+    }
+}
+
+// 添加一个临时的通知区域图标并弹出通知，SHOW_FOR 之后移除图标
+fn show(title: &str, text: &str) -> windows::core::Result<()> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = w!("ForegroundWatcherToast");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: class,
+            ..Default::default()
+        };
+        RegisterClassW(&wc); // 已经注册过时失败，不影响使用
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class,
+            w!("ForegroundWatcher"),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            Some(instance.into()),
+            None,
+        )?;
+        let mut data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            uFlags: NIF_ICON | NIF_TIP | NIF_INFO,
+            hIcon: LoadIconW(None, IDI_WARNING)?,
+            dwInfoFlags: NIIF_WARNING,
+            ..Default::default()
+        };
+        copy(&mut data.szTip, "ForegroundWatcher");
+        copy(&mut data.szInfoTitle, title);
+        copy(&mut data.szInfo, text);
+        let added = Shell_NotifyIconW(NIM_ADD, &data).as_bool();
+        if added {
+            let start = Instant::now();
+            while start.elapsed() < SHOW_FOR {
+                let mut msg = MSG::default();
+                while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+        }
+        let _ = DestroyWindow(hwnd);
+        if added {
+            Ok(())
+        } else {
+            Err(windows::core::Error::from_win32())
+        }
+    }
+}
+
+// 把字符串复制到固定长度的宽字符数组，过长时截断，保留结尾的0
+fn copy(buffer: &mut [u16], text: &str) {
+    let limit = buffer.len() - 1;
+    for (slot, c) in buffer.iter_mut().zip(text.encode_utf16().take(limit)) {
+        *slot = c;
+    }
+}
+
+// 接收通知区域图标消息的窗口过程，全部交给默认处理
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use windows::core::GUID; // Known Folder 的标识
+use windows::Win32::System::Com::CoTaskMemFree; // 释放 SHGetKnownFolderPath 分配的字符串
+use windows::Win32::UI::Shell::{
+    SHGetKnownFolderPath, // 查询 Known Folder 的实际路径
+    FOLDERID_LocalAppData, // %LOCALAPPDATA%，存放数据、日志等不需要漫游的文件
+    FOLDERID_RoamingAppData, // %APPDATA%，存放配置文件
+    KF_FLAG_DEFAULT,
+};
+
+use crate::error::{Error, PathsError}; // 无法确定位置
+
+// 应用在各个 Known Folder 下使用的子目录名
+const APP_DIR: &str = "ForegroundWatcher";
+
+// 程序读写的所有文件和目录的位置
+#[derive(Clone)]
+pub struct Paths {
+    pub config: PathBuf, // 配置文件
+    pub apps: PathBuf,   // 应用信息文件（显示名称、颜色、图标、分类），与配置文件放在同一目录
+    pub data: PathBuf,   // 数据根目录，下面各项默认都放在这里
+    pub logs: PathBuf,   // 运行日志
+    pub events: PathBuf, // 前台窗口事件记录（按天分文件的JSONL）
+    pub cache: PathBuf,  // 可以随时删除、会自动重建的缓存
+    pub crash: PathBuf,  // 崩溃报告
+    pub session: PathBuf, // 会话标记文件，正常退出时删除，用于发现上一次没有正常结束的运行
+    pub tokens: PathBuf,  // 本地HTTP接口的令牌，只保存散列值
+    pub portable: bool,   // 便携模式：所有文件放在可执行文件旁边，不使用 Known Folder 和注册表
+    pub instance: Option<String>, // --instance 指定的实例名称，各个实例的配置文件和数据目录互不相同
+}
+
+impl Paths {
+    // 根据命令行参数确定各个路径
+    // data_dir 为 --data-dir 指定的数据目录，config 为 --config 指定的配置文件，portable 为是否指定了 --portable，
+    // instance 为 --instance 指定的实例名称，只影响没有用 --data-dir、--config 指定的位置
+    pub fn resolve(data_dir: Option<&Path>, config: Option<&Path>, portable: bool, instance: Option<&str>) -> Result<Paths, Error> {
+        // 便携模式下的默认位置在可执行文件所在目录，每次启动时重新确定，U盘换了盘符也能找到
+        let base = if portable { Some(portable_dir()?) } else { None };
+        let data = match (data_dir, &base) {
+            (Some(dir), _) => dir.to_path_buf(),
+            (None, Some(base)) => instance_dir(base.join("data"), instance),
+            (None, None) => instance_dir(
+                known_folder(&FOLDERID_LocalAppData).ok_or_else(|| Error::Paths(PathsError::LocalAppData))?.join(APP_DIR),
+                instance,
+            ),
+        };
+        let config = match (config, &base) {
+            (Some(path), _) => path.to_path_buf(),
+            (None, Some(base)) => base.join(config_name(instance)),
+            (None, None) => default_config(instance).ok_or_else(|| Error::Paths(PathsError::AppData))?,
+        };
+        let apps = config.with_file_name("apps.toml");
+        Ok(Paths {
+            config,
+            apps,
+            logs: data.join("logs"),
+            events: data.join("events"),
+            cache: data.join("cache"),
+            crash: data.join("crash"),
+            session: data.join("session.json"),
+            tokens: data.join("tokens.json"),
+            data,
+            portable,
+            instance: instance.map(str::to_string),
+        })
+    }
+
+    // 创建数据目录下的各个子目录
+    pub fn create_dirs(&self) -> std::io::Result<()> {
+        for dir in [&self.logs, &self.events, &self.cache, &self.crash] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    // 以“名称: 路径”的形式列出所有位置，供 paths 子命令输出
+    pub fn describe(&self) -> Vec<(&'static str, &Path)> {
+        vec![
+            ("配置文件", &self.config),
+            ("应用信息", &self.apps),
+            ("数据目录", &self.data),
+            ("运行日志", &self.logs),
+            ("事件记录", &self.events),
+            ("缓存", &self.cache),
+            ("崩溃报告", &self.crash),
+            ("会话标记", &self.session),
+            ("API令牌", &self.tokens),
+        ]
+    }
+}
+
+// 便携模式的根目录：可执行文件所在的目录
+fn portable_dir() -> Result<PathBuf, Error> {
+    let exe = std::env::current_exe().map_err(|e| Error::Paths(PathsError::CurrentExe(e)))?;
+    Ok(exe.parent().ok_or_else(|| Error::Paths(PathsError::ExeDir))?.to_path_buf())
+}
+
+// 默认配置文件路径：%APPDATA%\ForegroundWatcher\config.toml，指定了实例时为 config.<实例>.toml
+pub fn default_config(instance: Option<&str>) -> Option<PathBuf> {
+    known_folder(&FOLDERID_RoamingAppData).map(|dir| dir.join(APP_DIR).join(config_name(instance)))
+}
+
+// 配置文件名，各个实例分别使用自己的配置文件，apps.toml 仍然共用
+fn config_name(instance: Option<&str>) -> String {
+    match instance {
+        Some(name) => format!("config.{}.toml", name),
+        None => "config.toml".to_string(),
+    }
+}
+
+// 实例的数据目录：默认数据目录下的 instances\<实例>
+fn instance_dir(data: PathBuf, instance: Option<&str>) -> PathBuf {
+    match instance {
+        Some(name) => data.join("instances").join(name),
+        None => data,
+    }
+}
+
+// 查询 Known Folder 的路径
+pub fn known_folder(id: &GUID) -> Option<PathBuf> {
+    unsafe {
+        let path = SHGetKnownFolderPath(id, KF_FLAG_DEFAULT, None).ok()?; // 返回的字符串需要用 CoTaskMemFree 释放
+        let result = path.to_string().ok().map(PathBuf::from);
+        CoTaskMemFree(Some(path.0 as *const _));
+        result
+    }
+}
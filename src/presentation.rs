@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use windows::Win32::UI::Shell::{
+    SHQueryUserNotificationState, // 查询系统当前是否适合弹出通知
+    QUERY_USER_NOTIFICATION_STATE,
+    QUNS_ACCEPTS_NOTIFICATIONS, // 正常状态
+    QUNS_APP,                   // Windows Store 应用全屏运行
+    QUNS_BUSY,                  // 有程序全屏运行，或开启了“演示设置”
+    QUNS_NOT_PRESENT,           // 屏幕保护程序运行中、计算机已锁定或正在切换用户
+    QUNS_PRESENTATION_MODE,     // 演示模式（如 PowerPoint 放映、共享屏幕时开启的演示模式）
+    QUNS_QUIET_TIME,            // 新用户首次登录后的安静时间
+    QUNS_RUNNING_D3D_FULL_SCREEN, // Direct3D 全屏程序（如游戏）运行中
+};
+
+// 查询间隔，SHQueryUserNotificationState 比 GetForegroundWindow 开销大，不需要每次轮询都查询
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// 系统的通知状态，反映是否有程序正在演示或全屏运行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationState {
+    Normal,       // 可以正常弹出通知
+    Presentation, // 演示模式
+    FullScreen,   // 全屏程序（包括 Direct3D 全屏和全屏的应用商店应用）
+    Busy,         // 全屏运行或开启了演示设置
+    NotPresent,   // 锁屏、屏幕保护程序或切换用户
+    QuietTime,    // 首次登录后的安静时间
+}
+
+impl NotificationState {
+    // 查询当前状态，查询失败时视为正常状态
+    pub fn query() -> NotificationState {
+        match unsafe { SHQueryUserNotificationState() } {
+            Ok(state) => NotificationState::from_raw(state),
+            Err(_) => NotificationState::Normal,
+        }
+    }
+
+    fn from_raw(state: QUERY_USER_NOTIFICATION_STATE) -> NotificationState {
+        match state {
+            QUNS_PRESENTATION_MODE => NotificationState::Presentation,
+            QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_APP => NotificationState::FullScreen,
+            QUNS_BUSY => NotificationState::Busy,
+            QUNS_NOT_PRESENT => NotificationState::NotPresent,
+            QUNS_QUIET_TIME => NotificationState::QuietTime,
+            QUNS_ACCEPTS_NOTIFICATIONS => NotificationState::Normal,
+            _ => NotificationState::Normal, // 将来新增的状态按正常处理
+        }
+    }
+
+    // 是否有程序正在演示，用于统计“演示中的时间”，PowerPoint 放映等全屏演示报告为 Busy 而不是演示模式
+    pub fn presenting(self) -> bool {
+        matches!(self, NotificationState::Presentation | NotificationState::Busy)
+    }
+
+    // 状态的中文名称，用于日志
+    pub fn name(self) -> &'static str {
+        match self {
+            NotificationState::Normal => "正常",
+            NotificationState::Presentation => "演示模式",
+            NotificationState::FullScreen => "全屏程序",
+            NotificationState::Busy => "全屏或演示设置",
+            NotificationState::NotPresent => "锁屏或屏幕保护",
+            NotificationState::QuietTime => "安静时间",
+        }
+    }
+}
+
+// 定期查询通知状态并发现变化
+pub struct PresentationMonitor {
+    state: NotificationState, // 最近一次查询到的状态
+    checked: Option<Instant>, // 最近一次查询的时间
+}
+
+impl PresentationMonitor {
+    pub fn new() -> PresentationMonitor {
+        PresentationMonitor { state: NotificationState::Normal, checked: None }
+    }
+
+    // 最近一次查询到的状态
+    pub fn state(&self) -> NotificationState {
+        self.state
+    }
+
+    // 距上次查询满一个间隔时重新查询，“是否在演示”发生变化时返回新的状态
+    pub fn poll(&mut self) -> Option<NotificationState> {
+        if self.checked.is_some_and(|t| t.elapsed() < CHECK_INTERVAL) {
+            return None;
+        }
+        self.checked = Some(Instant::now());
+        let state = NotificationState::query();
+        let changed = state.presenting() != self.state.presenting();
+        self.state = state;
+        changed.then_some(state)
+    }
+}
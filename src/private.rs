@@ -0,0 +1,23 @@
+use crate::metadata::exe_name; // 从路径中取出文件名
+
+// 隐私浏览窗口在记录中使用的标题，原标题一律不记录
+pub const PRIVATE_TITLE: &str = "[private browsing]";
+
+// 各个浏览器的隐私浏览窗口标题中会出现的标记，包括英文和中文界面
+const MARKERS: &[(&str, &[&str])] = &[
+    ("firefox.exe", &["Private Browsing", "隐私浏览"]),
+    ("msedge.exe", &["InPrivate"]),
+    ("chrome.exe", &["Incognito", "无痕"]),
+    ("brave.exe", &["Private", "隐私"]),
+    ("vivaldi.exe", &["Private", "隐私"]),
+    ("opera.exe", &["Private", "隐私"]),
+];
+
+// 窗口是否是浏览器的隐私浏览窗口，只检查已知的浏览器，避免普通程序的标题中恰好出现这些词
+pub fn is_private(exe: &str, title: &str) -> bool {
+    let name = exe_name(exe);
+    MARKERS
+        .iter()
+        .filter(|(browser, _)| browser.eq_ignore_ascii_case(name))
+        .any(|(_, markers)| markers.iter().any(|m| title.contains(m)))
+}
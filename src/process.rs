@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "sysinfo")]
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind}; // 系统信息库，用于获取进程信息
+
+#[cfg(not(feature = "sysinfo"))]
+use crate::capture; // 没有 sysinfo 时用 Win32 接口查询可执行文件路径
+
+// 进程信息的查询
+// 开启 sysinfo 功能（默认）时使用 sysinfo；精简构建中只用 QueryFullProcessImageNameW 查询可执行文件路径，
+// 不提供环境变量和内存占用
+pub struct Processes {
+    #[cfg(feature = "sysinfo")]
+    system: System, // sysinfo 的进程表
+    #[cfg(feature = "sysinfo")]
+    max_processes: usize, // 进程表最多保留的进程数
+}
+
+impl Processes {
+    #[cfg_attr(not(feature = "sysinfo"), allow(unused_variables))]
+    pub fn new(max_processes: usize) -> Processes {
+        Processes {
+            #[cfg(feature = "sysinfo")]
+            system: System::new(),
+            #[cfg(feature = "sysinfo")]
+            max_processes,
+        }
+    }
+
+    // 进程的可执行文件路径：进程不存在或已结束时为None，进程存在但无法获取路径时为Some(None)
+    #[cfg(feature = "sysinfo")]
+    pub fn exe(&mut self, pid: u32) -> Option<Option<String>> {
+        let pid = Pid::from(pid as usize); // 将u32类型的PID转换为sysinfo库的Pid类型
+        if self.system.processes().len() > self.max_processes {
+            self.system = System::new(); // 切换过的进程会一直留在进程表中，超出上限时清空重建
+        }
+        // 刷新特定进程的信息，第二个参数决定是否移除已经结束的进程
+        self.system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        let process = self.system.process(pid)?;
+        Some(process.exe().map(|p| p.to_string_lossy().into_owned()))
+    }
+
+    #[cfg(not(feature = "sysinfo"))]
+    pub fn exe(&mut self, pid: u32) -> Option<Option<String>> {
+        capture::process_image(pid).map(Some) // 无法区分进程已结束和无权访问，都视为进程不存在
+    }
+
+    // 读取进程环境块中允许记录的变量，无权读取的进程（如以管理员身份运行）为空
+    #[cfg(feature = "sysinfo")]
+    pub fn environ(&mut self, pid: u32, allowlist: &[&str]) -> BTreeMap<String, String> {
+        let pid = Pid::from(pid as usize);
+        // 环境块默认不刷新，只在需要时读取
+        let refresh = ProcessRefreshKind::nothing().with_environ(UpdateKind::Always);
+        self.system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), false, refresh);
+        self.system
+            .process(pid)
+            .map_or(BTreeMap::new(), |p| crate::environment::capture(p.environ(), allowlist))
+    }
+
+    #[cfg(not(feature = "sysinfo"))]
+    pub fn environ(&mut self, _pid: u32, _allowlist: &[&str]) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    // 本进程的工作集大小（字节）
+    #[cfg(feature = "sysinfo")]
+    pub fn own_memory(&mut self) -> Option<u64> {
+        let own = sysinfo::get_current_pid().ok()?;
+        self.system.refresh_processes(ProcessesToUpdate::Some(&[own]), true);
+        self.system.process(own).map(|p| p.memory())
+    }
+
+    #[cfg(not(feature = "sysinfo"))]
+    pub fn own_memory(&mut self) -> Option<u64> {
+        None
+    }
+
+    // 进程表中的进程数
+    pub fn count(&self) -> usize {
+        #[cfg(feature = "sysinfo")]
+        return self.system.processes().len();
+        #[cfg(not(feature = "sysinfo"))]
+        return 0;
+    }
+}
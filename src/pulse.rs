@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local}; // 状态心跳的时间
+use foreground_watcher_types::{FocusEvent, PulseRecord}; // 状态心跳记录
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+// [pulse] 状态心跳模式的设置
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct PulseConfig {
+    pub enabled: bool,         // 是否用状态心跳代替窗口切换记录
+    pub interval_seconds: u64, // 前台窗口没有变化时每隔多少秒重复发送一次当前状态
+    pub merge_seconds: u64,    // 合并窗口（秒），应大于 interval_seconds
+}
+
+impl Default for PulseConfig {
+    fn default() -> Self {
+        PulseConfig { enabled: false, interval_seconds: 10, merge_seconds: 15 }
+    }
+}
+
+// 状态心跳：记住当前前台窗口，窗口切换时立即发送，之后按固定间隔重复发送
+// 许多时间统计服务（以 bucket 和 heartbeat 组织数据的服务）期望这种格式，漏掉个别记录时也只影响一个合并窗口
+pub struct Pulse {
+    interval: Duration,          // 重复发送的间隔
+    merge_ms: u64,               // 记录中的合并窗口
+    current: Option<FocusEvent>, // 当前前台窗口，还没有记录过时为空
+    last_sent: Instant,          // 上一次发送的时间
+}
+
+impl Pulse {
+    pub fn new(config: &PulseConfig) -> Pulse {
+        Pulse {
+            interval: Duration::from_secs(config.interval_seconds.max(1)),
+            merge_ms: config.merge_seconds * 1000,
+            current: None,
+            last_sent: Instant::now(),
+        }
+    }
+
+    // 前台窗口发生变化，返回立即发送的状态心跳
+    pub fn switch(&mut self, event: &FocusEvent) -> PulseRecord {
+        self.current = Some(event.clone());
+        self.last_sent = Instant::now();
+        PulseRecord { state: event.clone(), merge_ms: self.merge_ms }
+    }
+
+    // 到了重复发送的时间时，返回当前状态的状态心跳
+    pub fn due(&mut self, now: DateTime<Local>) -> Option<PulseRecord> {
+        if self.last_sent.elapsed() < self.interval {
+            return None;
+        }
+        let current = self.current.as_ref()?;
+        self.last_sent = Instant::now();
+        Some(PulseRecord { state: FocusEvent { time: now, ..current.clone() }, merge_ms: self.merge_ms })
+    }
+
+    // 距下一次重复发送还有多久，还没有记录过前台窗口时为None
+    pub fn remaining(&self) -> Option<Duration> {
+        self.current.as_ref()?;
+        Some(self.interval.saturating_sub(self.last_sent.elapsed()))
+    }
+
+    // 记录中断（如系统睡眠），在重新记录前台窗口之前不再重复发送之前的状态
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
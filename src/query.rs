@@ -0,0 +1,305 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Days, Local, NaiveDate}; // 查询的时间范围
+use foreground_watcher_types::{FocusEvent, SessionRecord}; // 查询结果中的记录
+
+use crate::aggregate::Live; // /stats 返回的今天各应用的时长
+use crate::clock::SharedClock; // 默认查询到现在
+use crate::error::{Error, RangeError}; // 查询失败的原因
+use crate::export; // 与导出相同的日期范围限制
+use crate::history::{self, Entry}; // 读取事件记录文件
+use crate::metadata::Metadata; // 按分类筛选
+use crate::report; // 应用的分类
+use crate::sink; // 与实时推送的记录一样只保留允许的字段
+
+// 每页默认的记录数
+const DEFAULT_LIMIT: usize = 100;
+
+// 每页最多的记录数
+const MAX_LIMIT: usize = 1000;
+
+// 查询结果的排列顺序
+#[derive(Clone, Copy, PartialEq)]
+pub enum Order {
+    Asc,  // 从早到晚
+    Desc, // 从晚到早
+}
+
+// 查询的记录种类
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Focus,   // 窗口切换记录（状态心跳合并后）
+    Session, // 会话的开始和结束标记，用于确定记录覆盖了哪些时间
+}
+
+// 查询到的一条记录
+enum Record {
+    Focus(FocusEvent),
+    Session(SessionRecord),
+}
+
+impl Record {
+    fn time(&self) -> DateTime<Local> {
+        match self {
+            Record::Focus(event) => event.time,
+            Record::Session(record) => record.time,
+        }
+    }
+}
+
+// 翻页位置：上一页最后一条记录的时间（毫秒时间戳），以及这个时间上已经返回的记录数
+// 同一毫秒内可能有多条记录，只记时间会漏掉或重复
+struct Cursor {
+    ms: i64,
+    skip: usize,
+}
+
+impl Cursor {
+    // 解析 cursor 参数，格式为 “毫秒时间戳.条数”
+    fn parse(text: &str) -> Option<Cursor> {
+        let (ms, skip) = text.split_once('.')?;
+        Some(Cursor { ms: ms.parse().ok()?, skip: skip.parse().ok()? })
+    }
+
+    fn format(&self) -> String {
+        format!("{}.{}", self.ms, self.skip)
+    }
+}
+
+// 一次查询的条件
+pub struct Query {
+    from: Option<DateTime<Local>>, // 开始时间（含），默认为结束时间当天的零点
+    to: Option<DateTime<Local>>,   // 结束时间（不含），默认为现在
+    kind: Kind,                    // 查询的记录种类
+    app: Option<String>,           // 只要这个应用标识的记录
+    category: Option<String>,      // 只要这个分类的记录，没有设置分类的应用属于“其他”
+    order: Order,                  // 排列顺序
+    limit: usize,                  // 每页的记录数
+    cursor: Option<Cursor>,        // 从上一页结束的位置继续
+}
+
+impl Query {
+    // 从查询参数解析查询条件，参数有误时返回错误说明
+    pub fn parse(params: &[(String, String)]) -> Result<Query, String> {
+        let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+        let limit = match param("limit").map(str::parse::<usize>) {
+            Some(Ok(limit)) if (1..=MAX_LIMIT).contains(&limit) => limit,
+            Some(_) => return Err(format!("limit 应为 1-{} 的整数", MAX_LIMIT)),
+            None => DEFAULT_LIMIT,
+        };
+        let order = match param("order") {
+            None | Some("asc") => Order::Asc,
+            Some("desc") => Order::Desc,
+            Some(other) => return Err(format!("无效的 order: {}，可以写 asc 或 desc", other)),
+        };
+        let kind = match param("kind") {
+            None | Some("focus") => Kind::Focus,
+            Some("session") => Kind::Session,
+            Some(other) => return Err(format!("无效的 kind: {}，可以写 focus 或 session", other)),
+        };
+        if kind == Kind::Session && (param("app").is_some() || param("category").is_some()) {
+            return Err("app 和 category 只能用于窗口切换记录，不能与 kind=session 一起使用".to_string());
+        }
+        let cursor = match param("cursor") {
+            Some(text) => Some(Cursor::parse(text).ok_or_else(|| format!("无效的 cursor: {}，应原样传回上一页的 next", text))?),
+            None => None,
+        };
+        Ok(Query {
+            from: param("from").map(|t| parse_time("from", t)).transpose()?,
+            to: param("to").map(|t| parse_time("to", t)).transpose()?,
+            kind,
+            app: param("app").map(str::to_string),
+            category: param("category").map(str::to_string),
+            order,
+            limit,
+            cursor,
+        })
+    }
+
+    // 记录是否符合应用和分类条件
+    fn matches(&self, event: &FocusEvent, metadata: &Metadata) -> bool {
+        self.app.as_ref().is_none_or(|app| event.identity().id == *app)
+            && self.category.as_ref().is_none_or(|category| report::category(metadata, event.exe.as_deref()) == *category)
+    }
+}
+
+// 解析时间参数：RFC 3339 时间，或 YYYY-MM-DD 表示当天零点
+fn parse_time(name: &str, text: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => Ok(history::day_start(date)),
+        Err(_) => Err(format!("无效的 {}: {}，可以写 RFC 3339 时间（如 2024-05-01T09:00:00+08:00）或 YYYY-MM-DD", name, text)),
+    }
+}
+
+// 一页查询结果
+pub struct Page {
+    pub total: usize,        // 符合条件的记录总数，不受分页影响
+    pub next: Option<String>, // 下一页的 cursor，没有更多记录时为空
+    pub lines: Vec<String>,  // 本页记录的JSON
+}
+
+// 按条件查询已经写入事件记录文件的窗口切换记录或会话标记，包括已经压缩归档的文件
+pub struct Store {
+    events: PathBuf,           // 事件记录目录
+    metadata: Mutex<Metadata>, // 按分类筛选时使用，文件有变化时重新加载
+    fields: Vec<String>,       // 只返回这些字段，为空表示全部，与 [fields] 中对应输出目标的设置相同
+    clock: SharedClock,        // 默认查询到现在
+}
+
+impl Store {
+    pub fn new(events: PathBuf, metadata: Metadata, fields: Vec<String>, clock: SharedClock) -> Store {
+        Store { events, metadata: Mutex::new(metadata), fields, clock }
+    }
+
+    // [fields] 是否允许返回可执行文件路径；不允许时也不合并由它得出的显示名称和分类
+    fn exposes_exe(&self) -> bool {
+        self.fields.is_empty() || self.fields.iter().any(|f| f == "exe")
+    }
+
+    // 今天各应用的前台时长：{"day": 日期, "total_ms": 总时长, "apps": [...]}，按时长从长到短排列
+    // 结果按应用标识和可执行文件路径汇总，[fields] 不允许返回可执行文件路径时返回None
+    pub fn stats(&self, live: &Live) -> Result<Option<String>, Error> {
+        if !self.exposes_exe() {
+            return Ok(None);
+        }
+        let now = self.clock.now();
+        let mut apps = live.totals(now);
+        apps.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms).then_with(|| a.app.cmp(&b.app)));
+        let total_ms: i64 = apps.iter().map(|app| app.duration_ms).sum();
+        Ok(Some(serde_json::to_string(&serde_json::json!({ "day": now.date_naive(), "total_ms": total_ms, "apps": apps }))?))
+    }
+
+    // 查询一页记录，开始时间晚于结束时间或跨度太大时返回 Error::Range
+    pub fn query(&self, query: &Query) -> Result<Page, Error> {
+        let to = query.to.unwrap_or_else(|| self.clock.now());
+        let from = query.from.unwrap_or_else(|| history::day_start(to.date_naive()));
+        if from > to {
+            return Err(Error::Range(RangeError::Times { from, to }));
+        }
+        export::check_range(from.date_naive(), to.date_naive())?;
+        let mut metadata = self.metadata.lock().unwrap_or_else(|e| e.into_inner());
+        metadata.refresh();
+
+        let mut events = Vec::new();
+        let mut date = from.date_naive();
+        while date <= to.date_naive() {
+            match query.kind {
+                Kind::Focus => {
+                    for entry in history::read_day(&self.events, date)? {
+                        if let Entry::Focus(event) = entry {
+                            if query.matches(&event, &metadata) {
+                                events.push(Record::Focus(*event));
+                            }
+                        }
+                    }
+                }
+                Kind::Session => events.extend(history::sessions(&self.events, date)?.into_iter().map(Record::Session)),
+            }
+            date = date + Days::new(1);
+        }
+        events.retain(|e| e.time() >= from && e.time() < to);
+        events.sort_by_key(Record::time); // 稳定排序，同一时间的记录保持写入的顺序
+        if query.order == Order::Desc {
+            events.reverse();
+        }
+
+        // 跳过上一页及之前的记录
+        let start = match &query.cursor {
+            Some(cursor) => {
+                let before = events
+                    .iter()
+                    .take_while(|e| match query.order {
+                        Order::Asc => e.time().timestamp_millis() < cursor.ms,
+                        Order::Desc => e.time().timestamp_millis() > cursor.ms,
+                    })
+                    .count();
+                (before + cursor.skip).min(events.len())
+            }
+            None => 0,
+        };
+        let end = (start + query.limit).min(events.len());
+        let next = (end < events.len() && end > 0).then(|| {
+            let ms = events[end - 1].time().timestamp_millis();
+            let first = events[..end].iter().rposition(|e| e.time().timestamp_millis() != ms).map_or(0, |i| i + 1);
+            Cursor { ms, skip: end - first }.format()
+        });
+        let mut lines = Vec::new();
+        for record in &events[start..end] {
+            let line = match record {
+                Record::Focus(event) => serde_json::to_string(event)?,
+                Record::Session(record) => serde_json::to_string(record)?,
+            };
+            let line = if self.fields.is_empty() { line } else { sink::select(&line, &self.fields)? };
+            lines.push(match record {
+                Record::Focus(event) if self.exposes_exe() => with_metadata(&line, event, &metadata)?,
+                _ => line,
+            });
+        }
+        Ok(Page { total: events.len(), next, lines })
+    }
+}
+
+// 在记录中合并 apps.toml 中的应用信息：显示名称 display_name、分类 category，设置了时还有 color 和 icon
+fn with_metadata(line: &str, event: &FocusEvent, metadata: &Metadata) -> Result<String, Error> {
+    let Some(exe) = event.exe.as_deref() else {
+        return Ok(line.to_string());
+    };
+    let mut record: serde_json::Map<String, serde_json::Value> = serde_json::from_str(line)?;
+    record.insert("display_name".to_string(), metadata.display_name(exe, event.product.as_deref()).into());
+    record.insert("category".to_string(), report::category(metadata, Some(exe)).into());
+    if let Some(app) = metadata.get(exe) {
+        record.extend(app.color.clone().map(|color| ("color".to_string(), color.into())));
+        record.extend(app.icon.clone().map(|icon| ("icon".to_string(), icon.into())));
+    }
+    Ok(serde_json::to_string(&record)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::TimeZone;
+    use foreground_watcher_types::{SessionMarker, SessionReason};
+
+    use super::*;
+    use crate::clock::FixedClock;
+
+    // kind=session 返回会话标记，默认只返回窗口切换记录
+    #[test]
+    fn sessions_are_queryable() {
+        let dir = std::env::temp_dir().join(format!("foreground-watcher-query-{}", std::process::id()));
+        let events = dir.join("events");
+        std::fs::create_dir_all(&events).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let at = |hour| Local.from_local_datetime(&date.and_hms_opt(hour, 0, 0).unwrap()).unwrap();
+        let session = |time, marker, reason| SessionRecord { time, session: "s1".to_string(), marker, reason, previous: None, last_seen: None };
+        let lines = [
+            serde_json::to_string(&session(at(9), SessionMarker::Begin, SessionReason::Startup)).unwrap(),
+            serde_json::to_string(&FocusEvent { exe: Some(r"c:\a.exe".to_string()), ..FocusEvent::bare(at(10), 1) }).unwrap(),
+            serde_json::to_string(&session(at(11), SessionMarker::End, SessionReason::Shutdown)).unwrap(),
+        ];
+        std::fs::write(history::day_path(&events, date), lines.join("\n") + "\n").unwrap();
+        let metadata = Metadata::load(&dir.join("apps.toml")).unwrap();
+        let store = Store::new(events, metadata, Vec::new(), Arc::new(FixedClock(at(12))));
+        let query = |params: &[(&str, &str)]| {
+            let params: Vec<(String, String)> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            store.query(&Query::parse(&params).unwrap()).unwrap()
+        };
+
+        let focus = query(&[]);
+        let sessions = query(&[("kind", "session")]);
+        let first = query(&[("kind", "session"), ("limit", "1")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(focus.total, 1);
+        assert_eq!(sessions.total, 2);
+        let markers: Vec<SessionRecord> = sessions.lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(markers, [session(at(9), SessionMarker::Begin, SessionReason::Startup), session(at(11), SessionMarker::End, SessionReason::Shutdown)]);
+        assert_eq!(first.lines.len(), 1);
+        assert!(first.next.is_some());
+        assert!(Query::parse(&[("kind".to_string(), "session".to_string()), ("app".to_string(), "a".to_string())]).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate}; // 报告的日期范围
+use foreground_watcher_types::{AppIdentity, Category, FocusInterval, SessionMarker, SessionReason, StatsSnapshot}; // 按应用汇总
+use windows::Win32::System::Console::{
+    GetConsoleMode,
+    GetStdHandle,
+    SetConsoleMode, // 开启控制台的ANSI颜色支持
+    ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    STD_OUTPUT_HANDLE,
+};
+
+use crate::clock::Clock; // 确定“今天”
+use crate::history; // 读取事件记录
+use crate::metadata::{exe_name, Metadata}; // 应用的分类和显示名称
+use crate::private; // 隐私浏览窗口的标题
+use crate::workhours::WorkHours; // 拆分工作时间内和工作时间外
+
+// 没有设置分类的应用在报告中归入的分类
+pub const OTHER: &str = "其他";
+
+// 时间线每一格的长度（分钟）
+const SLOT_MINUTES: i64 = 10;
+
+// 分类使用的颜色（ANSI 256色），按总时长从长到短依次分配
+const PALETTE: &[u8] = &[33, 208, 70, 163, 178, 37, 160, 99, 130, 67];
+
+// “其他”使用的颜色
+const OTHER_COLOR: u8 = 245;
+
+// 下钻明细最多列出的窗口标题数
+const DRILL_DOWN_LIMIT: usize = 20;
+
+// 数据质量报告最多统计的周数
+pub const MAX_WEEKS: u32 = 52;
+
+// --drill-down 指定的应用
+pub enum DrillDown {
+    Exe(String), // exe=chrome.exe，按可执行文件名匹配，不区分大小写
+    App(String), // app=Discord，按应用标识匹配
+}
+
+impl DrillDown {
+    // 解析 --drill-down 参数
+    pub fn parse(text: &str) -> Result<DrillDown, String> {
+        match text.split_once('=') {
+            Some(("exe", exe)) if !exe.is_empty() => Ok(DrillDown::Exe(exe.to_string())),
+            Some(("app", app)) if !app.is_empty() => Ok(DrillDown::App(app.to_string())),
+            _ => Err(format!("无效的下钻条件: {}，可以写 exe=<可执行文件名> 或 app=<应用标识>", text)),
+        }
+    }
+
+    // 区间是否属于指定的应用
+    fn matches(&self, interval: &FocusInterval) -> bool {
+        match self {
+            DrillDown::Exe(name) => interval.event.exe.as_deref().is_some_and(|e| exe_name(e).eq_ignore_ascii_case(name)),
+            DrillDown::App(app) => interval.event.identity().id == *app,
+        }
+    }
+
+    // 用于标题的说明
+    fn describe(&self) -> String {
+        match self {
+            DrillDown::Exe(name) => name.clone(),
+            DrillDown::App(app) => format!("应用 {}", app),
+        }
+    }
+}
+
+// 解析 --day 参数：today、yesterday 或 YYYY-MM-DD
+pub fn parse_day(text: &str, clock: &dyn Clock) -> Result<NaiveDate, String> {
+    match text {
+        "today" => Ok(clock.today()),
+        "yesterday" => Ok(clock.today() - Days::new(1)),
+        _ => NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| format!("无效的日期: {}，可以写 today、yesterday 或 YYYY-MM-DD", text)),
+    }
+}
+
+// 某一天每10分钟占用时间最多的分类，按小时一行输出为彩色条，输出不是控制台时用字母代替颜色
+pub fn timeline(events: &std::path::Path, metadata: &Metadata, work: &WorkHours, day: NaiveDate, clock: &dyn Clock) -> std::io::Result<String> {
+    let start = history::day_start(day);
+    let end = history::day_start(day + Days::new(1)).min(clock.now()); // 今天只统计到现在
+    let intervals = if start < end { history::intervals(events, start, end)? } else { Vec::new() };
+
+    // 每一格内各个分类的时长（毫秒）
+    let mut slots: Vec<BTreeMap<String, i64>> = vec![BTreeMap::new(); (24 * 60 / SLOT_MINUTES) as usize];
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    let mut work_totals: BTreeMap<String, i64> = BTreeMap::new(); // 其中在工作时间内的时长
+    let mut app_totals: BTreeMap<&str, (String, i64)> = BTreeMap::new(); // 可执行文件 -> 分类和时长，用于选取分类的颜色
+    for interval in &intervals {
+        let category = category(metadata, interval.event.exe.as_deref());
+        *totals.entry(category.clone()).or_default() += interval.duration_ms();
+        if let Some(exe) = interval.event.exe.as_deref() {
+            app_totals.entry(exe).or_insert_with(|| (category.clone(), 0)).1 += interval.duration_ms();
+        }
+        *work_totals.entry(category.clone()).or_default() += work.overlap_ms(interval.start, interval.end);
+        let mut from = interval.start;
+        while from < interval.end {
+            let slot = slot_index(start, from);
+            let slot_end = (start + chrono::TimeDelta::minutes(SLOT_MINUTES * (slot as i64 + 1))).min(interval.end);
+            if let Some(slot) = slots.get_mut(slot) {
+                *slot.entry(category.clone()).or_default() += (slot_end - from).num_milliseconds();
+            }
+            from = slot_end;
+        }
+    }
+
+    // 按总时长分配颜色和字母；分类中有应用在 apps.toml 中设置了颜色时，使用其中时长最长的应用的颜色
+    let mut ranked: Vec<(String, i64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let colors = enable_colors();
+    let mut symbols = BTreeMap::new();
+    let mut palette = PALETTE.iter().cycle();
+    for (i, (name, _)) in ranked.iter().enumerate() {
+        let custom = app_totals
+            .iter()
+            .filter(|(_, (category, _))| category == name)
+            .filter_map(|(exe, (_, ms))| metadata.color(exe).map(|rgb| (*ms, rgb)))
+            .max_by_key(|(ms, _)| *ms);
+        let letter = char::from(b'A' + (i % 26) as u8);
+        let cell = match custom {
+            _ if !colors => format!("{}{}", letter, letter),
+            Some((_, (r, g, b))) => format!("\x1b[38;2;{};{};{}m██\x1b[0m", r, g, b),
+            None => {
+                let color = if name == OTHER { OTHER_COLOR } else { *palette.next().unwrap_or(&OTHER_COLOR) };
+                format!("\x1b[38;5;{}m██\x1b[0m", color)
+            }
+        };
+        symbols.insert(name.clone(), cell);
+    }
+
+    let mut text = format!("{} 每{}分钟占用时间最多的分类\n\n    ", day.format("%Y-%m-%d"), SLOT_MINUTES);
+    for minute in (0..60).step_by(SLOT_MINUTES as usize) {
+        text.push_str(&format!("{:<3}", format!("{:02}", minute)));
+    }
+    text.push('\n');
+    let per_hour = (60 / SLOT_MINUTES) as usize;
+    for (hour, row) in slots.chunks(per_hour).enumerate() {
+        text.push_str(&format!("{:02}  ", hour));
+        for slot in row {
+            let dominant = slot.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+            match dominant.and_then(|(name, _)| symbols.get(name)) {
+                Some(cell) => text.push_str(cell),
+                None => text.push_str("··"), // 没有记录（未运行、睡眠或还没到）
+            }
+            text.push(' ');
+        }
+        text.push('\n');
+    }
+    text.push('\n');
+    if ranked.is_empty() {
+        text.push_str("这一天没有记录\n");
+    }
+    for (name, ms) in &ranked {
+        let split = work_split(work, *ms, work_totals.get(name).copied().unwrap_or_default());
+        text.push_str(&format!("{} {}  {}{}\n", symbols[name], name, format_duration(*ms), split));
+    }
+    if !ranked.is_empty() {
+        let total: i64 = ranked.iter().map(|(_, ms)| ms).sum();
+        text.push_str(&format!("\n共 {}{}\n", format_duration(total), work_split(work, total, work_totals.values().sum())));
+    }
+    Ok(text)
+}
+
+// 某一天各个应用在前台的时长和切换次数；指定了 drill_down 时改为列出该应用中占用时间最多的窗口标题
+pub fn apps(events: &std::path::Path, metadata: &Metadata, work: &WorkHours, day: NaiveDate, drill_down: Option<&DrillDown>, clock: &dyn Clock) -> std::io::Result<String> {
+    let start = history::day_start(day);
+    let end = history::day_start(day + Days::new(1)).min(clock.now());
+    let intervals = if start < end { history::intervals(events, start, end)? } else { Vec::new() };
+    if let Some(drill_down) = drill_down {
+        return Ok(titles(&intervals, drill_down, work, day));
+    }
+
+    let products: BTreeMap<&str, &str> = intervals // 可执行文件第一次出现时的产品名称，没有设置显示名称时使用
+        .iter()
+        .rev()
+        .filter_map(|i| Some((i.event.exe.as_deref()?, i.event.product.as_deref()?)))
+        .collect();
+    let mut stats = StatsSnapshot::from_intervals(start, end, &intervals, |app: &AppIdentity| {
+        Some(Category(category(metadata, app.exe.as_deref())))
+    });
+    if !work.is_empty() {
+        stats.split_work(&intervals, |i| work.overlap_ms(i.start, i.end));
+    }
+    let mut text = format!(
+        "{} 各应用在前台的时间，共 {}{}\n\n",
+        day.format("%Y-%m-%d"),
+        format_duration(stats.total_ms),
+        work_split(work, stats.total_ms, stats.work_ms)
+    );
+    if stats.apps.is_empty() {
+        text.push_str("这一天没有记录\n");
+    }
+    for app in &stats.apps {
+        let name = app.app.exe.as_deref().map_or(app.app.id.clone(), |exe| metadata.display_name(exe, products.get(exe).copied()));
+        let category = app.category.as_ref().map_or(OTHER, |c| c.0.as_str());
+        if work.is_empty() {
+            text.push_str(&format!("{:>10}  {:>4}次  {}（{}）\n", format_duration(app.duration_ms), app.switches, name, category));
+        } else {
+            text.push_str(&format!(
+                "{:>10}  工作 {:>10}  其余 {:>10}  {:>4}次  {}（{}）\n",
+                format_duration(app.duration_ms),
+                format_duration(app.work_ms),
+                format_duration(app.duration_ms - app.work_ms),
+                app.switches,
+                name,
+                category
+            ));
+        }
+    }
+    Ok(text)
+}
+
+// 按周统计的数据质量：记录覆盖率、未知程序和隐私标题的占比、崩溃后恢复的次数，判断记录是否可信、哪里需要修正
+// 统计截至 day 所在的一周（周一开始），共 weeks 周
+pub fn quality(events: &std::path::Path, day: NaiveDate, weeks: u32, clock: &dyn Clock) -> std::io::Result<String> {
+    let last = day - Days::new(day.weekday().num_days_from_monday() as u64);
+    let mut text = format!("最近 {} 周的数据质量（截至 {}）\n\n", weeks, day.format("%Y-%m-%d"));
+    text.push_str(&format!(
+        "{:<10}  {:>10}  {:>10}  {:>6}  {:>8}  {:>8}  {}\n",
+        "周", "记录", "中断", "覆盖率", "未知程序", "隐私标题", "崩溃恢复"
+    ));
+    for week in (0..weeks).rev() {
+        let monday = last - Days::new(week as u64 * 7);
+        let mut quality = WeekQuality::default();
+        for offset in 0..7 {
+            let date = monday + Days::new(offset);
+            if date > day {
+                break;
+            }
+            quality.add_day(events, date, clock)?;
+        }
+        text.push_str(&format!(
+            "{:<10}  {:>10}  {:>10}  {:>6}  {:>8}  {:>8}  {}\n",
+            monday.format("%Y-%m-%d"),
+            format_duration(quality.tracked_ms),
+            format_duration(quality.gap_ms),
+            percent(quality.tracked_ms, quality.tracked_ms + quality.gap_ms),
+            percent(quality.unknown_ms, quality.tracked_ms),
+            percent(quality.redacted_ms, quality.tracked_ms),
+            if quality.crashes == 0 { "0次".to_string() } else { format!("{}次（丢失 {}）", quality.crashes, format_duration(quality.lost_ms)) }
+        ));
+    }
+    text.push_str("\n中断：每天第一条到最后一条记录之间没有记录的时间（睡眠、锁屏、程序没有运行），覆盖率 = 记录 / (记录 + 中断)\n");
+    text.push_str("未知程序：没有可执行文件路径的时间占比，通常是无权读取的进程，可以以管理员身份运行监视程序\n");
+    text.push_str("隐私标题：标题记录为 [private browsing] 的时间占比\n");
+    text.push_str("崩溃恢复：启动时发现上一个会话没有正常结束的次数，丢失的是上一个会话最后存活到重新启动之间的时间\n");
+    Ok(text)
+}
+
+// 一周的数据质量
+#[derive(Default)]
+struct WeekQuality {
+    tracked_ms: i64,  // 有前台窗口记录的时间
+    gap_ms: i64,      // 每天第一条到最后一条记录之间没有记录的时间
+    unknown_ms: i64,  // 没有可执行文件路径的时间
+    redacted_ms: i64, // 隐私浏览窗口的时间
+    crashes: usize,   // 崩溃后恢复的次数
+    lost_ms: i64,     // 崩溃丢失的时间
+}
+
+impl WeekQuality {
+    // 加上一天的记录，今天只统计到现在
+    fn add_day(&mut self, events: &std::path::Path, date: NaiveDate, clock: &dyn Clock) -> std::io::Result<()> {
+        let start = history::day_start(date);
+        let end = history::day_start(date + Days::new(1)).min(clock.now());
+        if start >= end {
+            return Ok(());
+        }
+        let intervals = history::intervals(events, start, end)?;
+        if let (Some(first), Some(last)) = (intervals.first(), intervals.last()) {
+            let tracked: i64 = intervals.iter().map(FocusInterval::duration_ms).sum();
+            self.tracked_ms += tracked;
+            self.gap_ms += (last.end - first.start).num_milliseconds() - tracked;
+        }
+        for interval in &intervals {
+            if interval.event.exe.is_none() {
+                self.unknown_ms += interval.duration_ms();
+            }
+            if interval.event.title.as_deref() == Some(private::PRIVATE_TITLE) {
+                self.redacted_ms += interval.duration_ms();
+            }
+        }
+        for record in history::sessions(events, date)? {
+            if record.marker == SessionMarker::Begin && record.reason == SessionReason::CrashRecovered {
+                self.crashes += 1;
+                self.lost_ms += record.last_seen.map_or(0, |seen| (record.time - seen).num_milliseconds().max(0));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 百分比，保留一位小数；分母为0时为 -
+fn percent(part: i64, whole: i64) -> String {
+    if whole <= 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+}
+
+// 指定应用中各个窗口标题的时长，按时长从长到短列出前几项
+fn titles(intervals: &[FocusInterval], drill_down: &DrillDown, work: &WorkHours, day: NaiveDate) -> String {
+    let mut titles: BTreeMap<String, i64> = BTreeMap::new();
+    let mut work_ms = 0;
+    for interval in intervals.iter().filter(|i| drill_down.matches(i)) {
+        let title = interval.event.title.clone().unwrap_or_else(|| "（无标题）".to_string());
+        *titles.entry(title).or_default() += interval.duration_ms();
+        work_ms += work.overlap_ms(interval.start, interval.end);
+    }
+    let total: i64 = titles.values().sum();
+    let mut ranked: Vec<(String, i64)> = titles.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut text = format!(
+        "{} {} 中占用时间最多的窗口标题，共 {}{}\n\n",
+        day.format("%Y-%m-%d"),
+        drill_down.describe(),
+        format_duration(total),
+        work_split(work, total, work_ms)
+    );
+    if ranked.is_empty() {
+        text.push_str("这一天没有该应用的记录\n");
+    }
+    for (title, ms) in ranked.iter().take(DRILL_DOWN_LIMIT) {
+        text.push_str(&format!("{:>10}  {}\n", format_duration(*ms), title));
+    }
+    if ranked.len() > DRILL_DOWN_LIMIT {
+        let rest: i64 = ranked[DRILL_DOWN_LIMIT..].iter().map(|(_, ms)| ms).sum();
+        text.push_str(&format!("{:>10}  其余 {} 个标题\n", format_duration(rest), ranked.len() - DRILL_DOWN_LIMIT));
+    }
+    text
+}
+
+// 时长在工作时间内外的拆分说明，如（工作时间内 2小时10分，工作时间外 35分）；没有设置工作时间时为空
+fn work_split(work: &WorkHours, total_ms: i64, work_ms: i64) -> String {
+    if work.is_empty() {
+        return String::new();
+    }
+    format!("（工作时间内 {}，工作时间外 {}）", format_duration(work_ms), format_duration(total_ms - work_ms))
+}
+
+// 应用的分类，没有设置时归入“其他”
+pub fn category(metadata: &Metadata, exe: Option<&str>) -> String {
+    exe.and_then(|e| metadata.get(e)).and_then(|app| app.category.clone()).unwrap_or_else(|| OTHER.to_string())
+}
+
+// 时长的可读形式，如 3小时12分
+pub fn format_duration(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => format!("{}秒", ms / 1000),
+        (0, m) => format!("{}分", m),
+        (h, m) => format!("{}小时{}分", h, m),
+    }
+}
+
+// 时间落在一天中的第几格
+fn slot_index(day_start: DateTime<Local>, time: DateTime<Local>) -> usize {
+    ((time - day_start).num_minutes() / SLOT_MINUTES) as usize
+}
+
+// 标准输出是控制台且没有设置 NO_COLOR 时，开启ANSI颜色支持
+fn enable_colors() -> bool {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    unsafe {
+        let Ok(handle) = GetStdHandle(STD_OUTPUT_HANDLE) else {
+            return false;
+        };
+        let mut mode = Default::default();
+        if GetConsoleMode(handle, &mut mode).is_err() {
+            return false; // 输出被重定向到文件或管道
+        }
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING).is_ok()
+    }
+}
@@ -0,0 +1,90 @@
+use foreground_watcher_types::FocusEvent; // 规则作用后的记录
+
+use crate::capture::CaptureMode; // 精简模式下不记录大部分数据
+use crate::clock::Clock; // 假想记录的时间
+use crate::config::Config; // 要测试的规则
+use crate::environment::EnvironmentRules; // 环境变量规则
+use crate::error::Error; // 规则无效或记录无法序列化
+use crate::identity::{self, Identities}; // 别名规则
+use crate::language; // 标题语言
+use crate::metadata::{exe_name, Metadata}; // 显示名称和分类
+use crate::private; // 隐私浏览窗口
+use crate::versioninfo; // 文件存在时读取版本资源
+
+// 用一条假想的记录测试配置的各项规则，列出每条规则是否匹配以及最终会写入的记录，每行为“名称: 值”
+pub fn test(exe: &str, title: Option<&str>, config: &Config, metadata: &Metadata, clock: &dyn Clock) -> Result<Vec<(String, String)>, Error> {
+    let mut lines = Vec::new();
+    let mut add = |name: String, value: String| lines.push((name, value));
+    add("可执行文件".to_string(), exe.to_string());
+    add("窗口标题".to_string(), title.unwrap_or("（无）").to_string());
+    let private = title.is_some_and(|t| private::is_private(exe, t));
+    if private {
+        add("隐私浏览窗口".to_string(), format!("是，标题记录为 {}", private::PRIVATE_TITLE));
+    }
+
+    // 别名规则按顺序取第一条匹配的
+    let mut matched = false;
+    for (i, rule) in config.alias.iter().enumerate() {
+        if identity::compile(&rule.pattern)?.is_match(exe) {
+            let state = if matched { "匹配，但前面已有规则生效" } else { "匹配，生效" };
+            add(format!("[[alias]] 第{}条", i + 1), format!("{} -> {}（{}）", rule.pattern, rule.app, state));
+            matched = true;
+        }
+    }
+    if !matched {
+        add("[[alias]]".to_string(), format!("没有匹配的规则（共{}条），使用去掉版本号目录的路径", config.alias.len()));
+    }
+    let app = Identities::new(&config.alias)?.resolve(exe);
+    add("应用标识".to_string(), app.clone());
+
+    // 应用信息文件按可执行文件名查找
+    let version = versioninfo::query(exe).unwrap_or_default(); // 只有文件确实存在时才有版本资源
+    let entry = metadata.get(exe);
+    add(
+        "apps.toml".to_string(),
+        if entry.is_some() { format!("匹配 [\"{}\"]", exe_name(exe)) } else { format!("没有 [\"{}\"]", exe_name(exe)) },
+    );
+    add("显示名称".to_string(), metadata.display_name(exe, version.product.as_deref()));
+    add("分类".to_string(), entry.and_then(|a| a.category.clone()).unwrap_or_else(|| "未分类".to_string()));
+
+    let listed = config.minimal.is_listed(exe);
+    let minimal = config.capture == CaptureMode::Minimal || listed;
+    add(
+        "记录方式".to_string(),
+        match (config.capture == CaptureMode::Minimal, listed) {
+            (true, _) => "精简模式（capture = \"minimal\"）",
+            (false, true) => "精简模式（在 [minimal] apps 中）",
+            (false, false) => "完整记录",
+        }
+        .to_string(),
+    );
+
+    // 环境变量规则取所有匹配规则的并集
+    for (i, rule) in config.environment.iter().enumerate() {
+        if identity::compile(&rule.pattern)?.is_match(exe) {
+            add(format!("[[environment]] 第{}条", i + 1), format!("{} -> {}", rule.pattern, rule.variables.join(", ")));
+        }
+    }
+    let allowlist = EnvironmentRules::new(&config.environment)?.allowlist(exe).join(", ");
+    let variables = if minimal || allowlist.is_empty() { "（无）".to_string() } else { allowlist };
+    add("记录的环境变量".to_string(), variables);
+
+    // 最终写入的记录，进程ID和环境变量的值只有实际运行时才知道
+    let now = clock.now();
+    let record = if minimal {
+        FocusEvent { exe: Some(exe.to_string()), ..FocusEvent::bare(now, 0) }
+    } else {
+        FocusEvent {
+            title: if private { Some(private::PRIVATE_TITLE.to_string()) } else { title.map(str::to_string) },
+            exe: Some(exe.to_string()),
+            app: Some(app),
+            product: version.product,
+            company: version.company,
+            version: version.version,
+            lang: if config.detect_language && !private { title.and_then(language::detect).map(str::to_string) } else { None },
+            ..FocusEvent::bare(now, 0)
+        }
+    };
+    add("记录".to_string(), serde_json::to_string(&record)?);
+    Ok(lines)
+}
@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Days, Local, Timelike}; // 按本地时间匹配 cron 表达式
+use log::{error, info, warn}; // 任务完成或失败时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 从配置文件读取，发送给 webhook 的内容
+
+use crate::clock::{Clock, SharedClock}; // 报告的日期范围
+use crate::error::{Error, FileAction}; // 任务失败的原因
+use crate::export; // 导出
+use crate::metadata::Metadata; // 分类和显示名称
+use crate::paths::Paths; // 事件记录和应用信息文件的位置
+use crate::report; // 报告
+use crate::trigger::Action; // 与触发规则共用的 webhook 和程序动作
+use crate::workhours::{WorkHours, WorkWindow}; // 报告中拆分工作时间
+
+// 定时任务可以执行的报告和导出
+pub const TASKS: &[&str] = &["report apps", "report quality", "export daily-matrix", "export perfetto", "export dot"];
+
+// cron 表达式中星期的英文缩写，从星期日开始
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// 配置文件中的一条 [[schedule]] 定时任务
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ScheduleRule {
+    pub cron: String,            // 执行时间，cron 表达式：分 时 日 月 星期
+    pub task: String,            // 执行的报告或导出，见 TASKS
+    pub name: Option<String>,    // 任务的名称，用于日志和通知内容，省略时使用 task
+    #[serde(default = "default_days")]
+    pub days: u32,               // 导出的天数，到 until 为止；report quality 按周数取整，report apps 只统计 until 这一天
+    #[serde(default = "default_until")]
+    pub until: String,           // 统计的最后一天：yesterday 或 today
+    pub output: String,          // 输出文件，{date} 替换为最后一天（YYYY-MM-DD），相对路径相对于数据目录
+    pub webhook: Option<String>, // 完成后以 POST 发送JSON的地址，只支持 http://
+    #[serde(default)]
+    pub command: Vec<String>,    // 完成后执行的程序及其参数，如发送邮件的脚本
+}
+
+fn default_days() -> u32 {
+    1
+}
+
+fn default_until() -> String {
+    "yesterday".to_string()
+}
+
+// 完成后发送给 webhook 的内容
+#[derive(Serialize)]
+struct Finished<'a> {
+    kind: &'static str,    // 固定为 schedule
+    schedule: &'a str,     // 任务的名称
+    time: DateTime<Local>, // 完成的时间
+    output: &'a Path,      // 写入的文件
+}
+
+// 解析后的 cron 表达式，每个字段为允许取值的位集合
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,    // 0 为星期日，7 也按星期日处理
+    any_day: bool,    // 日为 *
+    any_weekday: bool, // 星期为 *
+}
+
+impl Cron {
+    // 解析“分 时 日 月 星期”五个字段，每个字段可以写 *、数字、范围 a-b、列表 a,b 和步长 */n、a-b/n，星期还可以写 mon 等英文缩写
+    pub fn parse(text: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron 表达式应有5个字段（分 时 日 月 星期），实际为 {} 个: {}", fields.len(), text));
+        };
+        let weekday = WEEKDAYS.iter().zip(0..).fold(weekday.to_lowercase(), |text, (name, n)| text.replace(name, &n.to_string()));
+        let weekdays = field(&weekday, 0, 7, "星期")?;
+        Ok(Cron {
+            minutes: field(minute, 0, 59, "分")?,
+            hours: field(hour, 0, 23, "时")?,
+            days: field(day, 1, 31, "日")?,
+            months: field(month, 1, 12, "月")?,
+            weekdays: if weekdays & (1 << 7) != 0 { weekdays | 1 } else { weekdays },
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    // 时间是否匹配；日和星期都有限制时满足其一即可，与通常的 cron 相同
+    pub fn matches(&self, time: DateTime<Local>) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let date = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        date && has(self.minutes, time.minute()) && has(self.hours, time.hour()) && has(self.months, time.month())
+    }
+}
+
+// 解析 cron 表达式的一个字段，返回允许取值的位集合
+fn field(text: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("cron 表达式的{}字段无效: {}，取值范围为 {}-{}", name, text, min, max);
+    let number = |s: &str| s.parse::<u32>().ok().filter(|n| (min..=max).contains(n)).ok_or_else(invalid);
+    let mut set = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (number(a)?, number(b)?),
+                None if part.contains('/') => (number(range)?, max), // a/n 表示从 a 开始每隔 n
+                None => (number(range)?, number(range)?),
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+// 一条定时任务
+struct Job {
+    rule: ScheduleRule,
+    cron: Cron,
+    action: Action,
+}
+
+// 监视程序运行期间按 [[schedule]] 定时生成报告和导出，不需要为每个报告在任务计划程序中添加一项
+// 每分钟检查一次，任务在后台线程中执行；监视程序没有运行（或电脑在睡眠）时错过的任务不会补做
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    paths: Paths,
+    work: Vec<WorkWindow>,
+    clock: SharedClock,
+    last_minute: DateTime<Local>, // 上一次检查的分钟
+}
+
+impl Scheduler {
+    // 没有定时任务时返回None，cron 表达式已由配置校验检查过
+    pub fn new(rules: &[ScheduleRule], paths: &Paths, work: &[WorkWindow], clock: SharedClock) -> Option<Scheduler> {
+        let jobs: Vec<Job> = rules
+            .iter()
+            .filter_map(|rule| {
+                let cron = Cron::parse(&rule.cron).ok()?;
+                let name = format!("定时任务 {}", rule.name.as_deref().unwrap_or(&rule.task));
+                let action = Action { name, webhook: rule.webhook.clone(), command: rule.command.clone() };
+                Some(Job { rule: rule.clone(), cron, action })
+            })
+            .collect();
+        if jobs.is_empty() {
+            return None;
+        }
+        let last_minute = minute(clock.now()); // 启动的这一分钟不执行，避免重启时重复执行
+        Some(Scheduler { jobs, paths: paths.clone(), work: work.to_vec(), clock, last_minute })
+    }
+
+    // 进入新的一分钟时执行匹配的任务
+    pub fn tick(&mut self, now: DateTime<Local>) {
+        let current = minute(now);
+        if current <= self.last_minute {
+            return;
+        }
+        self.last_minute = current;
+        for job in self.jobs.iter().filter(|job| job.cron.matches(current)) {
+            let (rule, action, paths, work, clock) = (job.rule.clone(), job.action.clone(), self.paths.clone(), self.work.clone(), self.clock.clone());
+            let spawned = std::thread::Builder::new().name("schedule".to_string()).spawn(move || {
+                match execute(&rule, &paths, &work, clock.as_ref()) {
+                    Ok(output) => finished(&action, &output, clock.now()),
+                    Err(e) => error!("{}失败: {}", action.name, e),
+                }
+            });
+            if let Err(e) = spawned {
+                error!("{}失败: {}", job.action.name, e);
+            }
+        }
+    }
+}
+
+// 截到分钟的时间
+fn minute(time: DateTime<Local>) -> DateTime<Local> {
+    time.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(time)
+}
+
+// 生成报告或导出并写入文件，返回写入的路径
+fn execute(rule: &ScheduleRule, paths: &Paths, work: &[WorkWindow], clock: &dyn Clock) -> Result<PathBuf, Error> {
+    let until = report::parse_day(&rule.until, clock).map_err(Error::Argument)?;
+    let from = until - Days::new(rule.days.max(1) as u64 - 1);
+    let metadata = Metadata::load(&paths.apps)?;
+    let events = &paths.events;
+    let text = match rule.task.as_str() {
+        "report apps" => report::apps(events, &metadata, &WorkHours::new(work), until, None, clock)?,
+        "report quality" => report::quality(events, until, rule.days.div_ceil(7).clamp(1, report::MAX_WEEKS), clock)?,
+        task => {
+            let format = task.trim_start_matches("export ");
+            let pivot = export::Pivot::parse(None, format).map_err(Error::Argument)?;
+            match format {
+                "daily-matrix" => format!("\u{feff}{}", export::daily_matrix(events, &paths.cache, &metadata, from, until, pivot, clock)?), // Excel 按 UTF-8 打开需要 BOM
+                "perfetto" => export::perfetto(events, &metadata, from, until, pivot, clock)?,
+                "dot" => export::dot(events, &metadata, from, until, pivot, clock)?,
+                _ => return Err(Error::Argument(format!("不支持的任务: {}", task))), // 配置校验阶段已检查
+            }
+        }
+    };
+    let output = paths.data.join(rule.output.replace("{date}", &until.format("%Y-%m-%d").to_string())); // 绝对路径时 join 直接使用它
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&output, text).map_err(|source| Error::File { action: FileAction::Write, path: output.clone(), source })?;
+    Ok(output)
+}
+
+// 任务完成：记录日志，并发送 webhook、执行程序
+fn finished(action: &Action, output: &Path, now: DateTime<Local>) {
+    info!("{}完成，已写入 {}", action.name, output.display());
+    if action.webhook.is_none() && action.command.is_empty() {
+        return;
+    }
+    let payload = Finished { kind: "schedule", schedule: &action.name, time: now, output };
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => body,
+        Err(e) => return warn!("序列化定时任务的结果失败: {}", e),
+    };
+    let env = vec![("FW_SCHEDULE", action.name.clone()), ("FW_TIME", now.to_rfc3339()), ("FW_OUTPUT", output.display().to_string())];
+    if let Err(e) = action.run(body, env) {
+        warn!("执行{}的动作失败: {}", action.name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn values(set: u64) -> Vec<u32> {
+        (0..64).filter(|n| set & (1 << n) != 0).collect()
+    }
+
+    // 范围、列表和步长，a/n 从 a 开始到最大值
+    #[test]
+    fn field_ranges_and_steps() {
+        assert_eq!(values(field("*", 1, 12, "月").unwrap()), (1..=12).collect::<Vec<_>>());
+        assert_eq!(values(field("1,3-4", 0, 59, "分").unwrap()), [1, 3, 4]);
+        assert_eq!(values(field("*/20", 0, 59, "分").unwrap()), [0, 20, 40]);
+        assert_eq!(values(field("1-7/3", 0, 59, "分").unwrap()), [1, 4, 7]);
+        assert_eq!(values(field("10/15", 0, 59, "分").unwrap()), [10, 25, 40, 55]);
+    }
+
+    // 星期可以写英文缩写，7 与 0 都是星期日（2024-03-03 是星期日，03-05 是星期二）
+    #[test]
+    fn weekday_names_and_sunday() {
+        let weekdays = Cron::parse("0 9 * * Mon-Fri").unwrap();
+        assert!(weekdays.matches(at(5, 9, 0)));
+        assert!(!weekdays.matches(at(3, 9, 0)));
+        assert!(!weekdays.matches(at(5, 9, 1)));
+        for sunday in ["0 9 * * 7", "0 9 * * 0", "0 9 * * sun"] {
+            let cron = Cron::parse(sunday).unwrap();
+            assert!(cron.matches(at(3, 9, 0)), "{}", sunday);
+            assert!(!cron.matches(at(4, 9, 0)), "{}", sunday);
+        }
+    }
+
+    // 日和星期都有限制时满足其一即可，只限制一个时按它匹配（2024-03-01 是星期五，03-04 是星期一）
+    #[test]
+    fn day_or_weekday() {
+        let either = Cron::parse("0 9 1 * mon").unwrap();
+        assert!(either.matches(at(1, 9, 0)));
+        assert!(either.matches(at(4, 9, 0)));
+        assert!(!either.matches(at(5, 9, 0)));
+        let monday = Cron::parse("0 9 * * mon").unwrap();
+        assert!(!monday.matches(at(1, 9, 0)));
+        assert!(monday.matches(at(4, 9, 0)));
+        let first = Cron::parse("0 9 1 * *").unwrap();
+        assert!(first.matches(at(1, 9, 0)));
+        assert!(!first.matches(at(4, 9, 0)));
+    }
+
+    // 字段个数不对、超出范围、范围颠倒、步长为0和无法识别的取值都是错误
+    #[test]
+    fn parse_errors() {
+        for text in ["0 9 * *", "0 9 * * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "5-1 * * * *", "*/0 * * * *", "* * * * foo", "a * * * *"] {
+            assert!(Cron::parse(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+
+use foreground_watcher_types::{Capabilities, CorrectionRecord, EnrichmentRecord, FocusEvent, PulseRecord, SecureDesktopRecord, SessionRecord, StatsSnapshot}; // 事件记录、统计结果和数据提供情况
+use schemars::schema_for; // 根据类型定义生成JSON Schema
+
+use crate::config::Config; // 配置文件
+use crate::heartbeat::HeartbeatRecord; // 心跳记录
+use crate::metadata::AppMetadata; // 应用信息文件
+
+// 输出指定数据的JSON Schema，由Rust类型定义生成，始终与程序实际读写的格式一致
+pub fn generate(kind: &str) -> String {
+    let schema = match kind {
+        "events" => schema_for!(FocusEvent),          // 事件记录中的窗口切换记录
+        "heartbeat" => schema_for!(HeartbeatRecord),  // 事件记录中的心跳记录
+        "session" => schema_for!(SessionRecord),      // 事件记录中的会话标记
+        "pulse" => schema_for!(PulseRecord),          // 状态心跳模式下的状态心跳
+        "secure_desktop" => schema_for!(SecureDesktopRecord), // 事件记录中的安全桌面记录
+        "correction" => schema_for!(CorrectionRecord), // 钩子漏报时的纠正记录
+        "enrichment" => schema_for!(EnrichmentRecord), // 后台查出的补充信息
+        "stats" => schema_for!(StatsSnapshot),        // 按应用汇总的统计结果
+        "config" => schema_for!(Config),              // config.toml
+        "apps" => schema_for!(BTreeMap<String, AppMetadata>), // apps.toml
+        "capabilities" => schema_for!(Capabilities),  // capabilities 子命令和 /capabilities 接口的输出
+        _ => unreachable!("参数解析阶段已检查数据名称"),
+    };
+    let mut text = serde_json::to_string_pretty(&schema).unwrap_or_default();
+    text.push('\n');
+    text
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local}; // 会话标记中的时间
+use foreground_watcher_types::{SessionMarker, SessionReason, SessionRecord}; // 事件记录中的会话标记
+use log::warn; // 会话标记文件写入失败时记录日志
+use serde::{Deserialize, Serialize}; // 会话标记文件的内容
+
+// 每隔多久更新一次会话标记文件中的最后存活时间
+const TOUCH_INTERVAL: Duration = Duration::from_secs(60);
+
+// 两次轮询相隔比轮询间隔多出这个时间，认为期间系统睡眠或休眠过
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+// 会话标记文件的内容：正在运行的会话及其最后一次确认仍在运行的时间
+// 正常退出时删除，启动时仍然存在说明上一个会话没有正常结束
+#[derive(Serialize, Deserialize)]
+struct Marker {
+    session: String,
+    alive: DateTime<Local>,
+}
+
+// 监视程序是否正在运行：会话标记文件存在，且最近更新过（没有正常结束的会话留下的文件不会再更新）
+#[cfg(feature = "http")]
+pub fn running(marker: &std::path::Path, now: DateTime<Local>) -> bool {
+    let Some(marker) = std::fs::read_to_string(marker).ok().and_then(|text| serde_json::from_str::<Marker>(&text).ok()) else {
+        return false;
+    };
+    (now - marker.alive).to_std().is_ok_and(|age| age < TOUCH_INTERVAL * 2)
+}
+
+// 当前的监视会话
+pub struct Session {
+    id: String,       // 会话ID
+    marker: PathBuf,  // 会话标记文件
+    touched: Instant, // 上次更新会话标记文件的时间
+    events: u64,      // 本会话已经分配的记录ID数
+}
+
+impl Session {
+    // 开始一个新会话，返回会话和要写入事件记录的开始标记
+    // 会话标记文件仍然存在时，说明上一个会话没有正常结束，开始标记中会带上它的ID和最后存活时间
+    pub fn begin(marker: PathBuf, now: DateTime<Local>) -> (Session, SessionRecord) {
+        let previous = std::fs::read_to_string(&marker).ok().and_then(|text| serde_json::from_str::<Marker>(&text).ok());
+        let reason = if previous.is_some() { SessionReason::CrashRecovered } else { SessionReason::Startup };
+        let (previous, last_seen) = previous.map_or((None, None), |m| (Some(m.session), Some(m.alive)));
+        let mut session = Session { id: new_id(now), marker, touched: Instant::now(), events: 0 };
+        session.write_marker(now);
+        let record = session.record(now, SessionMarker::Begin, reason);
+        (session, SessionRecord { previous, last_seen, ..record })
+    }
+
+    // 系统从睡眠中恢复：结束当前会话并开始一个新会话，返回新会话的开始标记
+    // last_seen 为睡眠前最后一次轮询的时间
+    pub fn resume(&mut self, now: DateTime<Local>, last_seen: DateTime<Local>) -> SessionRecord {
+        let previous = std::mem::replace(&mut self.id, new_id(now));
+        self.events = 0;
+        self.write_marker(now);
+        SessionRecord { previous: Some(previous), last_seen: Some(last_seen), ..self.record(now, SessionMarker::Begin, SessionReason::Sleep) }
+    }
+
+    // 定期更新会话标记文件中的最后存活时间，程序崩溃后下次启动时据此确定记录覆盖到哪里
+    pub fn touch(&mut self, now: DateTime<Local>) {
+        if self.touched.elapsed() >= TOUCH_INTERVAL {
+            self.write_marker(now);
+        }
+    }
+
+    // 为一条窗口切换记录分配ID：会话ID加会话内的序号，不同会话之间不会重复
+    pub fn event_id(&mut self) -> String {
+        self.events += 1;
+        format!("{}:{}", self.id, self.events)
+    }
+
+    // 正常结束会话，删除会话标记文件，返回要写入事件记录的结束标记
+    pub fn end(self, now: DateTime<Local>) -> SessionRecord {
+        if let Err(e) = std::fs::remove_file(&self.marker) {
+            warn!("删除会话标记文件 {} 失败: {}", self.marker.display(), e);
+        }
+        self.record(now, SessionMarker::End, SessionReason::Shutdown)
+    }
+
+    // 生成当前会话的标记
+    fn record(&self, now: DateTime<Local>, marker: SessionMarker, reason: SessionReason) -> SessionRecord {
+        SessionRecord { time: now, session: self.id.clone(), marker, reason, previous: None, last_seen: None }
+    }
+
+    // 写入会话标记文件，先写临时文件再改名，避免断电时留下不完整的内容
+    fn write_marker(&mut self, now: DateTime<Local>) {
+        self.touched = Instant::now();
+        let text = serde_json::to_string(&Marker { session: self.id.clone(), alive: now }).unwrap_or_default();
+        let temp = self.marker.with_extension("tmp");
+        if let Err(e) = std::fs::write(&temp, text).and_then(|_| std::fs::rename(&temp, &self.marker)) {
+            warn!("写入会话标记文件 {} 失败: {}", self.marker.display(), e);
+        }
+    }
+}
+
+// 两次轮询之间的间隔是否说明系统睡眠过，interval 为最长的轮询间隔
+pub fn slept(since_last_poll: Duration, interval: Duration) -> bool {
+    since_last_poll >= interval + SLEEP_GAP
+}
+
+// 由开始时间（精确到毫秒）和进程ID组成的会话ID
+fn new_id(now: DateTime<Local>) -> String {
+    format!("{}-{}", now.format("%Y%m%dT%H%M%S%.3f"), std::process::id())
+}
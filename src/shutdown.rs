@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use log::warn; // 无法安装控制台事件处理函数时记录日志
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Console::SetConsoleCtrlHandler; // 接收 Ctrl+C、关闭控制台窗口、注销和关机事件
+
+// 收到 Ctrl+C、关闭窗口等事件后，最多等待主循环收尾多久
+// 关闭控制台窗口时系统只留出约5秒，超过后会直接结束进程
+const FINISH_TIMEOUT: Duration = Duration::from_secs(4);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false); // 是否已请求退出
+static FINISHED: AtomicBool = AtomicBool::new(false);  // 主循环是否已写完结束标记
+
+// 安装控制台事件处理函数，之后通过 requested() 检查是否应当退出
+pub fn install() {
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(Some(handler)), true) } {
+        warn!("无法接收控制台关闭事件，退出时不会写入会话结束标记: {}", e);
+    }
+}
+
+// 是否已请求退出
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+// 请求主循环退出，用于压力测试到时结束
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+// 主循环已经收尾，控制台事件处理函数可以返回了
+pub fn finished() {
+    FINISHED.store(true, Ordering::SeqCst);
+}
+
+// 控制台事件处理函数，在系统创建的线程中运行
+// 返回后进程可能立即被结束，因此等待主循环写完结束标记并清空输出队列再返回
+unsafe extern "system" fn handler(_event: u32) -> BOOL {
+    REQUESTED.store(true, Ordering::SeqCst);
+    let start = Instant::now();
+    while !FINISHED.load(Ordering::SeqCst) && start.elapsed() < FINISH_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    true.into()
+}
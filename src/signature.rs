@@ -0,0 +1,86 @@
+use windows::core::{HSTRING, PCWSTR}; // 文件路径参数
+use windows::Win32::Foundation::{HANDLE, HWND, TRUST_E_NOSIGNATURE};
+use windows::Win32::Security::Cryptography::{CertGetNameStringW, CERT_NAME_SIMPLE_DISPLAY_TYPE}; // 取出证书的显示名称
+use windows::Win32::Security::WinTrust::{
+    WTHelperGetProvSignerFromChain, // 取出签名者及其证书链
+    WTHelperProvDataFromStateData,  // 取出验证过程中保留的签名数据
+    WinVerifyTrust,                 // 验证文件的 Authenticode 签名
+    WINTRUST_ACTION_GENERIC_VERIFY_V2,
+    WINTRUST_DATA,
+    WINTRUST_DATA_0,
+    WINTRUST_FILE_INFO,
+    WTD_CHOICE_FILE,
+    WTD_REVOKE_NONE,
+    WTD_STATEACTION_CLOSE,
+    WTD_STATEACTION_VERIFY,
+    WTD_UI_NONE,
+};
+
+// 可执行文件的数字签名
+pub enum Signature {
+    Valid(Option<String>),        // 签名有效，附签名者名称
+    Invalid(Option<String>, i32), // 有签名但验证失败（证书不受信任、文件被修改等），附签名者名称和错误码
+    Unsigned,                     // 没有内嵌签名，系统文件可能使用目录签名
+}
+
+impl Signature {
+    // 用于输出的说明
+    pub fn describe(&self) -> String {
+        let signer = |name: &Option<String>| name.clone().unwrap_or_else(|| "未知签名者".to_string());
+        match self {
+            Signature::Valid(name) => format!("{}（有效）", signer(name)),
+            Signature::Invalid(name, code) => format!("{}（验证失败: 0x{:08X}）", signer(name), code),
+            Signature::Unsigned => "没有内嵌签名（系统文件可能使用目录签名）".to_string(),
+        }
+    }
+}
+
+// 验证可执行文件的 Authenticode 签名并取出签名者名称，不检查证书吊销，避免联网
+pub fn query(exe: &str) -> Signature {
+    unsafe {
+        let path = HSTRING::from(exe);
+        let mut file = WINTRUST_FILE_INFO {
+            cbStruct: size_of::<WINTRUST_FILE_INFO>() as u32,
+            pcwszFilePath: PCWSTR(path.as_ptr()),
+            ..Default::default()
+        };
+        let mut data = WINTRUST_DATA {
+            cbStruct: size_of::<WINTRUST_DATA>() as u32,
+            dwUIChoice: WTD_UI_NONE,
+            fdwRevocationChecks: WTD_REVOKE_NONE,
+            dwUnionChoice: WTD_CHOICE_FILE,
+            Anonymous: WINTRUST_DATA_0 { pFile: &mut file },
+            dwStateAction: WTD_STATEACTION_VERIFY, // 保留签名数据，之后取出签名者，用完需要 CLOSE
+            ..Default::default()
+        };
+        let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+        let status = WinVerifyTrust(HWND::default(), &mut action, &mut data as *mut _ as *mut _);
+        let signer = signer_name(data.hWVTStateData);
+        data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(HWND::default(), &mut action, &mut data as *mut _ as *mut _);
+        match status {
+            0 => Signature::Valid(signer),
+            _ if status == TRUST_E_NOSIGNATURE.0 => Signature::Unsigned,
+            _ => Signature::Invalid(signer, status),
+        }
+    }
+}
+
+// 从验证过程保留的数据中取出第一个签名者证书的显示名称
+unsafe fn signer_name(state: HANDLE) -> Option<String> {
+    if state.is_invalid() {
+        return None;
+    }
+    let provider = WTHelperProvDataFromStateData(state);
+    if provider.is_null() {
+        return None;
+    }
+    let signer = WTHelperGetProvSignerFromChain(provider, 0, false, 0);
+    if signer.is_null() || (*signer).csCertChain == 0 || (*signer).pasCertChain.is_null() {
+        return None;
+    }
+    let cert = (*(*signer).pasCertChain).pCert;
+    let mut buffer = [0u16; 256];
+    let len = CertGetNameStringW(cert, CERT_NAME_SIMPLE_DISPLAY_TYPE, 0, None, Some(&mut buffer)) as usize; // 含结尾的空字符
+    (len > 1).then(|| String::from_utf16_lossy(&buffer[..len - 1]))
+}
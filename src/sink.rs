@@ -0,0 +1,274 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use chrono::{DateTime, Local}; // 记录时间
+use log::{error, warn}; // 输出目标出错时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 记录序列化为JSON，从配置文件读取字段设置
+
+use crate::error::Error; // 输出目标写入失败的原因
+use crate::metrics::Metrics; // 统计写入延迟
+
+// 每个输出目标最多排队的记录数，输出目标处理不过来时丢弃新记录，而不是拖慢监视或占满内存
+const QUEUE_CAPACITY: usize = 4096;
+
+// 记录中总是保留的字段，用于按天分文件和区分记录的种类
+pub const KEY_FIELDS: &[&str] = &["time", "kind"];
+
+// [fields] 中可以列出的字段：窗口切换、心跳、会话标记记录中的字段
+pub const FIELDS: &[&str] = &[
+    "pid", "title", "exe", "app", "product", "company", "version", "presenting", "env", "lang", "id", "burst", // 窗口切换
+    "memory", "cache_budget", "caches", "processes", "latency", "corrections", "hook_restarts", // 心跳
+    "session", "marker", "reason", "previous", "last_seen", // 会话标记
+    "merge_ms", // 状态心跳
+    "desktop",  // 安全桌面
+    "since",    // 漏报纠正
+    "event_id", "enricher", "fields", // 补充信息
+];
+
+// [fields] 各个输出目标接收的字段，为空表示接收全部字段
+// 风险较高的输出目标可以只拿到最少的数据，由 Pipeline 统一去掉其余字段，输出目标自己不需要处理
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct FieldsConfig {
+    pub journal: Vec<String>, // 事件记录文件
+    #[cfg(feature = "http")]
+    pub http: Vec<String>,    // 本地HTTP接口
+}
+
+// 送往各个输出目标的一条记录，序列化一次后共享给所有输出目标
+#[derive(Clone)]
+pub struct Event {
+    pub time: DateTime<Local>, // 记录时间，用于按天分文件等
+    pub line: String,          // 记录的JSON，不含换行符
+    emitted: Instant,          // 生成记录的时间，用于统计写入延迟
+}
+
+// 记录的输出目标，如事件记录文件
+// 每个输出目标在自己的线程中按顺序处理记录，一个输出目标变慢不会影响其他输出目标
+pub trait Sink: Send {
+    // 输出目标的名称，用于日志和延迟统计
+    fn name(&self) -> &'static str;
+
+    // 输出一条记录
+    fn write(&mut self, event: &Event) -> Result<(), Error>;
+}
+
+// 自启动以来所有输出目标的写入次数，用于健康告警
+#[derive(Default)]
+struct Counters {
+    written: AtomicU64, // 写入成功
+    failed: AtomicU64,  // 写入失败
+    dropped: AtomicU64, // 队列已满而丢弃
+}
+
+// Counters 在某一时刻的值
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Totals {
+    pub written: u64,
+    pub failed: u64,
+    pub dropped: u64,
+}
+
+// 把记录分发给所有输出目标，每个输出目标一个工作线程和一个有界队列
+// 同一个输出目标收到记录的顺序与生成的顺序一致
+pub struct Pipeline {
+    queues: Vec<Queue>,           // 各个输出目标的队列
+    metrics: Arc<Mutex<Metrics>>, // 工作线程在写入完成时记录延迟
+    counters: Arc<Counters>,      // 工作线程在写入完成时计数
+    workers: Vec<JoinHandle<()>>, // 各个输出目标的工作线程
+}
+
+// 一个输出目标的队列
+struct Queue {
+    name: &'static str,             // 输出目标的名称
+    fields: Option<Vec<String>>,    // 只接收这些字段（以及 KEY_FIELDS），None 表示接收全部字段
+    sender: SyncSender<Arc<Event>>, // 放入记录
+    dropping: AtomicU64,            // 队列满了之后连续丢弃的记录数，为0表示队列没有满
+}
+
+impl Pipeline {
+    pub fn new(metrics: Arc<Mutex<Metrics>>) -> Pipeline {
+        Pipeline { queues: Vec::new(), metrics, counters: Arc::default(), workers: Vec::new() }
+    }
+
+    // 添加一个输出目标，并启动它的工作线程；fields 为空时接收全部字段
+    pub fn add(&mut self, sink: Box<dyn Sink>, fields: &[String]) -> Result<(), Error> {
+        let name = sink.name();
+        let (sender, receiver) = sync_channel(QUEUE_CAPACITY);
+        let metrics = Arc::clone(&self.metrics);
+        let counters = Arc::clone(&self.counters);
+        let worker = std::thread::Builder::new()
+            .name(format!("sink-{}", name))
+            .spawn(move || run_worker(sink, receiver, metrics, counters))?;
+        let fields = if fields.is_empty() { None } else { Some(fields.to_vec()) };
+        self.queues.push(Queue { name, fields, sender, dropping: AtomicU64::new(0) });
+        self.workers.push(worker);
+        Ok(())
+    }
+
+    // 序列化记录并放入每个输出目标的队列，不等待写入完成
+    // 接收全部字段的输出目标共享同一份序列化结果，限定了字段的输出目标各自得到去掉其余字段后的记录
+    pub fn send(&self, time: DateTime<Local>, record: &impl Serialize) -> Result<(), serde_json::Error> {
+        let emitted = Instant::now();
+        let event = Arc::new(Event { time, line: serde_json::to_string(record)?, emitted });
+        for queue in &self.queues {
+            let event = match &queue.fields {
+                None => Arc::clone(&event),
+                Some(fields) => Arc::new(Event { time, line: select(&event.line, fields)?, emitted }),
+            };
+            // 丢弃只在第一次和恢复后各记录一次日志，输出目标卡住时不会让运行日志被刷屏
+            match queue.sender.try_send(event) {
+                Ok(()) => {
+                    let dropped = queue.dropping.swap(0, Ordering::Relaxed);
+                    if dropped > 0 {
+                        warn!("输出目标 {} 已恢复，期间丢弃了 {} 条记录", queue.name, dropped);
+                    }
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    if queue.dropping.fetch_add(1, Ordering::Relaxed) == 0 {
+                        warn!("输出目标 {} 处理不过来，开始丢弃记录，恢复后报告丢弃的条数", queue.name);
+                    }
+                }
+                Err(TrySendError::Disconnected(_)) => error!("输出目标 {} 的工作线程已退出，记录未写入", queue.name),
+            }
+        }
+        Ok(())
+    }
+
+    // 自启动以来所有输出目标写入成功、失败和丢弃的记录数
+    pub fn totals(&self) -> Totals {
+        Totals {
+            written: self.counters.written.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    // 关闭所有队列，等待各个输出目标写完队列中剩余的记录，退出前调用
+    pub fn close(self) {
+        drop(self.queues);
+        for worker in self.workers {
+            let _ = worker.join(); // 工作线程panic时已经写入了崩溃报告
+        }
+    }
+}
+
+// 只保留记录中列出的字段和 KEY_FIELDS
+pub fn select(line: &str, fields: &[String]) -> Result<String, serde_json::Error> {
+    let mut value: serde_json::Value = serde_json::from_str(line)?;
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|key, _| KEY_FIELDS.contains(&key.as_str()) || fields.iter().any(|f| f == key));
+    }
+    serde_json::to_string(&value)
+}
+
+// 工作线程：按顺序把队列中的记录交给输出目标，出错时只记录日志，继续处理后面的记录
+fn run_worker(mut sink: Box<dyn Sink>, receiver: Receiver<Arc<Event>>, metrics: Arc<Mutex<Metrics>>, counters: Arc<Counters>) {
+    for event in receiver {
+        match sink.write(&event) {
+            Ok(()) => counters.written.fetch_add(1, Ordering::Relaxed),
+            Err(e) => {
+                error!("输出到 {} 失败: {}", sink.name(), e);
+                counters.failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+        if let Ok(mut metrics) = metrics.lock() {
+            metrics.sink(sink.name()).add(event.emitted.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::Duration;
+
+    use super::*;
+
+    // 记录收到的序号；gate 不为空时第一次写入前等待放行，模拟很慢的输出目标
+    struct Recorder {
+        name: &'static str,
+        seen: Arc<Mutex<Vec<u64>>>,
+        gate: Option<Receiver<()>>,
+    }
+
+    impl Sink for Recorder {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn write(&mut self, event: &Event) -> Result<(), Error> {
+            if let Some(gate) = self.gate.take() {
+                let _ = gate.recv();
+            }
+            let value: serde_json::Value = serde_json::from_str(&event.line)?;
+            self.seen.lock().unwrap().push(value["seq"].as_u64().unwrap());
+            Ok(())
+        }
+    }
+
+    // 慢的输出目标不拖慢快的输出目标，每个输出目标收到记录的顺序与发送的顺序一致
+    #[test]
+    fn slow_sink_does_not_hold_up_others_and_order_is_kept() {
+        const COUNT: u64 = 3000;
+        let (release, gate) = channel();
+        let (slow, fast) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        let mut pipeline = Pipeline::new(Arc::default());
+        pipeline.add(Box::new(Recorder { name: "slow", seen: Arc::clone(&slow), gate: Some(gate) }), &[]).unwrap();
+        pipeline.add(Box::new(Recorder { name: "fast", seen: Arc::clone(&fast), gate: None }), &[]).unwrap();
+
+        let time = Local::now();
+        for seq in 0..COUNT {
+            pipeline.send(time, &serde_json::json!({ "time": time, "seq": seq })).unwrap();
+        }
+        let start = Instant::now();
+        while (fast.lock().unwrap().len() as u64) < COUNT && start.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(fast.lock().unwrap().len() as u64, COUNT, "快的输出目标应当不等慢的输出目标就写完");
+        assert!(slow.lock().unwrap().is_empty(), "慢的输出目标还没有放行");
+
+        release.send(()).unwrap();
+        let totals = pipeline.totals();
+        pipeline.close();
+        let expected: Vec<u64> = (0..COUNT).collect();
+        assert_eq!(*fast.lock().unwrap(), expected);
+        assert_eq!(*slow.lock().unwrap(), expected);
+        assert_eq!(totals.dropped, 0);
+    }
+
+    // 队列满了之后丢弃的记录计入总数，队列恢复后重新开始计数
+    #[test]
+    fn drops_are_counted_until_queue_recovers() {
+        const EXTRA: u64 = 100;
+        let (release, gate) = channel();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut pipeline = Pipeline::new(Arc::default());
+        pipeline.add(Box::new(Recorder { name: "stalled", seen: Arc::clone(&seen), gate: Some(gate) }), &[]).unwrap();
+
+        let time = Local::now();
+        let send = |seq: u64| pipeline.send(time, &serde_json::json!({ "time": time, "seq": seq })).unwrap();
+        for seq in 0..QUEUE_CAPACITY as u64 + 1 + EXTRA {
+            send(seq); // 工作线程取走第一条后等待放行，队列再放满 QUEUE_CAPACITY 条
+        }
+        let dropped = pipeline.totals().dropped;
+        assert!((EXTRA..=EXTRA + 1).contains(&dropped), "丢弃了 {} 条", dropped);
+        assert_eq!(pipeline.queues[0].dropping.load(Ordering::Relaxed), dropped);
+
+        release.send(()).unwrap();
+        let start = Instant::now();
+        while seen.lock().unwrap().len() < QUEUE_CAPACITY && start.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        send(u64::MAX);
+        assert_eq!(pipeline.queues[0].dropping.load(Ordering::Relaxed), 0);
+        pipeline.close();
+    }
+}
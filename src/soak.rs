@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use log::{info, warn}; // 记录压力测试的进度
+use windows::core::{w, HSTRING};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW; // 注册窗口类所需的模块句柄
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW,     // 创建测试窗口
+    DefWindowProcW,      // 测试窗口不处理任何消息
+    DestroyWindow,       // 测试结束后销毁窗口
+    DispatchMessageW,
+    GetForegroundWindow, // 确认切换是否成功
+    PeekMessageW,        // 处理测试窗口的消息，避免被系统视为无响应
+    RegisterClassW,
+    SetForegroundWindow, // 把测试窗口切换到前台
+    TranslateMessage,
+    MSG,
+    PM_REMOVE,
+    WINDOW_EX_STYLE,
+    WNDCLASSW,
+    WS_OVERLAPPEDWINDOW,
+    WS_VISIBLE,
+};
+
+use crate::shutdown; // 测试结束后让主循环退出
+
+// 测试窗口的数量
+const WINDOWS: usize = 4;
+
+// 每次切换前台窗口的间隔
+const SWITCH_INTERVAL: Duration = Duration::from_millis(250);
+
+// 每隔多久在日志中报告一次进度
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(60);
+
+// 压力测试：在独立的线程中创建几个测试窗口，不模拟任何输入，按固定间隔轮流切换到前台
+// 监视程序照常记录，用于检查在目标机器上长时间运行时内存、延迟是否稳定
+pub struct Soak {
+    attempts: Arc<AtomicU64>,  // 尝试切换的次数
+    switched: Arc<AtomicU64>,  // 切换后确认窗口确实到了前台的次数
+    thread: JoinHandle<()>,    // 切换窗口的线程，测试结束后请求主循环退出
+}
+
+impl Soak {
+    // 开始压力测试，持续 minutes 分钟
+    pub fn start(minutes: u64) -> std::io::Result<Soak> {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let switched = Arc::new(AtomicU64::new(0));
+        let (a, s) = (Arc::clone(&attempts), Arc::clone(&switched));
+        let duration = Duration::from_secs(minutes * 60);
+        let thread = std::thread::Builder::new().name("soak".to_string()).spawn(move || {
+            run(duration, &a, &s);
+            shutdown::request();
+        })?;
+        Ok(Soak { attempts, switched, thread })
+    }
+
+    // 等待切换线程结束，返回尝试切换和确认成功的次数
+    pub fn finish(self) -> (u64, u64) {
+        let _ = self.thread.join();
+        (self.attempts.load(Ordering::SeqCst), self.switched.load(Ordering::SeqCst))
+    }
+}
+
+// 切换窗口的线程：窗口必须在处理其消息的线程中创建
+fn run(duration: Duration, attempts: &AtomicU64, switched: &AtomicU64) {
+    let windows = match create_windows() {
+        Ok(windows) => windows,
+        Err(e) => {
+            warn!("创建压力测试窗口失败: {}", e);
+            return;
+        }
+    };
+    info!("压力测试开始，在 {} 个测试窗口之间切换 {} 分钟", windows.len(), duration.as_secs() / 60);
+    let start = Instant::now();
+    let mut reported = Instant::now();
+    let mut next = 0;
+    while start.elapsed() < duration && !shutdown::requested() {
+        let hwnd = windows[next % windows.len()];
+        next += 1;
+        attempts.fetch_add(1, Ordering::SeqCst);
+        unsafe {
+            let _ = SetForegroundWindow(hwnd); // 是否成功以随后查询到的前台窗口为准
+            if GetForegroundWindow() == hwnd {
+                switched.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        pump_messages();
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            info!(
+                "压力测试进行中 | 已进行 {} 分钟 | 切换 {} 次，成功 {} 次",
+                start.elapsed().as_secs() / 60,
+                attempts.load(Ordering::SeqCst),
+                switched.load(Ordering::SeqCst)
+            );
+        }
+        std::thread::sleep(SWITCH_INTERVAL);
+    }
+    for hwnd in windows {
+        let _ = unsafe { DestroyWindow(hwnd) };
+    }
+}
+
+// 注册窗口类并创建测试窗口
+fn create_windows() -> windows::core::Result<Vec<HWND>> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = w!("ForegroundWatcherSoak");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: class,
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+        (0..WINDOWS)
+            .map(|i| {
+                let title = HSTRING::from(format!("ForegroundWatcher 压力测试 {}", i + 1));
+                let offset = 40 * i as i32; // 错开位置，便于肉眼确认切换
+                CreateWindowExW(
+                    WINDOW_EX_STYLE::default(),
+                    class,
+                    &title,
+                    WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                    100 + offset,
+                    100 + offset,
+                    400,
+                    300,
+                    None,
+                    None,
+                    Some(instance.into()),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+// 处理测试窗口线程中积压的消息
+fn pump_messages() {
+    unsafe {
+        let mut msg = MSG::default();
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// 测试窗口的窗口过程，全部交给默认处理
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use foreground_watcher_types::FocusEvent; // 保留的窗口切换记录
+use log::debug; // 并入之后的窗口时记录日志
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+use windows::Win32::Foundation::{CloseHandle, FILETIME};
+use windows::Win32::System::SystemInformation::GetSystemTimeAsFileTime; // 当前时间
+use windows::Win32::System::Threading::{
+    GetProcessTimes, // 进程的启动时间
+    OpenProcess,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+// [splash] 启动窗口的设置
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct SplashConfig {
+    pub grace_ms: u64,        // 新启动的程序的窗口在这段时间（毫秒）内就切换到同一进程的其他窗口时，并入之后的窗口，0表示不检查
+    pub startup_seconds: u64, // 进程启动后这么多秒以内出现的窗口才按启动窗口检查
+}
+
+impl Default for SplashConfig {
+    fn default() -> Self {
+        SplashConfig { grace_ms: 0, startup_seconds: 10 }
+    }
+}
+
+// 保留中的记录
+struct Held {
+    event: FocusEvent,
+    detected: Instant, // 发现窗口切换的时间，写入时用于统计延迟
+}
+
+// 启动窗口的合并：程序启动时先显示的启动画面、加载窗口很快就会被主窗口取代，单独记录只是噪音
+// 新启动的进程的窗口先保留 grace_ms，期间切换到同一进程的另一个窗口时丢弃保留的记录，之后的窗口从启动窗口出现时开始计算；
+// 期间切换到其他进程，或超过 grace_ms 仍在前台时，照常写入保留的记录
+pub struct Splash {
+    grace: Duration,
+    startup: Duration,
+    held: Option<Held>,
+}
+
+impl Splash {
+    // grace_ms 为0时返回None
+    pub fn new(config: &SplashConfig) -> Option<Splash> {
+        (config.grace_ms > 0).then(|| Splash {
+            grace: Duration::from_millis(config.grace_ms),
+            startup: Duration::from_secs(config.startup_seconds),
+            held: None,
+        })
+    }
+
+    // 新的窗口切换记录，返回现在需要写入的记录，按写入顺序排列
+    pub fn push(&mut self, mut event: FocusEvent, detected: Instant) -> Vec<(FocusEvent, Instant)> {
+        let mut ready = Vec::new();
+        if let Some(held) = self.held.take() {
+            if held.detected.elapsed() < self.grace && held.event.pid == event.pid {
+                debug!("进程 {} 的启动窗口 {:?} 已并入之后的窗口", held.event.pid, held.event.title.as_deref().unwrap_or(""));
+                event.time = held.event.time;
+            } else {
+                ready.push((held.event, held.detected));
+            }
+        }
+        if process_age(event.pid).is_some_and(|age| age < self.startup) {
+            self.held = Some(Held { event, detected });
+        } else {
+            ready.push((event, detected));
+        }
+        ready
+    }
+
+    // 保留超过 grace_ms 的记录
+    pub fn due(&mut self) -> Option<(FocusEvent, Instant)> {
+        if self.held.as_ref().is_some_and(|held| held.detected.elapsed() >= self.grace) {
+            return self.take();
+        }
+        None
+    }
+
+    // 距保留的记录到期还有多久，没有保留的记录时为None
+    pub fn remaining(&self) -> Option<Duration> {
+        self.held.as_ref().map(|held| self.grace.saturating_sub(held.detected.elapsed()))
+    }
+
+    // 立即取出保留的记录，在写入安全桌面、会话结束等其他记录之前调用，保证记录按时间顺序写入
+    pub fn take(&mut self) -> Option<(FocusEvent, Instant)> {
+        self.held.take().map(|held| (held.event, held.detected))
+    }
+}
+
+// 进程已经运行的时间，无法打开进程时返回None
+fn process_age(pid: u32) -> Option<Duration> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let (mut created, mut exited, mut kernel, mut user) = (FILETIME::default(), FILETIME::default(), FILETIME::default(), FILETIME::default());
+        let result = GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        let now = GetSystemTimeAsFileTime();
+        let ticks = |t: FILETIME| ((t.dwHighDateTime as u64) << 32) | t.dwLowDateTime as u64; // 单位为100纳秒
+        Some(Duration::from_nanos(ticks(now).saturating_sub(ticks(created)) * 100))
+    }
+}
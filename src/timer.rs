@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use log::warn; // 创建计时器失败时退回 std::thread::sleep
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::System::Threading::{
+    CreateWaitableTimerExW,  // 创建可等待计时器
+    GetCurrentProcess,       // 当前进程的伪句柄
+    SetProcessPriorityBoost, // 开关系统对本进程的临时优先级提升
+    SetWaitableTimerEx,      // 设置到期时间和允许的延迟
+    WaitForSingleObject,     // 等待计时器到期
+    INFINITE,
+    TIMER_ALL_ACCESS,
+};
+
+// 轮询间隔的等待方式：使用带容许延迟的可等待计时器，系统可以把多个程序的唤醒合并到一起，
+// 让CPU在轮询间隙进入更深的节能状态，而不是像 sleep 那样每次都精确唤醒
+pub struct Timer {
+    handle: Option<HANDLE>, // 可等待计时器，创建失败时为None，退回 std::thread::sleep
+    tolerance_ms: u32,      // 允许系统推迟唤醒的最长时间（毫秒）
+}
+
+impl Timer {
+    // 创建计时器，tolerance_ms 为0时不合并唤醒
+    pub fn new(tolerance_ms: u32) -> Timer {
+        let handle = unsafe { CreateWaitableTimerExW(None, PCWSTR::null(), 0, TIMER_ALL_ACCESS.0) };
+        match handle {
+            Ok(handle) => Timer { handle: Some(handle), tolerance_ms },
+            Err(e) => {
+                warn!("创建可等待计时器失败，改用普通休眠: {}", e);
+                Timer { handle: None, tolerance_ms }
+            }
+        }
+    }
+
+    // 等待指定的时间，实际唤醒可能最多推迟 tolerance_ms
+    pub fn sleep(&self, duration: Duration) {
+        let Some(handle) = self.handle else {
+            std::thread::sleep(duration);
+            return;
+        };
+        let due = -((duration.as_nanos() / 100) as i64); // 负数表示相对时间，单位为100纳秒
+        unsafe {
+            if SetWaitableTimerEx(handle, &due, 0, None, None, None, self.tolerance_ms).is_err() {
+                std::thread::sleep(duration);
+                return;
+            }
+            WaitForSingleObject(handle, INFINITE);
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle {
+            unsafe {
+                let _ = CloseHandle(handle);
+            }
+        }
+    }
+}
+
+// 关闭系统对本进程的临时优先级提升（如窗口切换到前台、等待结束时的提升），
+// 监视程序不需要及时响应，不应与前台程序争抢CPU
+pub fn disable_priority_boost() {
+    if let Err(e) = unsafe { SetProcessPriorityBoost(GetCurrentProcess(), true) } {
+        warn!("关闭优先级提升失败: {}", e);
+    }
+}
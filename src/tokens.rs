@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local}; // 令牌的创建时间
+use log::error; // 令牌文件有误时记录日志
+use serde::{Deserialize, Serialize}; // 令牌文件为JSON
+use windows::Win32::Security::Cryptography::{
+    BCryptGenRandom, // 生成令牌
+    BCryptHash,      // 令牌只保存 SHA-256 散列值
+    BCRYPT_SHA256_ALG_HANDLE,
+    BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+};
+
+use crate::error::{Error, TokenError}; // 令牌文件有误或没有匹配的令牌
+
+// 令牌的前缀，便于在日志、剪贴板中认出它是本程序的令牌
+const PREFIX: &str = "fw_";
+
+// 令牌的随机部分的字节数
+const SECRET_BYTES: usize = 24;
+
+// 令牌文件中的一项，只保存散列值，令牌本身只在创建时显示一次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenEntry {
+    pub id: String,              // 散列值的前8位，用于列出和吊销
+    pub name: String,            // 用途说明，如 dashboard
+    pub hash: String,            // 令牌的 SHA-256 散列值（十六进制）
+    pub created: DateTime<Local>, // 创建时间
+}
+
+// 读取令牌文件，文件不存在时为空
+pub fn load(path: &Path) -> Result<Vec<TokenEntry>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|source| Error::Token(TokenError::Invalid { path: path.to_path_buf(), source })),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(source) => Err(Error::Token(TokenError::Unreadable { path: path.to_path_buf(), source })),
+    }
+}
+
+// 写入令牌文件，先写临时文件再替换，避免写到一半时留下损坏的文件
+fn save(path: &Path, tokens: &[TokenEntry]) -> Result<(), Error> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(tokens)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// 创建一个令牌，返回令牌本身和保存的项
+pub fn create(path: &Path, name: &str, now: DateTime<Local>) -> Result<(String, TokenEntry), Error> {
+    let mut tokens = load(path)?;
+    if tokens.iter().any(|t| t.name == name) {
+        return Err(Error::Token(TokenError::Duplicate(name.to_string())));
+    }
+    let mut secret = [0u8; SECRET_BYTES];
+    unsafe { BCryptGenRandom(None, &mut secret, BCRYPT_USE_SYSTEM_PREFERRED_RNG) }.ok().map_err(|e| Error::Token(TokenError::Random(e)))?;
+    let token = format!("{}{}", PREFIX, hex(&secret));
+    let hash = sha256(&token)?;
+    let entry = TokenEntry { id: hash[..8].to_string(), name: name.to_string(), hash, created: now };
+    tokens.push(entry.clone());
+    save(path, &tokens)?;
+    Ok((token, entry))
+}
+
+// 吊销 id 或名称匹配的令牌，返回被吊销的项，没有匹配时返回错误
+pub fn revoke(path: &Path, key: &str) -> Result<TokenEntry, Error> {
+    let mut tokens = load(path)?;
+    let index = tokens
+        .iter()
+        .position(|t| t.id == key || t.name == key)
+        .ok_or_else(|| Error::Token(TokenError::NotFound(key.to_string())))?;
+    let entry = tokens.remove(index);
+    save(path, &tokens)?;
+    Ok(entry)
+}
+
+// 令牌的 SHA-256 散列值（十六进制）
+fn sha256(token: &str) -> Result<String, Error> {
+    let mut digest = [0u8; 32];
+    unsafe { BCryptHash(BCRYPT_SHA256_ALG_HANDLE, None, token.as_bytes(), &mut digest) }.ok().map_err(|e| Error::Token(TokenError::Hash(e)))?;
+    Ok(hex(&digest))
+}
+
+// 字节的十六进制形式
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 比较两个散列值，耗时与内容无关
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// 本地HTTP接口校验请求中的令牌，令牌文件修改后（创建、吊销）在下一次请求时自动重新加载
+pub struct Tokens {
+    path: PathBuf,
+    modified: Option<SystemTime>, // 上次加载时文件的修改时间
+    hashes: Vec<String>,          // 有效令牌的散列值
+}
+
+impl Tokens {
+    pub fn new(path: PathBuf) -> Tokens {
+        let mut tokens = Tokens { path, modified: None, hashes: Vec::new() };
+        tokens.refresh();
+        tokens
+    }
+
+    // 文件有变化时重新加载，文件有误时不接受任何令牌，而不是放开访问
+    fn refresh(&mut self) {
+        let modified = self.path.metadata().and_then(|m| m.modified()).ok();
+        if modified == self.modified && modified.is_some() {
+            return;
+        }
+        self.modified = modified;
+        self.hashes = match load(&self.path) {
+            Ok(tokens) => tokens.into_iter().map(|t| t.hash).collect(),
+            Err(e) => {
+                error!("{}，本地HTTP接口将拒绝所有请求", e);
+                vec![String::new()] // 非空：仍然要求令牌，但没有令牌能匹配
+            }
+        };
+    }
+
+    // 是否创建了令牌：没有创建任何令牌时本地HTTP接口不要求令牌，与之前的行为一致
+    pub fn required(&mut self) -> bool {
+        self.refresh();
+        !self.hashes.is_empty()
+    }
+
+    // 令牌是否有效
+    pub fn accepts(&mut self, token: &str) -> bool {
+        self.refresh();
+        let Ok(hash) = sha256(token) else {
+            return false;
+        };
+        self.hashes.iter().any(|h| same(h, &hash))
+    }
+}
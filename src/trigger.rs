@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate}; // 按本地日期判断是否是今天第一次
+use foreground_watcher_types::{FocusEvent, PulseRecord}; // 从记录中取出前台程序
+use log::{info, warn}; // 触发和失败时记录日志
+use regex::Regex; // 匹配可执行文件路径
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 从配置文件读取，发送给 webhook 的内容
+
+use crate::clock::SharedClock; // 确定“今天”
+use crate::error::{Error, WebhookError}; // 记录无法解析、webhook 发送失败
+use crate::history; // 启动时读取今天已有的记录
+use crate::identity; // 与别名规则使用同一种正则表达式编译方式
+use crate::sink::{Event, Sink}; // 作为输出目标接收记录
+
+// 连接和发送 webhook 请求的超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 配置文件中的一条 [[trigger]] 规则
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct TriggerRule {
+    pub pattern: String,              // 匹配可执行文件完整路径的正则表达式，不区分大小写
+    pub name: Option<String>,         // 规则的名称，用于日志和通知内容，省略时使用 pattern
+    pub webhook: Option<String>,      // 以 POST 发送JSON的地址，只支持 http://
+    #[serde(default)]
+    pub command: Vec<String>,         // 执行的程序及其参数，程序通过环境变量得到触发的应用
+}
+
+// 发送给 webhook 的内容
+#[derive(Serialize)]
+struct FirstFocus<'a> {
+    kind: &'static str,      // 固定为 first_focus
+    trigger: &'a str,        // 规则的名称
+    time: DateTime<Local>,   // 今天第一次切换到前台的时间
+    app: Option<&'a str>,    // 应用标识
+    exe: &'a str,            // 可执行文件路径
+}
+
+// 只取出记录的种类，窗口切换记录没有 kind
+#[derive(Deserialize)]
+struct Kind {
+    kind: Option<String>,
+}
+
+// 编译好的规则
+struct Trigger {
+    name: String,
+    pattern: Regex,
+    webhook: Option<String>,
+    command: Vec<String>,
+}
+
+// 每天第一次切换到匹配的程序时执行规则中的动作，用于在线状态自动化、估计开始工作的时间等
+pub struct TriggerSink {
+    triggers: Vec<Trigger>,
+    fired: HashMap<usize, NaiveDate>, // 规则序号 -> 最近一次触发的日期
+}
+
+impl TriggerSink {
+    // 编译规则，配置校验阶段已保证正则表达式有效
+    // 今天的事件记录中已经出现过的程序不再触发，当天重启监视程序不会重复通知
+    pub fn new(rules: &[TriggerRule], events: &Path, clock: &SharedClock) -> Result<TriggerSink, regex::Error> {
+        let triggers: Vec<Trigger> = rules
+            .iter()
+            .map(|r| {
+                identity::compile(&r.pattern).map(|pattern| Trigger {
+                    name: r.name.clone().unwrap_or_else(|| r.pattern.clone()),
+                    pattern,
+                    webhook: r.webhook.clone(),
+                    command: r.command.clone(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let now = clock.now();
+        let today = now.date_naive();
+        let mut fired = HashMap::new();
+        match history::intervals(events, history::day_start(today), now) {
+            Ok(intervals) => {
+                for exe in intervals.iter().filter_map(|i| i.event.exe.as_deref()) {
+                    for (index, _) in triggers.iter().enumerate().filter(|(_, t)| t.pattern.is_match(exe)) {
+                        fired.insert(index, today);
+                    }
+                }
+            }
+            Err(e) => warn!("读取今天的事件记录失败，今天已经切换过的程序可能会再次触发: {}", e),
+        }
+        Ok(TriggerSink { triggers, fired })
+    }
+}
+
+impl Sink for TriggerSink {
+    fn name(&self) -> &'static str {
+        "trigger"
+    }
+
+    fn write(&mut self, event: &Event) -> Result<(), Error> {
+        let Kind { kind } = serde_json::from_str(&event.line)?;
+        let state: FocusEvent = match kind.as_deref() {
+            None => serde_json::from_str(&event.line)?,
+            Some("pulse") => serde_json::from_str::<PulseRecord>(&event.line)?.state,
+            Some(_) => return Ok(()), // 只关心前台程序
+        };
+        let Some(exe) = state.exe.as_deref() else {
+            return Ok(());
+        };
+        let today = state.time.date_naive();
+        for (index, trigger) in self.triggers.iter().enumerate() {
+            if self.fired.get(&index) == Some(&today) || !trigger.pattern.is_match(exe) {
+                continue;
+            }
+            self.fired.insert(index, today);
+            info!("{} | 今天第一次切换到 {}，执行触发规则 {}", state.time.format("%Y-%m-%d %H:%M:%S"), exe, trigger.name);
+            let payload = FirstFocus { kind: "first_focus", trigger: &trigger.name, time: state.time, app: state.app.as_deref(), exe };
+            let env = vec![
+                ("FW_TRIGGER", trigger.name.clone()),
+                ("FW_TIME", state.time.to_rfc3339()),
+                ("FW_APP", state.app.clone().unwrap_or_default()),
+                ("FW_EXE", exe.to_string()),
+            ];
+            let action = Action { name: format!("触发规则 {}", trigger.name), webhook: trigger.webhook.clone(), command: trigger.command.clone() };
+            action.run(serde_json::to_string(&payload)?, env)?;
+        }
+        Ok(())
+    }
+}
+
+// 触发规则、健康告警等共用的动作：以 POST 向 webhook 发送JSON，执行程序
+#[derive(Clone)]
+pub struct Action {
+    pub name: String,            // 用于日志，如“触发规则 outlook”
+    pub webhook: Option<String>, // 只支持 http://
+    pub command: Vec<String>,    // 执行的程序及其参数
+}
+
+impl Action {
+    // 在独立的线程中执行，webhook 没有响应或程序运行很久时不阻塞调用者
+    // body 为发送给 webhook 的JSON，env 为传给程序的环境变量
+    pub fn run(&self, body: String, env: Vec<(&'static str, String)>) -> std::io::Result<()> {
+        let (name, webhook, command) = (self.name.clone(), self.webhook.clone(), self.command.clone());
+        std::thread::Builder::new().name("action".to_string()).spawn(move || {
+            if let Some(url) = webhook {
+                if let Err(e) = post(&url, &body) {
+                    warn!("{} 发送 webhook 失败: {}", name, e);
+                }
+            }
+            if let Some((program, args)) = command.split_first() {
+                if let Err(e) = std::process::Command::new(program).args(args).envs(env).spawn() {
+                    warn!("{} 执行 {} 失败: {}", name, program, e);
+                }
+            }
+        })?;
+        Ok(())
+    }
+}
+
+// 解析 http://host[:port][/path]，只支持 http，https 的地址可以改用 command 调用 curl 等程序
+pub fn parse_url(url: &str) -> Result<(String, String), String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| format!("webhook 只支持 http:// 地址，https 可以改用 command 调用 curl: {}", url))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(format!("webhook 地址缺少主机名: {}", url));
+    }
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    Ok((host, path.to_string()))
+}
+
+// 以 POST 发送JSON，响应的状态码不是 2xx 时返回错误
+fn post(url: &str, body: &str) -> Result<(), Error> {
+    let (host, path) = parse_url(url).map_err(|e| Error::Webhook(WebhookError::Url(e)))?;
+    let address = host.to_socket_addrs()?.next().ok_or_else(|| Error::Webhook(WebhookError::Resolve(host.clone())))?;
+    let mut stream = TcpStream::connect_timeout(&address, WEBHOOK_TIMEOUT)?;
+    stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+    stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+    let mut status = [0u8; 12]; // HTTP/1.1 200
+    stream.read_exact(&mut status)?;
+    let status = String::from_utf8_lossy(&status);
+    match status.get(9..12) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(Error::Webhook(WebhookError::Status(status.trim().to_string()))),
+    }
+}
@@ -0,0 +1,94 @@
+use windows::core::{HSTRING, PCWSTR}; // 宽字符串参数
+use windows::Win32::Storage::FileSystem::{
+    GetFileVersionInfoSizeW, // 获取版本资源的大小
+    GetFileVersionInfoW,     // 读取版本资源
+    VerQueryValueW,          // 从版本资源中查询某一项
+};
+
+use crate::lru::LruCache; // 限制缓存大小
+
+// 可执行文件版本资源中对报告有用的几项
+#[derive(Debug, Clone, Default)]
+pub struct VersionInfo {
+    pub product: Option<String>, // ProductName，产品名称，如 "Microsoft Teams"
+    pub company: Option<String>, // CompanyName，公司名称
+    pub version: Option<String>, // FileVersion，文件版本
+}
+
+// 读取可执行文件的版本资源，文件没有版本资源时返回None
+pub fn query(exe: &str) -> Option<VersionInfo> {
+    unsafe {
+        let path = HSTRING::from(exe);
+        let size = GetFileVersionInfoSizeW(&path, None); // 没有版本资源时返回0
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(&path, None, size, data.as_mut_ptr() as *mut _).ok()?;
+
+        // 版本资源可能包含多种语言，先取文件声明的语言，再尝试最常见的英文（Unicode / Windows-1252）
+        let mut translations = Vec::new();
+        if let Some((raw, bytes)) = query_value(&data, "\\VarFileInfo\\Translation") {
+            let raw = std::slice::from_raw_parts(raw, bytes / 2); // 每项为语言ID和代码页两个u16
+            translations.extend(raw.chunks_exact(2).map(|pair| (pair[0], pair[1])));
+        }
+        translations.extend([(0x0409, 0x04B0), (0x0409, 0x04E4)]);
+
+        let string = |name: &str| {
+            translations.iter().find_map(|(lang, codepage)| {
+                let sub_block = format!("\\StringFileInfo\\{:04x}{:04x}\\{}", lang, codepage, name);
+                let (raw, chars) = query_value(&data, &sub_block)?; // 字符串的长度以字符为单位
+                let value = String::from_utf16_lossy(std::slice::from_raw_parts(raw, chars));
+                let value = value.trim_end_matches('\u{0}').trim();
+                (!value.is_empty()).then(|| value.to_string()) // 空字符串视为没有设置
+            })
+        };
+        Some(VersionInfo {
+            product: string("ProductName"),
+            company: string("CompanyName"),
+            version: string("FileVersion"),
+        })
+    }
+}
+
+// 查询版本资源中的一项，返回指向 data 内部的指针和长度（单位由查询的项决定）
+unsafe fn query_value(data: &[u8], sub_block: &str) -> Option<(*const u16, usize)> {
+    let mut buffer: *mut core::ffi::c_void = std::ptr::null_mut();
+    let mut len: u32 = 0;
+    let sub_block = HSTRING::from(sub_block);
+    let found = VerQueryValueW(data.as_ptr() as *const _, PCWSTR(sub_block.as_ptr()), &mut buffer, &mut len).as_bool();
+    (found && !buffer.is_null() && len > 0).then_some((buffer as *const u16, len as usize))
+}
+
+// 按可执行文件路径缓存版本信息，窗口来回切换时不必反复读取文件
+pub struct VersionCache {
+    entries: LruCache<String, Option<VersionInfo>>, // 可执行文件路径 -> 版本信息（没有版本资源时为None）
+}
+
+impl VersionCache {
+    // budget 为缓存的内存上限（字节），超出时淘汰最久没有切换到的程序
+    pub fn new(budget: usize) -> VersionCache {
+        VersionCache { entries: LruCache::new(budget) }
+    }
+
+    // 获取可执行文件的版本信息，第一次查询时读取文件
+    pub fn get(&mut self, exe: &str) -> Option<&VersionInfo> {
+        if self.entries.get(exe).is_none() {
+            let info = query(exe);
+            let size = estimate_size(exe, info.as_ref());
+            self.entries.insert(exe.to_string(), info, size);
+        }
+        self.entries.get(exe)?.as_ref()
+    }
+
+    // 缓存的项数和估算的占用字节数
+    pub fn usage(&self) -> (usize, usize) {
+        (self.entries.count(), self.entries.bytes())
+    }
+}
+
+// 估算一项缓存占用的字节数：字符串内容加上结构本身和哈希表的开销
+fn estimate_size(exe: &str, info: Option<&VersionInfo>) -> usize {
+    let strings = info.map_or(0, |i| [&i.product, &i.company, &i.version].iter().filter_map(|s| s.as_ref()).map(String::len).sum());
+    exe.len() + strings + std::mem::size_of::<(String, Option<VersionInfo>)>() + 64
+}
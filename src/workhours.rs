@@ -0,0 +1,102 @@
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeZone, Weekday}; // 按本地时间划分工作时间
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::Deserialize; // 从配置文件读取
+
+// 配置中的星期名称，按 chrono 的 Weekday 顺序排列
+pub const DAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+// 配置文件中的一条 [[work_hours]] 时间段
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct WorkWindow {
+    #[serde(default = "weekdays")]
+    pub days: Vec<String>, // 适用的星期，如 ["mon", "tue"]，省略时为周一到周五
+    pub start: String,     // 开始时间 HH:MM（本地时间）
+    pub end: String,       // 结束时间 HH:MM，不含，必须晚于开始时间，不支持跨过午夜
+}
+
+// 省略 days 时的默认值：周一到周五
+fn weekdays() -> Vec<String> {
+    DAYS[..5].iter().map(|d| d.to_string()).collect()
+}
+
+// 解析 HH:MM
+pub fn parse_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text, "%H:%M").ok()
+}
+
+// 解析星期名称，不区分大小写
+pub fn parse_day(text: &str) -> Option<Weekday> {
+    DAYS.iter().position(|d| d.eq_ignore_ascii_case(text)).and_then(|i| Weekday::try_from(i as u8).ok())
+}
+
+// 工作时间，用于把各项统计拆分为工作时间内和工作时间外
+pub struct WorkHours {
+    windows: Vec<(Vec<Weekday>, NaiveTime, NaiveTime)>, // 适用的星期、开始时间、结束时间
+}
+
+impl WorkHours {
+    // 配置校验阶段已保证时间和星期有效，这里跳过无法解析的时间段
+    pub fn new(windows: &[WorkWindow]) -> WorkHours {
+        WorkHours {
+            windows: windows
+                .iter()
+                .filter_map(|w| {
+                    let days = w.days.iter().filter_map(|d| parse_day(d)).collect();
+                    Some((days, parse_time(&w.start)?, parse_time(&w.end)?))
+                })
+                .filter(|(_, start, end)| start < end)
+                .collect(),
+        }
+    }
+
+    // 是否没有设置工作时间，这时报告不显示拆分
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    // 一段时间中属于工作时间的时长（毫秒）
+    pub fn overlap_ms(&self, start: DateTime<Local>, end: DateTime<Local>) -> i64 {
+        let mut total = 0;
+        let mut day = start.date_naive();
+        while local(day, NaiveTime::MIN) < end {
+            for (from, to) in self.ranges(day) {
+                let (from, to) = (from.max(start), to.min(end));
+                if from < to {
+                    total += (to - from).num_milliseconds();
+                }
+            }
+            let Some(next) = day.checked_add_days(Days::new(1)) else {
+                break;
+            };
+            day = next;
+        }
+        total
+    }
+
+    // 某一天的工作时间段，互相重叠的时间段合并为一段，避免重复计算
+    fn ranges(&self, day: NaiveDate) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        let mut ranges: Vec<(NaiveTime, NaiveTime)> = self
+            .windows
+            .iter()
+            .filter(|(days, _, _)| days.contains(&day.weekday()))
+            .map(|(_, start, end)| (*start, *end))
+            .collect();
+        ranges.sort();
+        let mut merged: Vec<(NaiveTime, NaiveTime)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged.into_iter().map(|(start, end)| (local(day, start), local(day, end))).collect()
+    }
+}
+
+// 某一天某个本地时间，夏令时切换造成的不存在的时间按UTC处理
+fn local(day: NaiveDate, time: NaiveTime) -> DateTime<Local> {
+    let time = day.and_time(time);
+    Local.from_local_datetime(&time).earliest().unwrap_or_else(|| Local.from_utc_datetime(&time))
+}
@@ -0,0 +1,287 @@
+// ForegroundWatcher 输出数据的类型定义
+// 事件记录文件中的每一行、统计结果都使用这里的类型序列化，集成方可以直接依赖本crate反序列化，
+// 不必根据JSON重新定义一遍
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local}; // 记录时间
+#[cfg(feature = "schema")]
+use schemars::JsonSchema; // 生成JSON Schema，供 schema 子命令输出
+use serde::{Deserialize, Serialize}; // 序列化为JSON
+
+// 一次前台窗口切换，对应事件记录文件（events\YYYY-MM-DD.jsonl）中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FocusEvent {
+    pub time: DateTime<Local>,   // 发现窗口切换的本地时间
+    pub pid: u32,                // 前台窗口所属进程ID
+    pub title: Option<String>,   // 窗口标题，获取失败时为空
+    pub exe: Option<String>,     // 可执行文件路径，进程已结束或无法获取时为空
+    pub app: Option<String>,     // 稳定的应用标识，同一个程序的不同版本取值相同，用于按应用汇总
+    pub product: Option<String>, // 可执行文件版本资源中的产品名称
+    pub company: Option<String>, // 可执行文件版本资源中的公司名称
+    pub version: Option<String>, // 可执行文件版本资源中的文件版本
+    #[serde(default)]
+    pub presenting: bool,        // 窗口切换时是否有程序正在演示（演示模式或全屏），持续到下一条记录
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>, // 按 [[environment]] 规则记录的环境变量，没有配置时不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,    // 开启 detect_language 时检测出的标题语言（ISO 639-1），判断不出时不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,      // 记录ID，会话ID加序号，补充信息按它对应到这条记录；旧版本写入的记录没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<String>,   // 属于一组连续快速的切换时为这一组第一条记录的ID，time 也与第一条相同，组内按 id 中的序号排列；第一条记录本身和不在组中的记录不输出
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub enrichment: BTreeMap<String, serde_json::Value>, // 读取时由 enrichment 记录合并而来的补充字段，写入时总是为空
+}
+
+impl FocusEvent {
+    // 只有时间和进程ID的记录，其余各项为空，用于进程已结束或精简模式
+    pub fn bare(time: DateTime<Local>, pid: u32) -> FocusEvent {
+        FocusEvent {
+            time,
+            pid,
+            title: None,
+            exe: None,
+            app: None,
+            product: None,
+            company: None,
+            version: None,
+            presenting: false,
+            env: BTreeMap::new(),
+            lang: None,
+            id: None,
+            burst: None,
+            enrichment: BTreeMap::new(),
+        }
+    }
+
+    // 记录所属的应用
+    pub fn identity(&self) -> AppIdentity {
+        AppIdentity {
+            id: self.app.clone().or_else(|| self.exe.clone()).unwrap_or_else(|| AppIdentity::UNKNOWN.to_string()),
+            exe: self.exe.clone(),
+        }
+    }
+}
+
+// 稳定的应用标识：匹配了 [[alias]] 规则时为规则中的应用名，否则为去掉版本号目录的小写路径
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct AppIdentity {
+    pub id: String,          // 应用标识，用于按应用汇总
+    pub exe: Option<String>, // 最近一次出现时的可执行文件路径
+}
+
+impl AppIdentity {
+    // 进程已结束、无法得知是哪个程序时使用的标识
+    pub const UNKNOWN: &'static str = "unknown";
+}
+
+// 用户在 apps.toml 中为应用设置的分类
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(transparent)]
+pub struct Category(pub String);
+
+// 一段连续停留在同一个前台窗口的时间：从一条记录开始，到下一条记录为止
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FocusInterval {
+    pub start: DateTime<Local>, // 开始时间，即记录的时间
+    pub end: DateTime<Local>,   // 结束时间，即下一条记录的时间
+    pub event: FocusEvent,      // 开始时的记录
+}
+
+impl FocusInterval {
+    // 把按时间排序的记录转换为区间，最后一条记录的区间到 end 为止
+    pub fn from_events(events: &[FocusEvent], end: DateTime<Local>) -> Vec<FocusInterval> {
+        events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| FocusInterval {
+                start: event.time,
+                end: events.get(i + 1).map_or(end, |next| next.time).max(event.time),
+                event: event.clone(),
+            })
+            .collect()
+    }
+
+    // 区间长度（毫秒）
+    pub fn duration_ms(&self) -> i64 {
+        (self.end - self.start).num_milliseconds()
+    }
+}
+
+// 某个应用在统计区间内的使用情况
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct AppStats {
+    pub app: AppIdentity,           // 应用
+    pub category: Option<Category>, // 分类
+    pub duration_ms: i64,           // 在前台的总时长（毫秒）
+    pub switches: u32,              // 切换到该应用的次数
+    pub presenting_ms: i64,         // 其中正在演示的时长（毫秒）
+    #[serde(default)]
+    pub work_ms: i64,               // 其中在工作时间内的时长（毫秒），没有设置工作时间时为0，工作时间外为 duration_ms - work_ms
+}
+
+// 一段时间内按应用汇总的统计结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct StatsSnapshot {
+    pub start: DateTime<Local>, // 统计区间的开始时间
+    pub end: DateTime<Local>,   // 统计区间的结束时间
+    pub total_ms: i64,          // 所有应用的总时长（毫秒）
+    #[serde(default)]
+    pub work_ms: i64,           // 其中在工作时间内的时长（毫秒），没有设置工作时间时为0
+    pub apps: Vec<AppStats>,    // 各个应用，按时长从长到短排列
+}
+
+impl StatsSnapshot {
+    // 按应用汇总区间，category 根据应用查找其分类
+    pub fn from_intervals(
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        intervals: &[FocusInterval],
+        category: impl Fn(&AppIdentity) -> Option<Category>,
+    ) -> StatsSnapshot {
+        let mut apps: BTreeMap<String, AppStats> = BTreeMap::new();
+        let mut previous: Option<String> = None; // 上一个区间的应用，同一个应用内的窗口切换不算切换到该应用
+        for interval in intervals {
+            let identity = interval.event.identity();
+            let stats = apps.entry(identity.id.clone()).or_insert_with(|| AppStats {
+                category: category(&identity),
+                app: identity.clone(),
+                duration_ms: 0,
+                switches: 0,
+                presenting_ms: 0,
+                work_ms: 0,
+            });
+            stats.app.exe = identity.exe.or(stats.app.exe.take()); // 保留最近一次出现时的路径
+            stats.duration_ms += interval.duration_ms();
+            if previous.as_deref() != Some(identity.id.as_str()) {
+                stats.switches += 1;
+            }
+            previous = Some(identity.id.clone());
+            if interval.event.presenting {
+                stats.presenting_ms += interval.duration_ms();
+            }
+        }
+        let mut apps: Vec<AppStats> = apps.into_values().collect();
+        apps.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms).then_with(|| a.app.id.cmp(&b.app.id)));
+        StatsSnapshot { start, end, total_ms: apps.iter().map(|a| a.duration_ms).sum(), work_ms: 0, apps }
+    }
+
+    // 按工作时间拆分各个应用的时长，work 返回一个区间中属于工作时间的时长（毫秒）
+    // intervals 应与生成统计结果时使用的相同
+    pub fn split_work(&mut self, intervals: &[FocusInterval], work: impl Fn(&FocusInterval) -> i64) {
+        let mut apps: BTreeMap<String, i64> = BTreeMap::new();
+        for interval in intervals {
+            *apps.entry(interval.event.identity().id).or_default() += work(interval);
+        }
+        for app in &mut self.apps {
+            app.work_ms = apps.get(&app.app.id).copied().unwrap_or_default();
+        }
+        self.work_ms = self.apps.iter().map(|a| a.work_ms).sum();
+    }
+}
+
+// 会话标记是会话的开始还是结束
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMarker {
+    Begin,
+    End,
+}
+
+// 会话开始或结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionReason {
+    Startup,        // 程序启动，上一个会话正常结束
+    Shutdown,       // 程序正常退出
+    CrashRecovered, // 程序启动时发现上一个会话没有正常结束（崩溃、被结束或断电）
+    Sleep,          // 系统睡眠或休眠后恢复，期间没有轮询
+}
+
+// 事件记录中的会话标记，一个会话是监视程序连续运行、记录没有中断的一段时间
+// 会话以 begin 开始；正常退出时以 end 结束，否则以下一个会话 begin 中的 last_seen 为止
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "kind", rename = "session")]
+pub struct SessionRecord {
+    pub time: DateTime<Local>,  // 写入标记的本地时间
+    pub session: String,        // 会话ID，每个会话唯一
+    pub marker: SessionMarker,  // 开始还是结束
+    pub reason: SessionReason,  // 原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>, // 上一个没有正常结束的会话ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Local>>, // 上一个会话最后一次确认仍在运行的时间，从这时到本会话开始没有记录
+}
+
+// 输入切换到了安全桌面（UAC 提示、Ctrl+Alt+Del 界面、登录界面），那里没有可以查询的窗口
+// 从这时起到下一条记录之间的时间不属于之前的前台程序
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "kind", rename = "secure_desktop")]
+pub struct SecureDesktopRecord {
+    pub time: DateTime<Local>, // 发现切换到安全桌面的本地时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop: Option<String>, // 桌面名称，如 Winlogon；没有权限打开安全桌面时为空
+}
+
+// 钩子和轮询混合方式下，定期核对时发现钩子漏报了一次前台窗口切换，紧接着会写一条实际前台窗口的记录
+// 漏报的切换发生在 since 到 time 之间，这段时间的归属不确定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "kind", rename = "correction")]
+pub struct CorrectionRecord {
+    pub time: DateTime<Local>,  // 核对时发现不一致的本地时间
+    pub since: DateTime<Local>, // 上一次确认前台窗口的时间
+}
+
+// 窗口切换记录写入之后才得到的补充信息（如后台验证的数字签名），另写一行，已经写入的记录不会改写
+// 读取时按 event_id 找到对应的窗口切换记录，把 fields 合并到它的 enrichment 中；同一个字段有多条时以后写的为准
+// 记录的格式与来源无关，较慢的补充信息和其他程序都可以用它为已有的记录附加数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "kind", rename = "enrichment")]
+pub struct EnrichmentRecord {
+    pub time: DateTime<Local>, // 得到补充信息的本地时间
+    pub event_id: String,      // 被补充的窗口切换记录的 id
+    pub enricher: String,      // 补充信息的来源，如 signer
+    pub fields: BTreeMap<String, serde_json::Value>, // 补充的字段，如 {"signature": "valid", "signer": "Microsoft Corporation"}
+}
+
+// 状态心跳模式下代替窗口切换记录的一行：当前前台窗口的状态，窗口切换时立即发送，之后按固定间隔重复发送
+// 与上一条状态相同、且间隔不超过 merge_ms 的状态心跳应合并为一段；超过 merge_ms 没有收到时，
+// 上一段在上一条的时间加 merge_ms 处结束，因此漏掉个别记录不影响统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "kind", rename = "pulse")]
+pub struct PulseRecord {
+    #[serde(flatten)]
+    pub state: FocusEvent, // 当前前台窗口，time 为发送这条状态心跳的时间
+    pub merge_ms: u64,     // 合并窗口（毫秒）
+}
+
+// 当前平台和配置下某一项数据能否提供
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Capability {
+    pub available: bool,      // 是否会出现在记录中
+    pub note: Option<String>, // 不能提供或只能部分提供的原因
+}
+
+// 当前平台和配置下各项数据的提供情况，跨平台的使用方据此调整，而不是面对静默为空的字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Capabilities {
+    pub platform: String,                     // 运行平台，如 "windows"
+    pub backend: String,                      // 发现前台窗口切换的方式，如 "poll"
+    pub fields: BTreeMap<String, Capability>, // 记录中的字段名 -> 提供情况
+}